@@ -0,0 +1,7 @@
+CREATE TABLE artifacts (
+    id INTEGER PRIMARY KEY,
+    execution INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    FOREIGN KEY(execution) REFERENCES executions(id)
+);
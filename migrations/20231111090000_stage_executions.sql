@@ -0,0 +1,13 @@
+ALTER TABLE executions ADD COLUMN triggered_by TEXT NOT NULL DEFAULT 'manual';
+ALTER TABLE executions ADD COLUMN commit_hash TEXT;
+
+CREATE TABLE stage_executions (
+    id INTEGER PRIMARY KEY,
+    execution INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    status INTEGER,
+    start_time INTEGER,
+    end_time INTEGER,
+    FOREIGN KEY(execution) REFERENCES executions(id)
+);
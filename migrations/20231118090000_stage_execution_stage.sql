@@ -0,0 +1 @@
+ALTER TABLE stage_executions ADD COLUMN stage INTEGER REFERENCES stages(id);
@@ -0,0 +1,4 @@
+ALTER TABLE executions ADD COLUMN git_ref TEXT;
+
+CREATE INDEX executions_job ON executions(job, id);
+CREATE INDEX stage_executions_execution ON stage_executions(execution, position);
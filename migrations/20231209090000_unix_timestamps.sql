@@ -0,0 +1,4 @@
+UPDATE executions SET start_time = unixepoch(start_time) WHERE typeof(start_time) = 'text';
+UPDATE executions SET end_time = unixepoch(end_time) WHERE typeof(end_time) = 'text';
+UPDATE stage_executions SET start_time = unixepoch(start_time) WHERE typeof(start_time) = 'text';
+UPDATE stage_executions SET end_time = unixepoch(end_time) WHERE typeof(end_time) = 'text';
@@ -0,0 +1,12 @@
+CREATE TABLE api_tokens (
+    id INTEGER PRIMARY KEY,
+    token_hash TEXT NOT NULL UNIQUE,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    service BOOLEAN NOT NULL DEFAULT FALSE,
+    scope TEXT NOT NULL,
+    jobs TEXT NOT NULL DEFAULT '[]',
+    created_at INTEGER NOT NULL,
+    last_used INTEGER,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
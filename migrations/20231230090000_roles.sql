@@ -0,0 +1,17 @@
+CREATE TABLE project_groups (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+ALTER TABLE jobs ADD COLUMN group_id INTEGER REFERENCES project_groups(id);
+
+CREATE TABLE grants (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    job_id INTEGER,
+    group_id INTEGER,
+    role TEXT NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id),
+    FOREIGN KEY(job_id) REFERENCES jobs(id),
+    FOREIGN KEY(group_id) REFERENCES project_groups(id)
+);
@@ -0,0 +1,19 @@
+CREATE TABLE webhooks (
+    id INTEGER PRIMARY KEY,
+    job_id INTEGER,
+    url TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+CREATE TABLE webhook_deliveries (
+    id INTEGER PRIMARY KEY,
+    webhook_id INTEGER NOT NULL,
+    execution_id INTEGER,
+    attempts INTEGER NOT NULL,
+    status_code INTEGER,
+    error TEXT,
+    delivered_at INTEGER NOT NULL,
+    FOREIGN KEY(webhook_id) REFERENCES webhooks(id),
+    FOREIGN KEY(execution_id) REFERENCES executions(id)
+);
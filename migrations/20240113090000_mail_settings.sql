@@ -0,0 +1,3 @@
+ALTER TABLE jobs ADD COLUMN mail_recipients TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE jobs ADD COLUMN mail_commit_author BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE jobs ADD COLUMN mail_every_run BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,78 @@
+CREATE TABLE jobs (
+    id BIGSERIAL PRIMARY KEY,
+    source TEXT NOT NULL
+);
+
+CREATE TABLE executions (
+    id BIGSERIAL PRIMARY KEY,
+    job BIGINT NOT NULL REFERENCES jobs(id),
+    status BIGINT,
+    start_time TIMESTAMPTZ,
+    end_time TIMESTAMPTZ,
+    triggered_by TEXT NOT NULL DEFAULT 'manual',
+    commit_hash TEXT,
+    git_ref TEXT
+);
+
+CREATE INDEX executions_job ON executions(job, id);
+
+CREATE TABLE stages (
+    id BIGSERIAL PRIMARY KEY,
+    dependency BIGINT REFERENCES stages(id),
+    job BIGINT NOT NULL REFERENCES jobs(id),
+    definition TEXT NOT NULL
+);
+
+CREATE TABLE templates (
+    name TEXT NOT NULL,
+    definition TEXT NOT NULL
+);
+
+CREATE TABLE artifacts (
+    id BIGSERIAL PRIMARY KEY,
+    execution BIGINT NOT NULL REFERENCES executions(id),
+    path TEXT NOT NULL,
+    size BIGINT NOT NULL
+);
+
+CREATE TABLE stage_executions (
+    id BIGSERIAL PRIMARY KEY,
+    execution BIGINT NOT NULL REFERENCES executions(id),
+    stage BIGINT REFERENCES stages(id),
+    position BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    status BIGINT,
+    start_time TIMESTAMPTZ,
+    end_time TIMESTAMPTZ
+);
+
+CREATE INDEX stage_executions_execution ON stage_executions(execution, position);
+
+INSERT INTO templates (
+    name,
+    definition
+) VALUES (
+    'sqlx',
+    '{
+        "name": "sqlx",
+        "image": "ghcr.io/musergi/sqlx-setup:latest",
+        "environment": [
+            {
+                "name": "DATABASE_URL",
+                "value": "sqlite:testing.db"
+            }
+        ]
+    }'
+), (
+    'build',
+    '{
+        "name": "cargo-build",
+        "image": "cargo-build:latest",
+        "environment": [
+            {
+                "name": "DATABASE_URL",
+                "value": "sqlite:testing.db"
+            }
+        ]
+    }'
+);
@@ -0,0 +1,11 @@
+CREATE TABLE api_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    token_hash TEXT NOT NULL UNIQUE,
+    user_id BIGINT NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    service BOOLEAN NOT NULL DEFAULT FALSE,
+    scope TEXT NOT NULL,
+    jobs TEXT NOT NULL DEFAULT '[]',
+    created_at TIMESTAMPTZ NOT NULL,
+    last_used TIMESTAMPTZ
+);
@@ -0,0 +1,14 @@
+CREATE TABLE project_groups (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+ALTER TABLE jobs ADD COLUMN group_id BIGINT REFERENCES project_groups(id);
+
+CREATE TABLE grants (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id),
+    job_id BIGINT REFERENCES jobs(id),
+    group_id BIGINT REFERENCES project_groups(id),
+    role TEXT NOT NULL
+);
@@ -0,0 +1,16 @@
+CREATE TABLE webhooks (
+    id BIGSERIAL PRIMARY KEY,
+    job_id BIGINT REFERENCES jobs(id),
+    url TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id BIGINT NOT NULL REFERENCES webhooks(id),
+    execution_id BIGINT REFERENCES executions(id),
+    attempts BIGINT NOT NULL,
+    status_code BIGINT,
+    error TEXT,
+    delivered_at TIMESTAMPTZ NOT NULL
+);
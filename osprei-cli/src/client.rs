@@ -0,0 +1,151 @@
+use osprei_data::api::{
+    ExecutionDetail, ExecutionList, Job, NewJob, NewStage, Stage, Started, Trigger,
+};
+use osprei_data::ErrorCode;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::{self, HeaderMap, HeaderValue};
+
+/// Client of the REST API of an osprei server.
+pub struct Client {
+    base: String,
+    http: reqwest::blocking::Client,
+}
+
+/// Filter of `Client::executions`.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct ExecutionQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+impl Client {
+    /// Client sending the API token, when given, as bearer with every request.
+    pub fn new(server: &str, token: Option<&str>) -> Result<Client, Error> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {token}"))
+                .map_err(|_| Error::InvalidToken)?;
+            value.set_sensitive(true);
+            headers.insert(header::AUTHORIZATION, value);
+        }
+        Ok(Client {
+            base: format!("{}/api/v1", server.trim_end_matches('/')),
+            http: reqwest::blocking::Client::builder()
+                .default_headers(headers)
+                .build()?,
+        })
+    }
+
+    pub fn jobs(&self) -> Result<Vec<Job>, Error> {
+        self.send(self.http.get(self.url("/jobs")))
+    }
+
+    pub fn create_job(&self, source: String) -> Result<Job, Error> {
+        self.send(self.http.post(self.url("/jobs")).json(&NewJob { source }))
+    }
+
+    pub fn stages(&self, job: i64) -> Result<Vec<Stage>, Error> {
+        self.send(self.http.get(self.url(&format!("/jobs/{job}/stages"))))
+    }
+
+    pub fn create_stage(&self, job: i64, stage: &NewStage) -> Result<Stage, Error> {
+        let url = self.url(&format!("/jobs/{job}/stages"));
+        self.send(self.http.post(url).json(stage))
+    }
+
+    pub fn trigger(&self, job: i64, git_ref: Option<String>) -> Result<Started, Error> {
+        let url = self.url(&format!("/jobs/{job}/executions"));
+        self.send(self.http.post(url).json(&Trigger { git_ref }))
+    }
+
+    pub fn executions(&self, query: &ExecutionQuery) -> Result<ExecutionList, Error> {
+        self.send(self.http.get(self.url("/executions")).query(query))
+    }
+
+    pub fn execution(&self, id: i64) -> Result<ExecutionDetail, Error> {
+        self.send(self.http.get(self.url(&format!("/executions/{id}"))))
+    }
+
+    /// Log of a stage execution past the first `offset` bytes.
+    pub fn log(&self, stage_execution: i64, offset: u64) -> Result<Vec<u8>, Error> {
+        let url = self.url(&format!("/stage-executions/{stage_execution}/log"));
+        let response = check(self.http.get(url).query(&[("offset", offset)]).send()?)?;
+        Ok(response.bytes()?.to_vec())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base)
+    }
+
+    fn send<T: serde::de::DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, Error> {
+        Ok(check(request.send()?)?.json()?)
+    }
+}
+
+/// Turns error responses into `Error::Api`.
+fn check(response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().unwrap_or_default();
+    let error = serde_json::from_str::<ApiError>(&text).unwrap_or(ApiError {
+        code: None,
+        message: text,
+    });
+    Err(Error::Api {
+        status: status.as_u16(),
+        code: error.code,
+        message: error.message,
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct ApiError {
+    code: Option<ErrorCode>,
+    message: String,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    /// The server answered with an error status.
+    Api {
+        status: u16,
+        code: Option<ErrorCode>,
+        message: String,
+    },
+    /// The token cannot be sent in a header.
+    InvalidToken,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Http(err) => write!(f, "http: {}", err),
+            Error::Api {
+                status,
+                code: Some(code),
+                message,
+            } => write!(f, "{} ({}, {})", message, code.key(), status),
+            Error::Api {
+                status, message, ..
+            } => write!(f, "{} ({})", message, status),
+            Error::InvalidToken => write!(f, "invalid token"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Error {
+        Error::Http(value)
+    }
+}
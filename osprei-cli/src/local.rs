@@ -0,0 +1,112 @@
+use osprei_data::StageDefinition;
+use osprei_execution::{Event, Output, Settings};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const TAIL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Runs the stages with the current directory as the checkout, printing their
+/// output.
+///
+/// Returns whether every stage succeeded.
+pub fn run(
+    settings: Settings,
+    stages: Vec<StageDefinition>,
+    output_dir: &Path,
+) -> Result<bool, osprei_execution::Error> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(execute(settings, stages, output_dir))
+}
+
+async fn execute(
+    settings: Settings,
+    stages: Vec<StageDefinition>,
+    output_dir: &Path,
+) -> Result<bool, osprei_execution::Error> {
+    let names: Vec<String> = stages.iter().map(|stage| stage.name.clone()).collect();
+    let output = Output {
+        artifact_dir: output_dir.join("artifacts"),
+        log_dir: output_dir.join("logs"),
+    };
+    let log_dir = output.log_dir.clone();
+    let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let execution = osprei_execution::execute(&settings, stages, output, sender);
+    tokio::pin!(execution);
+    let mut tail = None;
+    let result = loop {
+        tokio::select! {
+            result = &mut execution => break result,
+            Some(event) = events.recv() => {
+                report(event, &names, &log_dir, &mut tail)?;
+            }
+            _ = tokio::time::sleep(TAIL_INTERVAL) => {
+                if let Some(tail) = &mut tail {
+                    tail.print()?;
+                }
+            }
+        }
+    };
+    while let Ok(event) = events.try_recv() {
+        report(event, &names, &log_dir, &mut tail)?;
+    }
+    match result {
+        Ok(()) => Ok(true),
+        Err(osprei_execution::Error::Execution | osprei_execution::Error::TimedOut) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+fn report(
+    event: Event,
+    names: &[String],
+    log_dir: &Path,
+    tail: &mut Option<Tail>,
+) -> std::io::Result<()> {
+    match event {
+        Event::Commit(commit) => println!("==> commit {commit}"),
+        Event::Author(author) => println!("==> author {author}"),
+        Event::StageStarted(index) => {
+            println!("==> {} <==", names[index]);
+            *tail = Some(Tail::new(log_dir.join(format!("{index}.log"))));
+        }
+        Event::StageFinished(index, success) => {
+            if let Some(mut tail) = tail.take() {
+                tail.print()?;
+            }
+            let status = if success { "success" } else { "failure" };
+            println!("==> {}: {status}", names[index]);
+        }
+    }
+    Ok(())
+}
+
+/// Prints what was appended to a log file since the last call.
+struct Tail {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl Tail {
+    fn new(path: PathBuf) -> Tail {
+        Tail { path, offset: 0 }
+    }
+
+    fn print(&mut self) -> std::io::Result<()> {
+        let mut file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            // The log is only created once the container started
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        self.offset += content.len() as u64;
+        let mut stdout = std::io::stdout();
+        stdout.write_all(&content)?;
+        stdout.flush()
+    }
+}
@@ -0,0 +1,331 @@
+use clap::{Parser, Subcommand};
+use osprei_data::api::{ExecutionDetail, NewStage};
+use osprei_data::ExecutionStatus;
+use std::collections::{hash_map::Entry, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+mod client;
+mod local;
+mod pipeline;
+
+use client::{Client, ExecutionQuery};
+use pipeline::{Pipeline, CHECKOUT_STAGE};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Command-line client of an osprei server.
+#[derive(Parser)]
+#[command(name = "osprei", version)]
+struct Cli {
+    /// Url of the osprei server.
+    #[arg(long, env = "OSPREI_SERVER", default_value = "http://localhost:3000")]
+    server: String,
+    /// API token sent as bearer, created on the API tokens page of the server.
+    #[arg(long, env = "OSPREI_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists the jobs and the status of their last execution.
+    Jobs,
+    /// Lists the latest executions.
+    Executions {
+        #[arg(long)]
+        job: Option<i64>,
+        /// Status key, such as `failure` or `running`.
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long = "ref")]
+        git_ref: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Triggers a job and waits for it, failing unless the execution succeeds.
+    Run {
+        job: i64,
+        /// Branch, tag or commit to check out.
+        #[arg(long = "ref")]
+        git_ref: Option<String>,
+        /// Returns once the execution is started.
+        #[arg(long)]
+        detach: bool,
+    },
+    /// Prints the logs of an execution.
+    Logs {
+        execution: i64,
+        /// Only prints the log of this stage.
+        #[arg(long)]
+        stage: Option<String>,
+        /// Keeps printing until the execution finishes.
+        #[arg(long, short)]
+        follow: bool,
+    },
+    /// Creates a job and its stages from a JSON or YAML pipeline file.
+    Create {
+        file: PathBuf,
+        /// Adds the stages to this job instead of creating one from the file
+        /// `source`.
+        #[arg(long)]
+        job: Option<i64>,
+    },
+    /// Runs a pipeline with the current directory as the checkout, without a
+    /// server.
+    Local {
+        /// Pipeline file to run.
+        #[arg(required_unless_present = "job", conflicts_with = "job")]
+        file: Option<PathBuf>,
+        /// Runs the stages of this job, exported from the server, instead of a
+        /// file.
+        #[arg(long)]
+        job: Option<i64>,
+        /// Docker endpoint, `unix://` socket or `tcp://` address.
+        #[arg(long, env = "DOCKER_HOST", default_value = osprei_execution::DEFAULT_DOCKER_URL)]
+        docker_url: String,
+        /// Directory receiving the logs and artifacts, a temporary one when not
+        /// set.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let client = match Client::new(&cli.server, cli.token.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let result = match cli.command {
+        Command::Jobs => jobs(&client),
+        Command::Executions {
+            job,
+            status,
+            git_ref,
+            limit,
+        } => executions(
+            &client,
+            ExecutionQuery {
+                job,
+                status,
+                git_ref,
+                limit: Some(limit),
+            },
+        ),
+        Command::Run {
+            job,
+            git_ref,
+            detach,
+        } => run(&client, job, git_ref, detach),
+        Command::Logs {
+            execution,
+            stage,
+            follow,
+        } => logs(&client, execution, stage.as_deref(), follow).map(|_| ExitCode::SUCCESS),
+        Command::Create { file, job } => create(&client, &file, job),
+        Command::Local {
+            file,
+            job,
+            docker_url,
+            output,
+        } => run_local(&client, file.as_deref(), job, docker_url, output),
+    };
+    result.unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        ExitCode::from(2)
+    })
+}
+
+fn jobs(client: &Client) -> Result<ExitCode> {
+    for job in client.jobs()? {
+        let status = job.status.map(ExecutionStatus::key).unwrap_or("never run");
+        println!("{:>6}  {:<12}  {}", job.id, status, job.source);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn executions(client: &Client, query: ExecutionQuery) -> Result<ExitCode> {
+    let list = client.executions(&query)?;
+    for execution in &list.executions {
+        println!(
+            "{:>6}  job {:<4}  {:<12}  {:<16}  {}",
+            execution.id,
+            execution.job,
+            execution.status.key(),
+            execution.git_ref.as_deref().unwrap_or("-"),
+            execution
+                .duration
+                .map(|duration| format!("{duration}s"))
+                .unwrap_or_default(),
+        );
+    }
+    println!("{} of {} executions", list.executions.len(), list.total);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run(client: &Client, job: i64, git_ref: Option<String>, detach: bool) -> Result<ExitCode> {
+    let execution = client.trigger(job, git_ref)?.execution;
+    println!("started execution {execution}");
+    if detach {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mut reported = HashMap::new();
+    loop {
+        let detail = client.execution(execution)?;
+        for stage in &detail.stages {
+            if reported.get(&stage.id) != Some(&stage.status) {
+                // Stages have no status until they start
+                let status = stage.status.map(ExecutionStatus::key).unwrap_or("pending");
+                println!("  {:<24} {}", stage.name, status);
+                reported.insert(stage.id, stage.status);
+            }
+        }
+        let status = detail.execution.status;
+        if status.is_finished() {
+            println!("execution {execution}: {}", status.key());
+            return Ok(if status == ExecutionStatus::Success {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            });
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn logs(client: &Client, execution: i64, stage: Option<&str>, follow: bool) -> Result<()> {
+    let mut offsets: HashMap<i64, u64> = HashMap::new();
+    let mut stdout = std::io::stdout();
+    loop {
+        let detail = client.execution(execution)?;
+        if let Some(name) = stage {
+            if !detail.stages.iter().any(|stage| stage.name == name) && !follow {
+                return Err(format!("execution {execution} has no stage {name:?}").into());
+            }
+        }
+        let finished = detail.execution.status.is_finished();
+        for stage_execution in selected(&detail, stage) {
+            // The header is printed once, when the stage is first seen
+            let offset = match offsets.entry(stage_execution.id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    if stage.is_none() {
+                        writeln!(stdout, "==> {} <==", stage_execution.name)?;
+                    }
+                    entry.insert(0)
+                }
+            };
+            let log = client.log(stage_execution.id, *offset)?;
+            stdout.write_all(&log)?;
+            stdout.flush()?;
+            *offset += log.len() as u64;
+        }
+        if !follow || finished {
+            return Ok(());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn selected<'a>(
+    detail: &'a ExecutionDetail,
+    stage: Option<&'a str>,
+) -> impl Iterator<Item = &'a osprei_data::api::StageExecution> {
+    detail
+        .stages
+        .iter()
+        .filter(move |stage_execution| stage.is_none_or(|name| stage_execution.name == name))
+}
+
+fn create(client: &Client, file: &Path, job: Option<i64>) -> Result<ExitCode> {
+    let pipeline = Pipeline::read(file)?;
+    let job = match (job, &pipeline.source) {
+        (Some(job), _) => job,
+        (None, Some(source)) => {
+            // Checked first so that an invalid file leaves no job behind
+            pipeline.validate(&[CHECKOUT_STAGE.to_string()])?;
+            let job = client.create_job(source.clone())?.id;
+            println!("created job {job}");
+            job
+        }
+        (None, None) => return Err("the file has no source, pass --job to extend a job".into()),
+    };
+    let existing = client.stages(job)?;
+    let names: Vec<String> = existing
+        .iter()
+        .map(|stage| stage.definition.name.clone())
+        .collect();
+    pipeline.validate(&names)?;
+    let mut ids: HashMap<String, i64> = existing
+        .iter()
+        .map(|stage| (stage.definition.name.clone(), stage.id))
+        .collect();
+    let last = existing.last().map(|stage| stage.definition.name.as_str());
+    for (stage, dependency) in pipeline.stages.iter().zip(pipeline.dependencies(last)) {
+        let dependency = dependency
+            .and_then(|name| ids.get(&name).copied())
+            .ok_or_else(|| format!("stage {:?} has no stage to depend on", stage.name))?;
+        let created = client.create_stage(
+            job,
+            &NewStage {
+                dependency,
+                definition: stage.definition(),
+            },
+        )?;
+        println!("created stage {} ({})", created.id, stage.name);
+        ids.insert(stage.name.clone(), created.id);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_local(
+    client: &Client,
+    file: Option<&Path>,
+    job: Option<i64>,
+    docker_url: String,
+    output: Option<PathBuf>,
+) -> Result<ExitCode> {
+    let stages = match (file, job) {
+        (Some(file), _) => {
+            let pipeline = Pipeline::read(file)?;
+            pipeline.validate(&[])?;
+            pipeline
+                .stages
+                .iter()
+                .map(|stage| stage.definition())
+                .collect()
+        }
+        // The root stage of a job is its checkout, replaced by the current directory
+        (None, Some(job)) => client
+            .stages(job)?
+            .into_iter()
+            .filter(|stage| stage.dependency.is_some())
+            .map(|stage| stage.definition)
+            .collect(),
+        (None, None) => return Err("pass a pipeline file or --job".into()),
+    };
+    let settings = osprei_execution::Settings {
+        docker_url,
+        code_dir: Some(std::env::current_dir()?.canonicalize()?),
+        ..Default::default()
+    };
+    let output = output.unwrap_or_else(|| {
+        std::env::temp_dir().join(format!("osprei-local-{}", std::process::id()))
+    });
+    let success = local::run(settings, stages, &output)?;
+    println!("logs and artifacts in {}", output.display());
+    Ok(if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,238 @@
+use osprei_data::{EnvironmentVariable, StageDefinition};
+use std::path::{Path, PathBuf};
+
+/// Stage every new job starts with, checking out its source.
+pub const CHECKOUT_STAGE: &str = "checkout";
+
+/// Job and stages described in a JSON or YAML file.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    /// Git url of a new job, the stages are added to an existing job when
+    /// absent.
+    #[serde(default)]
+    pub source: Option<String>,
+    pub stages: Vec<PipelineStage>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineStage {
+    pub name: String,
+    pub image: String,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default)]
+    pub environment: Vec<EnvironmentVariable>,
+    #[serde(default = "default_working_dir")]
+    pub working_dir: String,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    /// Name of the stage this one runs after, the previous stage of the file
+    /// when absent.
+    #[serde(default)]
+    pub depends_on: Option<String>,
+}
+
+fn default_working_dir() -> String {
+    "/workspace/code".to_string()
+}
+
+impl PipelineStage {
+    pub fn definition(&self) -> StageDefinition {
+        StageDefinition {
+            name: self.name.clone(),
+            image: self.image.clone(),
+            command: self.command.clone(),
+            arguments: self.arguments.clone(),
+            environment: self.environment.clone(),
+            working_dir: self.working_dir.clone(),
+            artifacts: self.artifacts.clone(),
+        }
+    }
+}
+
+impl Pipeline {
+    /// Parses the file as YAML when it has a `.yaml` or `.yml` extension, as
+    /// JSON otherwise.
+    pub fn read(path: &Path) -> Result<Pipeline, Error> {
+        let content =
+            std::fs::read_to_string(path).map_err(|err| Error::Io(path.to_path_buf(), err))?;
+        let pipeline: Pipeline = if path
+            .extension()
+            .is_some_and(|extension| extension == "yaml" || extension == "yml")
+        {
+            serde_yaml::from_str(&content)?
+        } else {
+            serde_json::from_str(&content)?
+        };
+        Ok(pipeline)
+    }
+
+    /// Checks every stage definition and that dependencies name an earlier
+    /// stage or one of the `existing` stages of the job.
+    pub fn validate(&self, existing: &[String]) -> Result<(), Error> {
+        let mut problems = Vec::new();
+        if self.stages.is_empty() {
+            problems.push("the pipeline has no stages".to_string());
+        }
+        for (index, stage) in self.stages.iter().enumerate() {
+            for error in stage.definition().validate() {
+                problems.push(format!("stage {:?}: {}", stage.name, error));
+            }
+            if let Some(dependency) = &stage.depends_on {
+                let earlier = self.stages[..index]
+                    .iter()
+                    .any(|earlier| &earlier.name == dependency);
+                if !earlier && !existing.contains(dependency) {
+                    problems.push(format!(
+                        "stage {:?}: depends on unknown stage {:?}",
+                        stage.name, dependency
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Invalid(problems))
+        }
+    }
+
+    /// Name of the dependency of each stage, `last` for the first stage without
+    /// one.
+    pub fn dependencies(&self, last: Option<&str>) -> Vec<Option<String>> {
+        let mut previous = last.map(str::to_string);
+        self.stages
+            .iter()
+            .map(|stage| {
+                let dependency = stage.depends_on.clone().or(previous.clone());
+                previous = Some(stage.name.clone());
+                dependency
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, std::io::Error),
+    Json(serde_json::Error),
+    Yaml(serde_yaml::Error),
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Io(path, err) => write!(f, "reading {}: {}", path.display(), err),
+            Error::Json(err) => write!(f, "json: {}", err),
+            Error::Yaml(err) => write!(f, "yaml: {}", err),
+            Error::Invalid(problems) => write!(f, "invalid pipeline: {}", problems.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Error {
+        Error::Json(value)
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(value: serde_yaml::Error) -> Error {
+        Error::Yaml(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_and_json_files_are_read() {
+        let directory =
+            std::env::temp_dir().join(format!("osprei-pipeline-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let yaml = directory.join("pipeline.yaml");
+        std::fs::write(
+            &yaml,
+            "source: https://github.com/user/repo\nstages:\n  - name: build\n    image: rust\n    command: cargo\n    arguments: [build]\n  - name: test\n    image: rust\n",
+        )
+        .unwrap();
+        let json = directory.join("pipeline.json");
+        std::fs::write(
+            &json,
+            r#"{"stages": [{"name": "lint", "image": "rust", "working_dir": "/src"}]}"#,
+        )
+        .unwrap();
+
+        let pipeline = Pipeline::read(&yaml).unwrap();
+        assert_eq!(
+            pipeline.source.as_deref(),
+            Some("https://github.com/user/repo")
+        );
+        assert_eq!(pipeline.stages[0].arguments, vec!["build"]);
+        assert_eq!(pipeline.stages[1].working_dir, "/workspace/code");
+        let pipeline = Pipeline::read(&json).unwrap();
+        assert_eq!(pipeline.source, None);
+        assert_eq!(pipeline.stages[0].working_dir, "/src");
+
+        std::fs::write(&json, r#"{"stages": [{"name": "", "image": "rust"}]}"#).unwrap();
+        let result = Pipeline::read(&json).unwrap().validate(&[]);
+        assert!(matches!(result, Err(Error::Invalid(_))));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn stages_depend_on_the_previous_one_by_default() {
+        let stage = |name: &str, depends_on: Option<&str>| PipelineStage {
+            name: name.to_string(),
+            image: "rust".to_string(),
+            command: None,
+            arguments: Vec::new(),
+            environment: Vec::new(),
+            working_dir: default_working_dir(),
+            artifacts: Vec::new(),
+            depends_on: depends_on.map(str::to_string),
+        };
+        let pipeline = Pipeline {
+            source: None,
+            stages: vec![
+                stage("build", None),
+                stage("test", None),
+                stage("docs", Some("build")),
+            ],
+        };
+        assert_eq!(
+            pipeline.dependencies(Some("checkout")),
+            vec![
+                Some("checkout".to_string()),
+                Some("build".to_string()),
+                Some("build".to_string())
+            ]
+        );
+        assert_eq!(pipeline.dependencies(None)[0], None);
+
+        let pipeline = Pipeline {
+            source: None,
+            stages: vec![stage("test", Some("build"))],
+        };
+        assert!(pipeline.validate(&[]).is_err());
+        assert!(pipeline.validate(&["build".to_string()]).is_ok());
+
+        // A new job can only be depended on through its checkout stage.
+        let pipeline = Pipeline {
+            source: Some("https://example.com/repo.git".to_string()),
+            stages: vec![
+                stage("test", Some(CHECKOUT_STAGE)),
+                stage("lint", Some("build")),
+            ],
+        };
+        assert!(pipeline.validate(&[CHECKOUT_STAGE.to_string()]).is_err());
+    }
+}
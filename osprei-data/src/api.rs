@@ -0,0 +1,106 @@
+//! Bodies of the REST API under `/api/v1`.
+
+use crate::{ExecutionStatus, StageDefinition};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Job {
+    pub id: i64,
+    pub source: String,
+    /// Status of the last execution, absent if it never ran.
+    pub status: Option<ExecutionStatus>,
+    pub last_execution: Option<i64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct NewJob {
+    /// Git url of the repository, checked out by the first stage.
+    pub source: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Stage {
+    pub id: i64,
+    pub dependency: Option<i64>,
+    pub definition: StageDefinition,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct NewStage {
+    /// Stage of the same job that runs before this one.
+    pub dependency: i64,
+    pub definition: StageDefinition,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Trigger {
+    /// Branch, tag or commit to check out, the default branch when absent.
+    #[serde(default)]
+    pub git_ref: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Started {
+    pub execution: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Execution {
+    pub id: i64,
+    pub job: i64,
+    pub source: String,
+    pub triggered_by: String,
+    pub git_ref: Option<String>,
+    pub commit_hash: Option<String>,
+    /// Seconds since the unix epoch.
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub status: ExecutionStatus,
+    /// Seconds, once finished.
+    pub duration: Option<i64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ExecutionList {
+    pub executions: Vec<Execution>,
+    /// Executions matching the filter, across every page.
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ExecutionDetail {
+    pub execution: Execution,
+    pub stages: Vec<StageExecution>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct StageExecution {
+    pub id: i64,
+    pub execution: i64,
+    /// Stage of the job, absent once the stage is deleted.
+    pub stage: Option<i64>,
+    pub position: i64,
+    pub name: String,
+    /// Absent until the stage starts.
+    pub status: Option<ExecutionStatus>,
+    pub duration: Option<i64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Artifact {
+    pub id: i64,
+    /// Path relative to the workspace.
+    pub path: String,
+    /// Bytes.
+    pub size: i64,
+}
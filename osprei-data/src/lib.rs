@@ -1,20 +1,381 @@
+pub mod api;
+pub mod webhook;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct StageDefinition {
     pub name: String,
     pub image: String,
+    /// Overrides the command of the image when set.
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default)]
     pub environment: Vec<EnvironmentVariable>,
     pub working_dir: String,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+impl StageDefinition {
+    /// Checks the definition can be run, returning the problems found per
+    /// field.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if self.name.trim().is_empty() {
+            errors.push(ValidationError::new("name", "name is required"));
+        }
+        if self.image.trim().is_empty() {
+            errors.push(ValidationError::new("image", "image is required"));
+        } else if self.image.contains(char::is_whitespace) {
+            errors.push(ValidationError::new(
+                "image",
+                "image cannot contain whitespace",
+            ));
+        }
+        let command_missing = self
+            .command
+            .as_ref()
+            .map(|command| command.trim().is_empty())
+            .unwrap_or(true);
+        if command_missing && !self.arguments.is_empty() {
+            errors.push(ValidationError::new(
+                "command",
+                "command is required when passing arguments",
+            ));
+        }
+        if !self.working_dir.starts_with('/') {
+            errors.push(ValidationError::new(
+                "working_dir",
+                "working directory must be an absolute path",
+            ));
+        }
+        for (index, variable) in self.environment.iter().enumerate() {
+            let field = format!("environment.{index}");
+            if !is_identifier(&variable.name) {
+                errors.push(ValidationError::new(
+                    &field,
+                    "variable names must be letters, digits or underscores and not start with a digit",
+                ));
+            } else if self.environment[..index]
+                .iter()
+                .any(|other| other.name == variable.name)
+            {
+                errors.push(ValidationError::new(&field, "variable is already defined"));
+            }
+        }
+        errors
+    }
+}
+
+/// Checks the job source is a git url, as `https://host/owner/repo.git` or
+/// `git@host:owner/repo.git`.
+pub fn validate_source(source: &str) -> Result<(), ValidationError> {
+    let invalid = |message| Err(ValidationError::new("source", message));
+    if source.trim().is_empty() {
+        return invalid("source is required");
+    }
+    if source.contains(char::is_whitespace) || source.starts_with('-') {
+        return invalid("source must be a git url");
+    }
+    let location = match source.split_once("://") {
+        Some((scheme, rest)) if is_scheme(scheme) => rest.split_once('/'),
+        Some(_) => None,
+        // `[user@]host:path`, as long as no slash comes before the colon.
+        None => source
+            .split_once(':')
+            .filter(|(host, _)| !host.contains('/')),
+    };
+    match location {
+        Some((host, path)) if !host.is_empty() && !path.trim_matches('/').is_empty() => Ok(()),
+        _ => invalid("source must be a git url, as https://host/owner/repo.git"),
+    }
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(field: &str, message: &str) -> ValidationError {
+        ValidationError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct EnvironmentVariable {
     pub name: String,
     pub value: String,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct Template {
     pub name: String,
     pub image: String,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<String>,
     pub environment: Vec<EnvironmentVariable>,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+/// State of an execution or of one of its stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ExecutionStatus {
+    Queued,
+    Running,
+    Success,
+    Failure,
+    Cancelled,
+    TimedOut,
+    /// The server stopped while it was running.
+    Interrupted,
+}
+
+impl ExecutionStatus {
+    pub const ALL: [ExecutionStatus; 7] = [
+        ExecutionStatus::Queued,
+        ExecutionStatus::Running,
+        ExecutionStatus::Success,
+        ExecutionStatus::Failure,
+        ExecutionStatus::Cancelled,
+        ExecutionStatus::TimedOut,
+        ExecutionStatus::Interrupted,
+    ];
+
+    /// Stable lowercase identifier, used in urls and style classes.
+    pub fn key(self) -> &'static str {
+        match self {
+            ExecutionStatus::Queued => "queued",
+            ExecutionStatus::Running => "running",
+            ExecutionStatus::Success => "success",
+            ExecutionStatus::Failure => "failure",
+            ExecutionStatus::Cancelled => "cancelled",
+            ExecutionStatus::TimedOut => "timed-out",
+            ExecutionStatus::Interrupted => "interrupted",
+        }
+    }
+
+    /// Whether the status can no longer change.
+    pub fn is_finished(self) -> bool {
+        !matches!(self, ExecutionStatus::Queued | ExecutionStatus::Running)
+    }
+}
+
+impl std::fmt::Display for ExecutionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let label = match self {
+            ExecutionStatus::Queued => "Queued",
+            ExecutionStatus::Running => "Running",
+            ExecutionStatus::Success => "Success",
+            ExecutionStatus::Failure => "Failure",
+            ExecutionStatus::Cancelled => "Cancelled",
+            ExecutionStatus::TimedOut => "Timed out",
+            ExecutionStatus::Interrupted => "Interrupted",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+impl std::str::FromStr for ExecutionStatus {
+    type Err = UnknownStatus;
+
+    /// Parses the key of a status.
+    fn from_str(s: &str) -> Result<ExecutionStatus, UnknownStatus> {
+        ExecutionStatus::ALL
+            .into_iter()
+            .find(|status| status.key() == s)
+            .ok_or_else(|| UnknownStatus(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownStatus(pub String);
+
+impl std::fmt::Display for UnknownStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "unknown status: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownStatus {}
+
+/// What an API token lets its bearer do, each scope includes the ones before
+/// it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub enum TokenScope {
+    /// Reading jobs, executions and logs.
+    Read,
+    /// Running jobs as well.
+    Trigger,
+    /// Creating jobs and stages as well.
+    Admin,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 3] = [TokenScope::Read, TokenScope::Trigger, TokenScope::Admin];
+
+    /// Stable identifier, matching the serialized form.
+    pub fn key(self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::Trigger => "trigger",
+            TokenScope::Admin => "admin",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<TokenScope> {
+        TokenScope::ALL.into_iter().find(|scope| scope.key() == key)
+    }
+}
+
+impl std::fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let label = match self {
+            TokenScope::Read => "Read only",
+            TokenScope::Trigger => "Trigger",
+            TokenScope::Admin => "Admin",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Role of a user on a job, granted on the job or on its project group.
+///
+/// Each role includes the ones before it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub enum Role {
+    /// Seeing the job, its executions and their logs.
+    Viewer,
+    /// Running the job as well.
+    Runner,
+    /// Editing its stages and seeing their definitions as well.
+    Maintainer,
+    /// Granting roles on it as well.
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Viewer, Role::Runner, Role::Maintainer, Role::Admin];
+
+    /// Stable identifier, matching the serialized form.
+    pub fn key(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Runner => "runner",
+            Role::Maintainer => "maintainer",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Role> {
+        Role::ALL.into_iter().find(|role| role.key() == key)
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let label = match self {
+            Role::Viewer => "Viewer",
+            Role::Runner => "Runner",
+            Role::Maintainer => "Maintainer",
+            Role::Admin => "Admin",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Stable code identifying the kind of an error reported to users and clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub enum ErrorCode {
+    NotFound,
+    /// The request has no valid session, logging in may help.
+    Unauthorized,
+    /// The user or token is not allowed to do that.
+    Forbidden,
+    /// The request was rejected, fixing its input may help.
+    Validation,
+    DockerUnavailable,
+    /// A stage uses an image the Docker daemon does not have.
+    ImageMissing,
+    Database,
+    /// Anything else, see the server logs.
+    Internal,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 8] = [
+        ErrorCode::NotFound,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::Validation,
+        ErrorCode::DockerUnavailable,
+        ErrorCode::ImageMissing,
+        ErrorCode::Database,
+        ErrorCode::Internal,
+    ];
+
+    /// Stable identifier, matching the serialized form.
+    pub fn key(self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::Validation => "validation",
+            ErrorCode::DockerUnavailable => "docker_unavailable",
+            ErrorCode::ImageMissing => "image_missing",
+            ErrorCode::Database => "database",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<ErrorCode> {
+        ErrorCode::ALL.into_iter().find(|code| code.key() == key)
+    }
 }
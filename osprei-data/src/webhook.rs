@@ -0,0 +1,41 @@
+//! Body of the notifications posted to webhooks.
+
+use crate::ExecutionStatus;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Notification {
+    pub event: Event,
+    pub job: Job,
+    /// Unset for test notifications.
+    pub execution: Option<Execution>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    ExecutionFinished,
+    /// Sent from the webhooks page to check the receiving end.
+    Test,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Job {
+    /// Unset for test notifications of webhooks of every job.
+    pub id: Option<i64>,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Execution {
+    pub id: i64,
+    /// Page of the execution in the web interface.
+    pub url: String,
+    pub status: ExecutionStatus,
+    /// Seconds from start to finish.
+    pub duration: Option<i64>,
+    pub triggered_by: String,
+    pub git_ref: Option<String>,
+    pub commit: Option<String>,
+    /// First stage that did not succeed, unset when every stage did.
+    pub failed_stage: Option<String>,
+}
@@ -1,11 +1,110 @@
+use futures_util::{StreamExt, TryStreamExt};
 use osprei_data::StageDefinition;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
 
-pub async fn execute(stages: Vec<StageDefinition>) -> Result<(), Error> {
-    let engine = Engine::new().unwrap();
+const WORKSPACE_DIR: &str = "/workspace";
+const CODE_DIR: &str = "/workspace/code";
+const GIT_DIR: &str = "/workspace/code/.git";
+pub const DEFAULT_DOCKER_URL: &str = "unix:///var/run/docker.sock";
+/// Counter of failed Docker calls, labelled by `operation`.
+pub const DOCKER_ERRORS_METRIC: &str = "osprei_docker_errors_total";
+/// Chunks of an artifact archive buffered while they are unpacked.
+const ARCHIVE_CHUNKS: usize = 8;
+
+/// Progress notifications sent while an execution runs.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Commit checked out in the workspace, sent once it is first found.
+    Commit(String),
+    /// Email of the author of that commit, sent after it when git can tell.
+    Author(String),
+    StageStarted(usize),
+    StageFinished(usize, bool),
+}
+
+/// Where the files produced by an execution are written.
+pub struct Output {
+    /// Files matching the artifact globs of each stage are copied here once the
+    /// stage finishes, keeping their path relative to the workspace.
+    pub artifact_dir: PathBuf,
+    /// The output of each stage is written to `<index>.log` while it runs.
+    pub log_dir: PathBuf,
+}
+
+/// How executions reach Docker and what each stage may use.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Docker endpoint, `unix://` socket or `tcp://` address.
+    pub docker_url: String,
+    pub limits: Limits,
+    /// Host directory bind-mounted as `/workspace/code`, in place of a
+    /// checkout.
+    pub code_dir: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            docker_url: DEFAULT_DOCKER_URL.to_string(),
+            limits: Limits::default(),
+            code_dir: None,
+        }
+    }
+}
+
+/// Resources granted to every stage, unlimited when not set.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Seconds a stage may run before it is killed.
+    pub timeout: Option<u64>,
+    /// Memory in bytes.
+    pub memory: Option<u64>,
+    pub cpus: Option<f64>,
+}
+
+/// Runs the stages in order on a shared workspace volume.
+pub async fn execute(
+    settings: &Settings,
+    stages: Vec<StageDefinition>,
+    output: Output,
+    events: UnboundedSender<Event>,
+) -> Result<(), Error> {
+    let engine = Engine::new(settings)?;
+    std::fs::create_dir_all(&output.log_dir)?;
     engine
         .with_volume(|engine, volume| async move {
-            for stage in stages {
-                if !engine.run(stage, volume.name()).await? {
+            let mut commit_found = false;
+            for (index, stage) in stages.into_iter().enumerate() {
+                let _ = events.send(Event::StageStarted(index));
+                let log = output.log_dir.join(format!("{index}.log"));
+                let result = engine
+                    .run(
+                        stage,
+                        volume.name(),
+                        &output.artifact_dir,
+                        &log,
+                        !commit_found,
+                    )
+                    .await;
+                let success = matches!(result, Ok(StageResult { success: true, .. }));
+                let _ = events.send(Event::StageFinished(index, success));
+                let StageResult {
+                    success,
+                    commit,
+                    author,
+                } = result?;
+                if let Some(commit) = commit {
+                    commit_found = true;
+                    let _ = events.send(Event::Commit(commit));
+                }
+                if let Some(author) = author {
+                    let _ = events.send(Event::Author(author));
+                }
+                if !success {
                     return Err(Error::Execution);
                 }
             }
@@ -14,17 +113,65 @@ pub async fn execute(stages: Vec<StageDefinition>) -> Result<(), Error> {
         .await
 }
 
+/// Checks that the Docker daemon answers, so executions are not started without
+/// it.
+pub async fn check(settings: &Settings) -> Result<(), Error> {
+    let engine = Engine::new(settings)?;
+    engine
+        .docker
+        .ping()
+        .await
+        .map_err(counted("ping"))
+        .map_err(Error::DockerUnavailable)?;
+    Ok(())
+}
+
+/// Versions reported by the Docker daemon.
+#[derive(Debug, Clone)]
+pub struct DockerVersion {
+    pub version: String,
+    pub api_version: String,
+}
+
+/// Checks that the Docker daemon answers, then asks for its version.
+pub async fn version(settings: &Settings) -> Result<DockerVersion, Error> {
+    check(settings).await?;
+    let engine = Engine::new(settings)?;
+    let version = engine
+        .docker
+        .version()
+        .await
+        .map_err(counted("version"))
+        .map_err(Error::DockerUnavailable)?;
+    Ok(DockerVersion {
+        version: version.version.unwrap_or_default(),
+        api_version: version.api_version.unwrap_or_default(),
+    })
+}
+
+struct StageResult {
+    success: bool,
+    commit: Option<String>,
+    author: Option<String>,
+}
+
 #[derive(Clone)]
 struct Engine {
     docker: docker_api::Docker,
+    limits: Limits,
+    code_dir: Option<PathBuf>,
 }
 
 impl Engine {
-    fn new() -> Result<Engine, Error> {
-        let docker_url = "unix:///var/run/docker.sock";
-        log::info!("Connecting docker: {}", docker_url);
-        let docker = docker_api::Docker::new(docker_url)?;
-        Ok(Engine { docker })
+    fn new(settings: &Settings) -> Result<Engine, Error> {
+        log::info!("Connecting docker: {}", settings.docker_url);
+        let docker =
+            docker_api::Docker::new(&settings.docker_url).map_err(Error::DockerUnavailable)?;
+        Ok(Engine {
+            docker,
+            limits: settings.limits.clone(),
+            code_dir: settings.code_dir.clone(),
+        })
     }
 
     async fn with_volume<F, Fut>(&self, action: F) -> Result<(), Error>
@@ -33,13 +180,21 @@ impl Engine {
         Fut: std::future::Future<Output = Result<(), Error>>,
     {
         log::info!("Creating volume");
-        let volume_ref = self.docker.volumes().create(&Default::default()).await?;
+        let volume_ref = self
+            .docker
+            .volumes()
+            .create(&Default::default())
+            .await
+            .map_err(counted("create_volume"))?;
         let volume = docker_api::Volume::new(self.docker.clone(), volume_ref.name.clone());
         log::info!("Created volume: {}", volume.name());
         let result = action(self.clone(), volume).await;
         let volume = docker_api::Volume::new(self.docker.clone(), volume_ref.name);
-        volume.delete().await?;
-        log::info!("Deleted volume: {}", volume.name());
+        // The outcome of the stages matters more than a leftover volume.
+        match volume.delete().await.map_err(counted("delete_volume")) {
+            Ok(()) => log::info!("Deleted volume: {}", volume.name()),
+            Err(err) => log::error!("Failed to delete volume {}: {err}", volume.name()),
+        }
         result
     }
 
@@ -47,53 +202,368 @@ impl Engine {
         &self,
         stage: StageDefinition,
         volume: impl std::fmt::Display,
-    ) -> Result<bool, Error> {
+        artifact_dir: &Path,
+        log: &Path,
+        detect_commit: bool,
+    ) -> Result<StageResult, Error> {
+        let artifacts = stage.artifacts;
         let env: Vec<_> = stage
             .environment
             .into_iter()
             .map(|var| format!("{}={}", var.name, var.value))
             .collect();
-        let opts = docker_api::opts::ContainerCreateOpts::builder()
-            .image(stage.image)
-            .volumes(vec![format!("{}:/workspace", volume)])
+        let image = stage.image;
+        let mut volumes = vec![format!("{}:{}", volume, WORKSPACE_DIR)];
+        if let Some(code_dir) = &self.code_dir {
+            volumes.push(format!("{}:{}", code_dir.display(), CODE_DIR));
+        }
+        let mut opts = docker_api::opts::ContainerCreateOpts::builder()
+            .image(&image)
+            .volumes(volumes.clone())
             .working_dir(stage.working_dir)
-            .env(env)
-            .build();
-        let container = self.docker.containers().create(&opts).await?;
+            .env(env);
+        if let Some(command) = stage.command {
+            opts = opts.command(std::iter::once(command).chain(stage.arguments));
+        }
+        if let Some(memory) = self.limits.memory {
+            opts = opts.memory(memory);
+        }
+        if let Some(cpus) = self.limits.cpus {
+            opts = opts.cpus(cpus);
+        }
+        let opts = opts.build();
+        let created = self.docker.containers().create(&opts).await;
+        let container = match created.map_err(counted("create_container")) {
+            Err(docker_api::Error::Fault { code, .. }) if code.as_u16() == 404 => {
+                return Err(Error::ImageMissing(image));
+            }
+            result => result?,
+        };
         log::info!("Created container: {}", container.id());
-        if let Err(err) = container.start().await {
+        if let Err(err) = container.start().await.map_err(counted("start_container")) {
             log::error!("Container failed to start: {err}");
-            let _ = container.delete().await?;
+            remove(&container).await;
             return Err(err.into());
         }
         log::info!("Started container: {}", container.id());
-        log::info!("Waiting container: {}", container.id());
-        let success = container.wait().await?.status_code == 0;
-        if success {
-            container.delete().await?;
-            log::info!("Deleted container: {}", container.id());
+        let finished = async {
+            if let Err(err) = write_logs(&container, log).await {
+                log::warn!("Failed to write logs for {}: {err}", container.id());
+            }
+            log::info!("Waiting container: {}", container.id());
+            container.wait().await.map_err(counted("wait_container"))
+        };
+        let exit = match self.limits.timeout {
+            Some(timeout) => {
+                match tokio::time::timeout(Duration::from_secs(timeout), finished).await {
+                    Ok(exit) => exit,
+                    Err(_) => {
+                        log::warn!("Container timed out: {}", container.id());
+                        let _ = container
+                            .kill(None)
+                            .await
+                            .map_err(counted("kill_container"));
+                        remove(&container).await;
+                        return Err(Error::TimedOut);
+                    }
+                }
+            }
+            None => finished.await,
+        };
+        let exit = match exit {
+            Ok(exit) => exit,
+            Err(err) => {
+                remove(&container).await;
+                return Err(err.into());
+            }
+        };
+        let success = exit.status_code == 0;
+        for pattern in artifacts.iter() {
+            if let Err(err) = collect_artifacts(&container, pattern, artifact_dir).await {
+                log::warn!("Failed to collect artifacts ({pattern}): {err}");
+            }
         }
-        Ok(success)
+        let commit = if detect_commit {
+            read_commit(&container).await
+        } else {
+            None
+        };
+        remove(&container).await;
+        let author = match commit {
+            Some(_) => self.read_author(&image, volumes).await,
+            None => None,
+        };
+        Ok(StageResult {
+            success,
+            commit,
+            author,
+        })
+    }
+
+    /// Asks git, in a container of the image that made the checkout, for the
+    /// email of the author of the commit checked out in the workspace.
+    async fn read_author(&self, image: &str, volumes: Vec<String>) -> Option<String> {
+        let opts = docker_api::opts::ContainerCreateOpts::builder()
+            .image(image)
+            .volumes(volumes)
+            .working_dir(CODE_DIR)
+            .entrypoint(["git", "-c", "safe.directory=*", "log", "-1", "--format=%ae"])
+            .build();
+        let created = self.docker.containers().create(&opts).await;
+        let container = created.map_err(counted("create_container")).ok()?;
+        let author = async {
+            container
+                .start()
+                .await
+                .map_err(counted("start_container"))
+                .ok()?;
+            let exit = container
+                .wait()
+                .await
+                .map_err(counted("wait_container"))
+                .ok()?;
+            let opts = docker_api::opts::LogsOpts::builder().stdout(true).build();
+            let output: Vec<u8> = container
+                .logs(&opts)
+                .map_ok(Vec::from)
+                .try_concat()
+                .await
+                .map_err(counted("logs"))
+                .ok()?;
+            let author = String::from_utf8_lossy(&output).trim().to_string();
+            (exit.status_code == 0 && !author.is_empty()).then_some(author)
+        }
+        .await;
+        remove(&container).await;
+        author
     }
 }
 
+/// Counts the failed Docker call under `DOCKER_ERRORS_METRIC`, passing the
+/// error on.
+fn counted(operation: &'static str) -> impl FnOnce(docker_api::Error) -> docker_api::Error {
+    move |err| {
+        metrics::increment_counter!(DOCKER_ERRORS_METRIC, "operation" => operation);
+        err
+    }
+}
+
+/// Removes the container, which keeps the workspace volume in use until then.
+async fn remove(container: &docker_api::Container) {
+    match container
+        .delete()
+        .await
+        .map_err(counted("delete_container"))
+    {
+        Ok(_) => log::info!("Deleted container: {}", container.id()),
+        Err(err) => log::warn!("Failed to delete container {}: {err}", container.id()),
+    }
+}
+
+/// Follows the container output until it exits.
+async fn write_logs(container: &docker_api::Container, path: &Path) -> Result<(), Error> {
+    let mut file = std::fs::File::create(path)?;
+    let opts = docker_api::opts::LogsOpts::builder()
+        .follow(true)
+        .stdout(true)
+        .stderr(true)
+        .build();
+    let mut logs = container.logs(&opts);
+    while let Some(chunk) = logs.next().await {
+        file.write_all(&chunk.map_err(counted("logs"))?)?;
+    }
+    Ok(())
+}
+
+async fn read_file(container: &docker_api::Container, path: &str) -> Option<String> {
+    let archive: Vec<u8> = container.copy_from(path).try_concat().await.ok()?;
+    let mut archive = tar::Archive::new(archive.as_slice());
+    let mut entry = archive.entries().ok()?.next()?.ok()?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content).ok()?;
+    Some(content)
+}
+
+/// Resolves the commit checked out in the workspace, if there is a repository.
+async fn read_commit(container: &docker_api::Container) -> Option<String> {
+    let head = read_file(container, &format!("{GIT_DIR}/HEAD")).await?;
+    let head = head.trim();
+    let Some(reference) = head.strip_prefix("ref: ") else {
+        return Some(head.to_string());
+    };
+    if let Some(commit) = read_file(container, &format!("{GIT_DIR}/{reference}")).await {
+        return Some(commit.trim().to_string());
+    }
+    read_file(container, &format!("{GIT_DIR}/packed-refs"))
+        .await?
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(_, name)| *name == reference)
+        .map(|(commit, _)| commit.to_string())
+}
+
+async fn collect_artifacts(
+    container: &docker_api::Container,
+    pattern: &str,
+    destination: &Path,
+) -> Result<(), Error> {
+    let matcher = glob::Pattern::new(pattern)?;
+    let root = pattern_root(pattern);
+    log::info!("Collecting artifacts ({pattern}) from: {}", root.display());
+    let mut archive = std::pin::pin!(container.copy_from(Path::new(WORKSPACE_DIR).join(&root)));
+    // The archive is unpacked on a blocking thread as its chunks arrive
+    let (sender, receiver) = tokio::sync::mpsc::channel(ARCHIVE_CHUNKS);
+    let destination = destination.to_path_buf();
+    let unpacked = tokio::task::spawn_blocking(move || {
+        let reader = ChunkReader {
+            receiver,
+            chunk: std::io::Cursor::new(Vec::new()),
+        };
+        unpack_artifacts(reader, &matcher, &root, &destination)
+    });
+    let forwarded = async {
+        while let Some(chunk) = archive.next().await {
+            if sender.send(chunk?).await.is_err() {
+                // Unpacking stopped on an error, reported below
+                break;
+            }
+        }
+        Ok::<(), docker_api::Error>(())
+    }
+    .await;
+    drop(sender);
+    let unpacked = unpacked.await.map_err(std::io::Error::other)?;
+    forwarded?;
+    unpacked
+}
+
+/// Copies the files of the archive matching the pattern under the destination.
+fn unpack_artifacts(
+    archive: impl Read,
+    matcher: &glob::Pattern,
+    root: &Path,
+    destination: &Path,
+) -> Result<(), Error> {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    let mut archive = tar::Archive::new(archive);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        // Entries are prefixed by the name of the copied directory itself
+        let rest: PathBuf = entry.path()?.components().skip(1).collect();
+        let path = if rest.as_os_str().is_empty() {
+            root.to_path_buf()
+        } else {
+            root.join(rest)
+        };
+        let is_safe = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !is_safe || !matcher.matches_path_with(&path, options) {
+            continue;
+        }
+        let target = destination.join(&path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&target)?;
+        log::info!("Collected artifact: {}", path.display());
+    }
+    Ok(())
+}
+
+/// Blocking reader over the chunks of an archive streamed from Docker.
+struct ChunkReader {
+    receiver: tokio::sync::mpsc::Receiver<Vec<u8>>,
+    chunk: std::io::Cursor<Vec<u8>>,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let read = self.chunk.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            match self.receiver.blocking_recv() {
+                Some(chunk) => self.chunk = std::io::Cursor::new(chunk),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Longest leading directory of the pattern without wildcards.
+fn pattern_root(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 pub enum Error {
     Docker(docker_api::Error),
+    /// The Docker daemon could not be reached.
+    DockerUnavailable(docker_api::Error),
     Execution,
+    /// A stage uses an image that is not available locally.
+    ImageMissing(String),
+    /// A stage ran longer than the timeout limit.
+    TimedOut,
+    Io(std::io::Error),
+    Pattern(glob::PatternError),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::Docker(err) => write!(f, "docker error: {err}"),
+            Error::DockerUnavailable(err) => write!(f, "docker is not available: {err}"),
             Error::Execution => write!(f, "stage failed"),
+            Error::ImageMissing(image) => write!(f, "image not found: {image}"),
+            Error::TimedOut => write!(f, "stage timed out"),
+            Error::Io(err) => write!(f, "io error: {err}"),
+            Error::Pattern(err) => write!(f, "invalid pattern: {err}"),
         }
     }
 }
 
+impl std::error::Error for Error {}
+
 impl From<docker_api::Error> for Error {
     fn from(value: docker_api::Error) -> Error {
-        Error::Docker(value)
+        use docker_api::conn::Error as Connection;
+        match value {
+            docker_api::Error::Hyper(_)
+            | docker_api::Error::IO(_)
+            | docker_api::Error::Error(Connection::Hyper(_) | Connection::IO(_)) => {
+                Error::DockerUnavailable(value)
+            }
+            value => Error::Docker(value),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Error {
+        Error::Io(value)
+    }
+}
+
+impl From<glob::PatternError> for Error {
+    fn from(value: glob::PatternError) -> Error {
+        Error::Pattern(value)
     }
 }
@@ -0,0 +1,863 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::auth::Authenticated;
+    use crate::error_template::AppError;
+    use crate::server::{missing, start_execution, Runner};
+    use crate::state::AppState;
+    use axum::{
+        extract::{Path, Query, State},
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+        Json, Router,
+    };
+    use osprei_data::{
+        validate_source, ErrorCode, ExecutionStatus, Role, StageDefinition, Template, TokenScope,
+    };
+    use osprei_storage::SharedStorage;
+    use osprei_data::api::{
+        Artifact, Execution, ExecutionDetail, ExecutionList, Job, NewJob, NewStage, Stage,
+        StageExecution, Started, Trigger,
+    };
+    use serde::Deserialize;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+    use utoipa::{IntoParams, OpenApi};
+
+    const DEFAULT_LIMIT: i64 = 20;
+    const MAX_LIMIT: i64 = 100;
+
+    /// Routes of the REST API, nested under `/api/v1`.
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route("/openapi.json", get(openapi))
+            .route("/jobs", get(list_jobs).post(create_job))
+            .route("/jobs/:id", get(get_job))
+            .route("/jobs/:id/stages", get(list_stages).post(create_stage))
+            .route("/jobs/:id/executions", axum::routing::post(trigger_job))
+            .route("/templates", get(list_templates))
+            .route("/templates/:name", get(get_template))
+            .route("/executions", get(list_executions))
+            .route("/executions/:id", get(get_execution))
+            .route("/executions/:id/artifacts", get(list_artifacts))
+            .route("/stage-executions/:id", get(get_stage_execution))
+            .route("/stage-executions/:id/log", get(get_log))
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(
+        info(
+            title = "Osprei",
+            description = "Jobs, stages and executions of an Osprei server, for a session or an \
+                API token sent as `Authorization: Bearer <token>`.",
+        ),
+        paths(
+            list_jobs,
+            create_job,
+            get_job,
+            list_stages,
+            create_stage,
+            trigger_job,
+            list_templates,
+            get_template,
+            list_executions,
+            get_execution,
+            list_artifacts,
+            get_stage_execution,
+            get_log,
+        ),
+        components(schemas(
+            AppError,
+            ErrorCode,
+            TokenScope,
+            osprei_data::EnvironmentVariable,
+            ExecutionStatus,
+            StageDefinition,
+            Template,
+            Job,
+            NewJob,
+            Stage,
+            NewStage,
+            Trigger,
+            Started,
+            Execution,
+            ExecutionList,
+            ExecutionDetail,
+            StageExecution,
+            Artifact,
+        ))
+    )]
+    pub struct ApiDoc;
+
+    impl IntoResponse for AppError {
+        fn into_response(self) -> Response {
+            (self.status_code(), Json(self)).into_response()
+        }
+    }
+
+    #[derive(Debug, Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub struct ExecutionQuery {
+        pub job: Option<i64>,
+        /// Status key, such as `success` or `timed-out`.
+        pub status: Option<String>,
+        /// Prefix of the ref or commit.
+        pub git_ref: Option<String>,
+        /// First day included, as `YYYY-MM-DD`.
+        pub since: Option<String>,
+        /// Last day included, as `YYYY-MM-DD`.
+        pub until: Option<String>,
+        /// At most 100, 20 by default.
+        pub limit: Option<i64>,
+        pub offset: Option<i64>,
+    }
+
+    #[derive(Debug, Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub struct LogQuery {
+        /// Bytes of the log to skip, to follow it while it grows.
+        pub offset: Option<u64>,
+    }
+
+    fn to_execution(value: osprei_storage::Execution) -> Execution {
+        let osprei_storage::Execution {
+            id,
+            job,
+            source,
+            triggered_by,
+            git_ref,
+            commit_hash,
+            start_time,
+            end_time,
+            status,
+            duration,
+        } = value;
+        Execution {
+            id,
+            job,
+            source,
+            triggered_by,
+            git_ref,
+            commit_hash,
+            start_time,
+            end_time,
+            status,
+            duration,
+        }
+    }
+
+    fn to_stage_execution(value: osprei_storage::StageExecution) -> StageExecution {
+        let osprei_storage::StageExecution {
+            id,
+            execution,
+            stage,
+            position,
+            name,
+            status,
+            duration,
+        } = value;
+        StageExecution {
+            id,
+            execution,
+            stage,
+            position,
+            name,
+            status,
+            duration,
+        }
+    }
+
+    async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+        Json(ApiDoc::openapi())
+    }
+
+    async fn job(storage: &SharedStorage, id: i64) -> Result<Job, AppError> {
+        let source = storage
+            .job_source(id)
+            .await
+            .map_err(missing(format!("Job {id}")))?;
+        Ok(Job {
+            id,
+            source,
+            status: storage.job_status(id).await?,
+            last_execution: storage.job_last_execution(id).await?,
+        })
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/jobs",
+        responses((status = 200, body = [Job]))
+    )]
+    async fn list_jobs(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+    ) -> Result<Json<Vec<Job>>, AppError> {
+        caller.require_scope(TokenScope::Read)?;
+        let permissions = caller.permissions(&*storage).await?;
+        let mut jobs = Vec::new();
+        for (id, group) in storage.job_groups().await? {
+            if !caller.can_access(id) || permissions.role(id, group).is_none() {
+                continue;
+            }
+            jobs.push(job(&storage, id).await?);
+        }
+        Ok(Json(jobs))
+    }
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/jobs",
+        request_body = NewJob,
+        responses(
+            (status = 201, body = Job),
+            (status = 400, body = AppError),
+        )
+    )]
+    async fn create_job(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Json(new_job): Json<NewJob>,
+    ) -> Result<(StatusCode, Json<Job>), AppError> {
+        caller.require(TokenScope::Admin, None)?;
+        caller.permissions(&*storage).await?.require_admin()?;
+        validate_source(&new_job.source).map_err(|err| AppError::validation(err.to_string()))?;
+        let id = storage.create_job(new_job.source).await?;
+        Ok((StatusCode::CREATED, Json(job(&storage, id).await?)))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/jobs/{id}",
+        params(("id" = i64, Path, description = "Job id")),
+        responses(
+            (status = 200, body = Job),
+            (status = 404, body = AppError),
+        )
+    )]
+    async fn get_job(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+    ) -> Result<Json<Job>, AppError> {
+        caller.require_role(&*storage, TokenScope::Read, id, Role::Viewer).await?;
+        Ok(Json(job(&storage, id).await?))
+    }
+
+    async fn stages(storage: &SharedStorage, job_id: i64) -> Result<Vec<Stage>, AppError> {
+        job(storage, job_id).await?;
+        let stages = storage
+            .stages_for_job(job_id)
+            .await?
+            .into_iter()
+            .map(
+                |osprei_storage::Stage {
+                     id,
+                     dependency,
+                     definition,
+                 }| Stage {
+                    id,
+                    dependency,
+                    definition,
+                },
+            )
+            .collect();
+        Ok(stages)
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/jobs/{id}/stages",
+        params(("id" = i64, Path, description = "Job id")),
+        responses(
+            (status = 200, body = [Stage]),
+            (status = 404, body = AppError),
+        )
+    )]
+    async fn list_stages(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+    ) -> Result<Json<Vec<Stage>>, AppError> {
+        // Stage definitions can hold credentials, so only maintainers see them.
+        caller.require_role(&*storage, TokenScope::Read, id, Role::Maintainer).await?;
+        Ok(Json(stages(&storage, id).await?))
+    }
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/jobs/{id}/stages",
+        params(("id" = i64, Path, description = "Job id")),
+        request_body = NewStage,
+        responses(
+            (status = 201, body = Stage),
+            (status = 400, body = AppError),
+            (status = 404, body = AppError),
+        )
+    )]
+    async fn create_stage(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+        Json(new_stage): Json<NewStage>,
+    ) -> Result<(StatusCode, Json<Stage>), AppError> {
+        caller.require_role(&*storage, TokenScope::Admin, id, Role::Maintainer).await?;
+        let existing = stages(&storage, id).await?;
+        if !existing.iter().any(|stage| stage.id == new_stage.dependency) {
+            return Err(AppError::validation(format!(
+                "dependency: stage {} is not part of job {id}",
+                new_stage.dependency
+            )));
+        }
+        let errors = new_stage.definition.validate();
+        if !errors.is_empty() {
+            let message = errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(AppError::validation(message));
+        }
+        let stage_id = storage
+            .create_stage(id, Some(new_stage.dependency), &new_stage.definition)
+            .await?;
+        let stage = Stage {
+            id: stage_id,
+            dependency: Some(new_stage.dependency),
+            definition: new_stage.definition,
+        };
+        Ok((StatusCode::CREATED, Json(stage)))
+    }
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/jobs/{id}/executions",
+        params(("id" = i64, Path, description = "Job id")),
+        request_body(content = Option<Trigger>, description = "Optional, to run a given ref"),
+        responses(
+            (status = 202, description = "The execution runs in the background", body = Started),
+            (status = 400, body = AppError),
+            (status = 404, body = AppError),
+            (status = 503, description = "Docker is not available", body = AppError),
+        )
+    )]
+    async fn trigger_job(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        State(runner): State<Runner>,
+        Path(id): Path<i64>,
+        trigger: Option<Json<Trigger>>,
+    ) -> Result<(StatusCode, Json<Started>), AppError> {
+        caller.require_role(&*storage, TokenScope::Trigger, id, Role::Runner).await?;
+        let git_ref = trigger.and_then(|Json(trigger)| trigger.git_ref);
+        if let Some(git_ref) = &git_ref {
+            if git_ref.is_empty() || git_ref.starts_with('-') || git_ref.contains(char::is_whitespace) {
+                return Err(AppError::validation(format!("git_ref: invalid ref {git_ref:?}")));
+            }
+        }
+        let execution = start_execution(runner, storage, id, "api", git_ref.as_deref()).await?;
+        Ok((StatusCode::ACCEPTED, Json(Started { execution })))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/templates",
+        responses((status = 200, description = "Template names", body = [String]))
+    )]
+    async fn list_templates(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+    ) -> Result<Json<Vec<String>>, AppError> {
+        caller.require_scope(TokenScope::Read)?;
+        caller.permissions(&*storage).await?.require_any(Role::Maintainer)?;
+        Ok(Json(storage.template_names().await?))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/templates/{name}",
+        params(("name" = String, Path, description = "Template name")),
+        responses(
+            (status = 200, body = Template),
+            (status = 404, body = AppError),
+        )
+    )]
+    async fn get_template(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(name): Path<String>,
+    ) -> Result<Json<Template>, AppError> {
+        caller.require_scope(TokenScope::Read)?;
+        caller.permissions(&*storage).await?.require_any(Role::Maintainer)?;
+        let template = storage
+            .template(&name)
+            .await
+            .map_err(missing(format!("Template {name}")))?;
+        Ok(Json(template))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/executions",
+        params(ExecutionQuery),
+        responses(
+            (status = 200, description = "Most recent first", body = ExecutionList),
+            (status = 400, body = AppError),
+        )
+    )]
+    async fn list_executions(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Query(query): Query<ExecutionQuery>,
+    ) -> Result<Json<ExecutionList>, AppError> {
+        caller.require(TokenScope::Read, query.job)?;
+        let permissions = caller.permissions(&*storage).await?;
+        let jobs = match query.job {
+            Some(job) => {
+                permissions.require(&*storage, job, Role::Viewer).await?;
+                None
+            }
+            None => permissions.visible_jobs(&*storage).await?,
+        };
+        let status = match query.status {
+            Some(status) => Some(
+                status
+                    .parse::<ExecutionStatus>()
+                    .map_err(|err| AppError::validation(err.to_string()))?,
+            ),
+            None => None,
+        };
+        let filter = osprei_storage::Filter {
+            job: query.job,
+            status,
+            git_ref: query.git_ref,
+            since: query.since,
+            until: query.until,
+            jobs,
+        };
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let offset = query.offset.unwrap_or(0).max(0);
+        let executions = storage
+            .list_executions(&filter, limit, offset)
+            .await?
+            .into_iter()
+            .map(to_execution)
+            .collect();
+        let total = storage.count_executions(&filter).await?;
+        Ok(Json(ExecutionList { executions, total }))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/executions/{id}",
+        params(("id" = i64, Path, description = "Execution id")),
+        responses(
+            (status = 200, body = ExecutionDetail),
+            (status = 404, body = AppError),
+        )
+    )]
+    async fn get_execution(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+    ) -> Result<Json<ExecutionDetail>, AppError> {
+        let execution = storage
+            .execution(id)
+            .await
+            .map_err(missing(format!("Execution {id}")))?;
+        caller.require_role(&*storage, TokenScope::Read, execution.job, Role::Viewer).await?;
+        let stages = storage
+            .stage_executions(id)
+            .await?
+            .into_iter()
+            .map(to_stage_execution)
+            .collect();
+        Ok(Json(ExecutionDetail {
+            execution: to_execution(execution),
+            stages,
+        }))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/executions/{id}/artifacts",
+        params(("id" = i64, Path, description = "Execution id")),
+        responses(
+            (status = 200, description = "Downloaded from `/artifacts/{id}`", body = [Artifact]),
+            (status = 404, body = AppError),
+        )
+    )]
+    async fn list_artifacts(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+    ) -> Result<Json<Vec<Artifact>>, AppError> {
+        let execution = storage
+            .execution(id)
+            .await
+            .map_err(missing(format!("Execution {id}")))?;
+        caller.require_role(&*storage, TokenScope::Read, execution.job, Role::Viewer).await?;
+        let artifacts = storage
+            .artifacts(id)
+            .await?
+            .into_iter()
+            .map(|osprei_storage::Artifact { id, path, size, .. }| Artifact { id, path, size })
+            .collect();
+        Ok(Json(artifacts))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/stage-executions/{id}",
+        params(("id" = i64, Path, description = "Stage execution id")),
+        responses(
+            (status = 200, body = StageExecution),
+            (status = 404, body = AppError),
+        )
+    )]
+    async fn get_stage_execution(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+    ) -> Result<Json<StageExecution>, AppError> {
+        let stage = storage
+            .stage_execution(id)
+            .await
+            .map_err(missing(format!("Stage execution {id}")))?;
+        let job = storage.execution(stage.execution).await?.job;
+        caller.require_role(&*storage, TokenScope::Read, job, Role::Viewer).await?;
+        Ok(Json(to_stage_execution(stage)))
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/stage-executions/{id}/log",
+        params(("id" = i64, Path, description = "Stage execution id"), LogQuery),
+        responses(
+            (status = 200, description = "Log written so far", content_type = "text/plain", body = String),
+            (status = 404, body = AppError),
+        )
+    )]
+    async fn get_log(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+        Query(query): Query<LogQuery>,
+    ) -> Result<Response, AppError> {
+        let stage = storage
+            .stage_execution(id)
+            .await
+            .map_err(missing(format!("Stage execution {id}")))?;
+        let job = storage.execution(stage.execution).await?.job;
+        caller.require_role(&*storage, TokenScope::Read, job, Role::Viewer).await?;
+        let location = osprei_storage::stage_execution::log_path(&stage);
+        let content = match read_from(&location, query.offset.unwrap_or(0)).await {
+            Ok(content) => content,
+            // Stages that did not start yet have no log.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                log::error!("Error reading log {}: {err}", location.display());
+                return Err(AppError::new(ErrorCode::Internal, err.to_string()));
+            }
+        };
+        Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], content).into_response())
+    }
+
+    async fn read_from(path: &std::path::Path, offset: u64) -> std::io::Result<Vec<u8>> {
+        let mut file = tokio::fs::File::open(path).await?;
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).await?;
+        Ok(content)
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{body::Body, http::Request, routing::post};
+    use osprei_storage::MemoryStorage;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// Session token of the user every request is sent as.
+    const TOKEN: &str = "session-token";
+
+    /// The API next to the server function route, as mounted by the server.
+    async fn app() -> Router {
+        app_with_storage().await.0
+    }
+
+    async fn app_with_storage() -> (Router, SharedStorage) {
+        let config = Config {
+            // Nothing listens there, so Docker is unavailable.
+            docker_url: "tcp://127.0.0.1:1".to_string(),
+            ..Config::default()
+        };
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let user = storage.create_user("user", "", true).await.unwrap();
+        let token_hash = osprei_storage::users::hash_token(TOKEN);
+        storage.create_session(&token_hash, user, 60).await.unwrap();
+        let state = AppState {
+            leptos_options: leptos::LeptosOptions::builder()
+                .output_name("osprei-gui")
+                .build(),
+            storage,
+            runner: Runner::new(&config),
+            sessions: crate::auth::Sessions::new(&config),
+        };
+        let router = Router::new()
+            .nest("/api/v1", router())
+            .route("/api/*fn_name", post(|| async { StatusCode::NO_CONTENT }))
+            .with_state(state.clone());
+        (router, state.storage)
+    }
+
+    async fn send(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(
+                header::COOKIE,
+                format!("theme=dark; osprei_session={TOKEN}"),
+            );
+        let body = match body {
+            Some(body) => Body::from(body.to_string()),
+            None => Body::empty(),
+        };
+        let response = app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+        (status, json)
+    }
+
+    #[tokio::test]
+    async fn jobs_and_stages_are_created() {
+        let app = app().await;
+        let (status, job) = send(
+            &app,
+            "POST",
+            "/api/v1/jobs",
+            Some(serde_json::json!({"source": "https://example.com/repo.git"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = job["id"].as_i64().unwrap();
+        let (status, jobs) = send(&app, "GET", "/api/v1/jobs", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(jobs[0]["source"], "https://example.com/repo.git");
+
+        let (_, stages) = send(&app, "GET", &format!("/api/v1/jobs/{id}/stages"), None).await;
+        let checkout = stages[0]["id"].as_i64().unwrap();
+        let definition = serde_json::json!({
+            "name": "test",
+            "image": "rust:latest",
+            "command": "cargo",
+            "arguments": ["test"],
+            "working_dir": "/workspace/code",
+        });
+        let uri = format!("/api/v1/jobs/{id}/stages");
+        let new_stage = serde_json::json!({"dependency": checkout, "definition": definition});
+        let (status, stage) = send(&app, "POST", &uri, Some(new_stage)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(stage["dependency"], checkout);
+
+        let mut invalid = definition.clone();
+        invalid["image"] = "".into();
+        let new_stage = serde_json::json!({"dependency": checkout, "definition": invalid});
+        let (status, error) = send(&app, "POST", &uri, Some(new_stage)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "validation");
+        let new_stage = serde_json::json!({"dependency": 999, "definition": definition});
+        let (status, _) = send(&app, "POST", &uri, Some(new_stage)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Server functions are still routed next to the API.
+        let (status, _) = send(&app, "POST", "/api/load_jobs", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn errors_have_codes_and_statuses() {
+        let app = app().await;
+        let (status, error) = send(&app, "GET", "/api/v1/jobs/42", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error["code"], "not_found");
+        assert_eq!(error["message"], "Job 42 does not exist");
+        let (status, _) = send(&app, "GET", "/api/v1/executions/42", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, "GET", "/api/v1/executions?status=bogus", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, error) = send(
+            &app,
+            "POST",
+            "/api/v1/jobs",
+            Some(serde_json::json!({"source": "source"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "validation");
+        let (_, job) = send(
+            &app,
+            "POST",
+            "/api/v1/jobs",
+            Some(serde_json::json!({"source": "git@example.com:owner/repo.git"})),
+        )
+        .await;
+        let uri = format!("/api/v1/jobs/{}/executions", job["id"]);
+        let (status, error) = send(&app, "POST", &uri, None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error["code"], "docker_unavailable");
+        let (_, executions) = send(&app, "GET", "/api/v1/executions", None).await;
+        assert_eq!(executions["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn openapi_document_lists_every_route() {
+        let (status, document) = send(&app().await, "GET", "/api/v1/openapi.json", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let paths = document["paths"].as_object().unwrap();
+        assert!(paths.contains_key("/api/v1/jobs/{id}/executions"));
+        assert!(paths.contains_key("/api/v1/stage-executions/{id}/log"));
+        assert!(document["components"]["schemas"]
+            .as_object()
+            .unwrap()
+            .contains_key("ExecutionDetail"));
+    }
+
+    #[tokio::test]
+    async fn requests_need_a_valid_session() {
+        let app = app().await;
+        for cookie in [None, Some("osprei_session=expired")] {
+            let mut request = Request::builder().uri("/api/v1/jobs");
+            if let Some(cookie) = cookie {
+                request = request.header(header::COOKIE, cookie);
+            }
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(error["code"], "unauthorized");
+        }
+    }
+
+    /// Sends the request with the token as bearer, returning the status.
+    async fn send_with_token(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        token: &str,
+        body: Option<serde_json::Value>,
+    ) -> StatusCode {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {token}"));
+        let body = match body {
+            Some(body) => Body::from(body.to_string()),
+            None => Body::empty(),
+        };
+        let response = app.clone().oneshot(request.body(body).unwrap()).await;
+        response.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn bearer_tokens_are_limited_to_their_scope_and_jobs() {
+        let (app, storage) = app_with_storage().await;
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let other = storage.create_job("other".to_string()).await.unwrap();
+        let user = storage.user_by_name("user").await.unwrap().id;
+        for (secret, scope, jobs) in [
+            ("read", TokenScope::Read, vec![]),
+            ("trigger", TokenScope::Trigger, vec![job]),
+        ] {
+            let hash = osprei_storage::users::hash_token(secret);
+            storage
+                .create_token(&hash, user, secret, false, scope, &jobs)
+                .await
+                .unwrap();
+        }
+
+        let new_job = serde_json::json!({"source": "https://example.com/repo.git"});
+        let status = send_with_token(&app, "GET", "/api/v1/jobs", "read", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let uri = format!("/api/v1/jobs/{job}/executions");
+        let status = send_with_token(&app, "POST", &uri, "read", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let status = send_with_token(&app, "POST", "/api/v1/jobs", "trigger", Some(new_job)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        // Allowed, so it only fails on Docker being unavailable.
+        let status = send_with_token(&app, "POST", &uri, "trigger", None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let uri = format!("/api/v1/jobs/{other}");
+        let status = send_with_token(&app, "GET", &uri, "trigger", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let status = send_with_token(&app, "GET", "/api/v1/executions", "trigger", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let uri = format!("/api/v1/executions?job={job}");
+        let status = send_with_token(&app, "GET", &uri, "trigger", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let status = send_with_token(&app, "GET", "/api/v1/jobs", "revoked", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let tokens = storage.tokens().await.unwrap();
+        let read = tokens.iter().find(|token| token.name == "read").unwrap();
+        assert!(read.last_used.is_some());
+    }
+
+    #[tokio::test]
+    async fn tokens_of_members_are_limited_to_their_roles() {
+        let (app, storage) = app_with_storage().await;
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        storage.create_job("other".to_string()).await.unwrap();
+        let member = storage.create_user("member", "", false).await.unwrap();
+        storage
+            .grant_role(member, Some(job), None, osprei_data::Role::Viewer)
+            .await
+            .unwrap();
+        let hash = osprei_storage::users::hash_token("member");
+        storage
+            .create_token(&hash, member, "member", false, TokenScope::Trigger, &[])
+            .await
+            .unwrap();
+
+        let request = Request::builder()
+            .uri("/api/v1/jobs")
+            .header(header::AUTHORIZATION, "Bearer member")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let jobs: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(jobs.as_array().unwrap().len(), 1);
+        assert_eq!(jobs[0]["id"], job);
+        let uri = format!("/api/v1/jobs/{job}/executions");
+        let status = send_with_token(&app, "POST", &uri, "member", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let uri = format!("/api/v1/jobs/{job}/stages");
+        let status = send_with_token(&app, "GET", &uri, "member", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let status = send_with_token(&app, "GET", "/api/v1/executions", "member", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let status = send_with_token(&app, "GET", "/api/v1/templates", "member", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
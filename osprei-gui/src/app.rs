@@ -1,5 +1,7 @@
+use crate::auth::LOGIN_PATH;
 use crate::error_template::{AppError, ErrorTemplate};
 use crate::pages::*;
+use crate::widget::UserMenu;
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
@@ -14,16 +16,24 @@ pub fn App() -> impl IntoView {
 
         <Router fallback=|| {
             let mut outside_errors = Errors::default();
-            outside_errors.insert_with_default_key(AppError::NotFound);
+            outside_errors.insert_with_default_key(AppError::not_found("Page not found"));
             view! { <ErrorTemplate outside_errors/> }.into_view()
         }>
             <header>
                 <h1>"Osprei"</h1>
+                <UserMenu/>
             </header>
             <main>
                 <Routes>
                     <Route path="" view=Home/>
-                    <Route path="/job/:id" view=Job/>
+                    <Route path=LOGIN_PATH view=Login/>
+                    // Rendered once their data is loaded, so a missing id gets a 404 status.
+                    <Route path="/job/:id" view=Job ssr=SsrMode::Async/>
+                    <Route path="/execution/:id" view=Execution ssr=SsrMode::Async/>
+                    <Route path="/stage/:id" view=Stage ssr=SsrMode::Async/>
+                    <Route path="/tokens" view=Tokens/>
+                    <Route path="/access" view=Access/>
+                    <Route path="/webhooks" view=Webhooks/>
                 </Routes>
             </main>
         </Router>
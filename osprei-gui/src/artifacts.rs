@@ -0,0 +1,98 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::auth::Authenticated;
+    use osprei_storage::SharedStorage;
+    use axum::{
+        body::StreamBody,
+        extract::{Path, State},
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+    };
+    use tokio_util::io::ReaderStream;
+
+    pub async fn download_artifact(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+    ) -> Response {
+        let artifact = match storage.artifact(id).await {
+            Ok(artifact) => artifact,
+            Err(err) => {
+                log::warn!("Artifact ({id}) not found: {err}");
+                return StatusCode::NOT_FOUND.into_response();
+            }
+        };
+        if let Err(err) = caller.require_execution(&*storage, artifact.execution).await {
+            return err.into_response();
+        }
+        let location = osprei_storage::artifacts::location(&artifact);
+        let file = match tokio::fs::File::open(&location).await {
+            Ok(file) => file,
+            Err(err) => {
+                log::error!("Error reading artifact {}: {err}", location.display());
+                return StatusCode::NOT_FOUND.into_response();
+            }
+        };
+        let name = std::path::Path::new(&artifact.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let disposition = format!("attachment; filename=\"{name}\"");
+        (
+            [
+                (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            StreamBody::new(ReaderStream::new(file)),
+        )
+            .into_response()
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::auth::Caller;
+    use osprei_storage::MemoryStorage;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn artifacts_are_streamed_from_disk() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let user = storage.create_user("admin", "", true).await.unwrap();
+        let user = storage.user(user).await.unwrap();
+        let caller = || {
+            Authenticated(Caller {
+                user: user.clone(),
+                token: None,
+            })
+        };
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let execution = storage.create_execution(job, "manual", None).await.unwrap();
+        let content = "artifact\n".repeat(10_000);
+        let files = vec![("streamed/report.txt".to_string(), content.len() as i64)];
+        storage.create_artifacts(execution, files).await.unwrap();
+        let artifact = storage.artifacts(execution).await.unwrap().remove(0);
+        let location = osprei_storage::artifacts::location(&artifact);
+        std::fs::create_dir_all(location.parent().unwrap()).unwrap();
+        std::fs::write(&location, &content).unwrap();
+
+        let response = download_artifact(caller(), State(storage.clone()), Path(artifact.id)).await;
+        std::fs::remove_file(&location).unwrap();
+        // Removes the directories made above unless other tests use them
+        for dir in location.ancestors().skip(1).take(3) {
+            let _ = std::fs::remove_dir(dir);
+        }
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"report.txt\""
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, content.as_bytes());
+
+        let response = download_artifact(caller(), State(storage), Path(artifact.id)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,259 @@
+use cfg_if::cfg_if;
+
+/// Path of the login page, the only page reachable without a session.
+pub const LOGIN_PATH: &str = "/login";
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use axum::{
+        extract::{FromRef, FromRequestParts, State},
+        http::{header, request::Parts, HeaderMap, Request},
+        middleware::Next,
+        response::{IntoResponse, Redirect, Response},
+    };
+    use osprei_data::{ErrorCode, Role, TokenScope};
+    use osprei_storage::{ApiToken, SharedStorage, Storage, User};
+    use crate::config::Config;
+    use crate::error_template::AppError;
+    use crate::permissions::Permissions;
+
+    /// Name of the cookie holding the session token.
+    pub const SESSION_COOKIE: &str = "osprei_session";
+
+    /// How logins are kept, provided to the server functions.
+    #[derive(Clone)]
+    pub struct Sessions {
+        /// Seconds a session stays valid.
+        pub lifetime: i64,
+        /// Whether cookies are only sent over https, as `public_url` is.
+        pub secure: bool,
+    }
+
+    impl Sessions {
+        pub fn new(config: &Config) -> Sessions {
+            Sessions {
+                lifetime: config.session_lifetime,
+                secure: config.public_url.starts_with("https://"),
+            }
+        }
+
+        /// `Set-Cookie` value keeping the token for the lifetime of its session.
+        pub fn cookie(&self, token: &str) -> String {
+            self.cookie_with(token, self.lifetime)
+        }
+
+        /// `Set-Cookie` value removing the session token.
+        pub fn cleared_cookie(&self) -> String {
+            self.cookie_with("", 0)
+        }
+
+        fn cookie_with(&self, token: &str, max_age: i64) -> String {
+            let secure = if self.secure { "; Secure" } else { "" };
+            format!(
+                "{SESSION_COOKIE}={token}; Path=/; Max-Age={max_age}; HttpOnly; SameSite=Lax{secure}"
+            )
+        }
+    }
+
+    /// Hash of a random password, checked when logging in as a user that does not exist so
+    /// that unknown names take as long to refuse as wrong passwords.
+    pub fn dummy_password_hash() -> &'static str {
+        static HASH: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+        HASH.get_or_init(|| {
+            osprei_storage::users::hash_password(&osprei_storage::users::new_token())
+        })
+    }
+
+    /// Session token in the cookies of the request.
+    pub fn session_token(headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == SESSION_COOKIE)
+            .map(|(_, token)| token)
+    }
+
+    /// User of the session of the request.
+    pub async fn authenticate(
+        storage: &dyn Storage,
+        headers: &HeaderMap,
+    ) -> Result<User, AppError> {
+        let token =
+            session_token(headers).ok_or_else(|| AppError::unauthorized("Log in to continue"))?;
+        let token_hash = osprei_storage::users::hash_token(token);
+        match storage.session_user(&token_hash).await {
+            Ok(user) => Ok(user),
+            Err(osprei_storage::Error::NotFound) => {
+                Err(AppError::unauthorized("The session expired, log in again"))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Creates the configured administrator unless a user already has its name.
+    pub async fn bootstrap_admin(
+        storage: &dyn Storage,
+        config: &Config,
+    ) -> Result<(), osprei_storage::Error> {
+        let Some(admin) = &config.admin else {
+            return Ok(());
+        };
+        match storage.user_by_name(&admin.username).await {
+            Ok(_) => Ok(()),
+            Err(osprei_storage::Error::NotFound) => {
+                let password_hash = osprei_storage::users::hash_password(&admin.password);
+                storage
+                    .create_user(&admin.username, &password_hash, true)
+                    .await?;
+                log::info!("Created administrator: {}", admin.username);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Token sent as `Authorization: Bearer <token>`.
+    pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+    }
+
+    /// Who sent an API request, and with which token if not with a session.
+    pub struct Caller {
+        pub user: User,
+        pub token: Option<ApiToken>,
+    }
+
+    impl Caller {
+        /// Whether the caller may see the job, tokens can be restricted to some jobs.
+        pub fn can_access(&self, job: i64) -> bool {
+            self.token
+                .as_ref()
+                .is_none_or(|token| token.jobs.is_empty() || token.jobs.contains(&job))
+        }
+
+        /// Fails with a 403 unless the caller has the scope, sessions have every scope.
+        pub fn require_scope(&self, scope: TokenScope) -> Result<(), AppError> {
+            match &self.token {
+                Some(token) if token.scope < scope => Err(AppError::forbidden(format!(
+                    "The token is limited to the {} scope",
+                    token.scope.key()
+                ))),
+                _ => Ok(()),
+            }
+        }
+
+        /// Fails with a 403 unless the caller has the scope on the job, or on every job when
+        /// `None`.
+        pub fn require(&self, scope: TokenScope, job: Option<i64>) -> Result<(), AppError> {
+            self.require_scope(scope)?;
+            let restricted = self
+                .token
+                .as_ref()
+                .is_some_and(|token| !token.jobs.is_empty());
+            match job {
+                Some(job) if !self.can_access(job) => Err(AppError::forbidden(format!(
+                    "The token does not give access to job {job}"
+                ))),
+                None if restricted => Err(AppError::forbidden(
+                    "The token only gives access to some jobs",
+                )),
+                _ => Ok(()),
+            }
+        }
+
+        /// Roles of the user behind the caller.
+        pub async fn permissions(&self, storage: &dyn Storage) -> Result<Permissions, AppError> {
+            Permissions::load(storage, &self.user).await
+        }
+
+        /// Fails with a 403 unless the caller has the scope on the job and its user the role.
+        pub async fn require_role(
+            &self,
+            storage: &dyn Storage,
+            scope: TokenScope,
+            job: i64,
+            role: Role,
+        ) -> Result<(), AppError> {
+            self.require(scope, Some(job))?;
+            self.permissions(storage).await?.require(storage, job, role).await?;
+            Ok(())
+        }
+
+        /// Fails with a 403 unless the caller can read the job of the execution.
+        pub async fn require_execution(
+            &self,
+            storage: &dyn Storage,
+            execution: i64,
+        ) -> Result<(), AppError> {
+            let job = storage.execution(execution).await?.job;
+            self.require_role(storage, TokenScope::Read, job, Role::Viewer).await
+        }
+    }
+
+    /// Caller of an API request, by its bearer token or else by its session.
+    pub async fn authenticate_caller(
+        storage: &dyn Storage,
+        headers: &HeaderMap,
+    ) -> Result<Caller, AppError> {
+        let Some(secret) = bearer_token(headers) else {
+            let user = authenticate(storage, headers).await?;
+            return Ok(Caller { user, token: None });
+        };
+        let token_hash = osprei_storage::users::hash_token(secret);
+        let token = match storage.use_token(&token_hash).await {
+            Ok(token) => token,
+            Err(osprei_storage::Error::NotFound) => {
+                return Err(AppError::unauthorized("The token is invalid or was revoked"));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let user = storage.user(token.user_id).await?;
+        Ok(Caller {
+            user,
+            token: Some(token),
+        })
+    }
+
+    /// Caller of the request, rejected with a 401 without a valid token or session.
+    pub struct Authenticated(pub Caller);
+
+    #[axum::async_trait]
+    impl<S> FromRequestParts<S> for Authenticated
+    where
+        S: Send + Sync,
+        SharedStorage: FromRef<S>,
+    {
+        type Rejection = AppError;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+            let storage = SharedStorage::from_ref(state);
+            authenticate_caller(&*storage, &parts.headers)
+                .await
+                .map(Authenticated)
+        }
+    }
+
+    /// Sends requests without a valid session to the login page.
+    pub async fn require_login<B>(
+        State(storage): State<SharedStorage>,
+        request: Request<B>,
+        next: Next<B>,
+    ) -> Response {
+        if request.uri().path() == LOGIN_PATH {
+            return next.run(request).await;
+        }
+        match authenticate(&*storage, request.headers()).await {
+            Ok(_) => next.run(request).await,
+            Err(err) if err.code == ErrorCode::Unauthorized => {
+                Redirect::to(LOGIN_PATH).into_response()
+            }
+            Err(err) => err.into_response(),
+        }
+    }
+}}
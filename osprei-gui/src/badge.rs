@@ -0,0 +1,216 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use askama::Template;
+    use axum::{
+        extract::{Path, Query, State},
+        http::{header, HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+    };
+    use osprei_data::ExecutionStatus;
+    use osprei_storage::{Filter, SharedStorage, Storage};
+
+    /// Seconds clients and proxies may reuse a badge before asking again.
+    const MAX_AGE: u32 = 60;
+
+    #[derive(serde::Deserialize)]
+    pub struct BadgeQuery {
+        /// Branch, tag or commit prefix the executions were run for, any when unset.
+        #[serde(rename = "ref")]
+        git_ref: Option<String>,
+    }
+
+    /// Badge in the style of shields.io, sized for its text in Verdana 11px.
+    #[derive(Template)]
+    #[template(path = "badge.svg")]
+    struct Badge<'a> {
+        label: &'a str,
+        message: &'a str,
+        color: &'a str,
+        label_width: u32,
+        message_width: u32,
+        width: u32,
+    }
+
+    impl<'a> Badge<'a> {
+        fn new(label: &'a str, message: &'a str, color: &'a str) -> Badge<'a> {
+            let label_width = text_width(label) + 10;
+            let message_width = text_width(message) + 10;
+            Badge {
+                label,
+                message,
+                color,
+                label_width,
+                message_width,
+                width: label_width + message_width,
+            }
+        }
+    }
+
+    /// Status of the latest execution of the job, as `/badge/job/<id>.svg`. Public, so
+    /// repositories can show it in their README.
+    pub async fn job_badge(
+        State(storage): State<SharedStorage>,
+        Path(file): Path<String>,
+        Query(query): Query<BadgeQuery>,
+        headers: HeaderMap,
+    ) -> Response {
+        let Some(id) = file.strip_suffix(".svg").and_then(|id| id.parse::<i64>().ok()) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let status = match latest_status(&*storage, id, query.git_ref).await {
+            Ok(status) => status,
+            Err(osprei_storage::Error::NotFound) => return StatusCode::NOT_FOUND.into_response(),
+            Err(err) => {
+                log::error!("Error loading job ({id}) status: {err}");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+        let (message, color) = appearance(status);
+        let etag = format!("\"{}\"", message.replace(' ', "-"));
+        let cache_control = format!("public, max-age={MAX_AGE}");
+        let not_modified = headers
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|value| value.as_bytes() == etag.as_bytes());
+        if not_modified {
+            return (
+                StatusCode::NOT_MODIFIED,
+                [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)],
+            )
+                .into_response();
+        }
+        match Badge::new("build", message, color).render() {
+            Ok(svg) => (
+                [
+                    (header::CONTENT_TYPE, "image/svg+xml".to_string()),
+                    (header::CACHE_CONTROL, cache_control),
+                    (header::ETAG, etag),
+                ],
+                svg,
+            )
+                .into_response(),
+            Err(err) => {
+                log::error!("Error rendering badge: {err}");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Status of the most recent execution of the job, of the ref when set.
+    async fn latest_status(
+        storage: &dyn Storage,
+        job_id: i64,
+        git_ref: Option<String>,
+    ) -> Result<Option<ExecutionStatus>, osprei_storage::Error> {
+        storage.job_source(job_id).await?;
+        let Some(git_ref) = git_ref.filter(|git_ref| !git_ref.is_empty()) else {
+            return storage.job_status(job_id).await;
+        };
+        let filter = Filter {
+            job: Some(job_id),
+            git_ref: Some(git_ref),
+            ..Filter::default()
+        };
+        let executions = storage.list_executions(&filter, 1, 0).await?;
+        Ok(executions.first().map(|execution| execution.status))
+    }
+
+    /// Message and color shown for the status, as shields.io shows build statuses.
+    fn appearance(status: Option<ExecutionStatus>) -> (&'static str, &'static str) {
+        match status {
+            None => ("unknown", "#9f9f9f"),
+            Some(ExecutionStatus::Success) => ("passing", "#4c1"),
+            Some(ExecutionStatus::Failure) => ("failing", "#e05d44"),
+            Some(ExecutionStatus::TimedOut) => ("timed out", "#e05d44"),
+            Some(ExecutionStatus::Interrupted) => ("interrupted", "#fe7d37"),
+            Some(ExecutionStatus::Running) => ("running", "#dfb317"),
+            Some(ExecutionStatus::Queued) => ("queued", "#9f9f9f"),
+            Some(ExecutionStatus::Cancelled) => ("cancelled", "#9f9f9f"),
+        }
+    }
+
+    /// Approximate width in pixels of the text in Verdana 11px.
+    fn text_width(text: &str) -> u32 {
+        text.chars()
+            .map(|c| match c {
+                'i' | 'j' | 'l' | '.' | ',' | ':' | '\'' | '|' => 3,
+                'f' | 'r' | 't' | ' ' | '-' | '(' | ')' => 5,
+                'm' | 'w' => 10,
+                c if c.is_ascii_uppercase() => 8,
+                _ => 7,
+            })
+            .sum()
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use osprei_storage::MemoryStorage;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get_badge(storage: &SharedStorage, uri: &str, etag: Option<&str>) -> Response {
+        let app = Router::new()
+            .route("/badge/job/:file", get(job_badge))
+            .with_state(storage.clone());
+        let mut request = Request::get(uri);
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn svg(response: Response) -> String {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn badges_show_the_latest_status_of_the_job_or_ref() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let uri = format!("/badge/job/{job}.svg");
+        let response = get_badge(&storage, &uri, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(svg(response).await.contains("build: unknown"));
+
+        let release = storage
+            .create_execution(job, "manual", Some("release"))
+            .await
+            .unwrap();
+        storage
+            .finish_execution(release, ExecutionStatus::Success)
+            .await
+            .unwrap();
+        let main = storage
+            .create_execution(job, "manual", Some("main"))
+            .await
+            .unwrap();
+        storage
+            .finish_execution(main, ExecutionStatus::Failure)
+            .await
+            .unwrap();
+
+        let response = get_badge(&storage, &uri, None).await;
+        let headers = response.headers().clone();
+        assert_eq!(headers[header::CONTENT_TYPE], "image/svg+xml");
+        assert_eq!(headers[header::CACHE_CONTROL], "public, max-age=60");
+        let badge = svg(response).await;
+        assert!(badge.starts_with("<svg"));
+        assert!(badge.contains("build: failing") && badge.contains("#e05d44"));
+        let response = get_badge(&storage, &format!("{uri}?ref=release"), None).await;
+        assert!(svg(response).await.contains("build: passing"));
+
+        let etag = headers[header::ETAG].to_str().unwrap();
+        let response = get_badge(&storage, &uri, Some(etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        for missing in ["/badge/job/42.svg", &format!("/badge/job/{job}.png")] {
+            let response = get_badge(&storage, missing, None).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{missing}");
+        }
+    }
+}
@@ -0,0 +1,545 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use osprei_execution::Limits;
+    use std::net::SocketAddr;
+    use std::path::{Path, PathBuf};
+
+    /// Server settings, read from a JSON or TOML file and overridden by the environment.
+    #[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct Config {
+        /// Address to listen on, the leptos `site-addr` when not set.
+        pub address: Option<SocketAddr>,
+        #[serde(alias = "persistance")]
+        pub database_url: String,
+        /// Directory for the logs and artifacts of executions.
+        pub data_path: PathBuf,
+        pub docker_url: String,
+        /// Executions running at once, later ones wait for a free slot.
+        pub max_concurrent_executions: usize,
+        /// Number of executions per job whose artifacts are kept.
+        pub artifact_retention: i64,
+        /// Limits applied to every stage.
+        pub stage_limits: Limits,
+        /// Seconds a login stays valid.
+        pub session_lifetime: i64,
+        /// Administrator created at startup when no user has its name.
+        pub admin: Option<Admin>,
+        /// Address users reach the server at, for links sent in notifications.
+        pub public_url: String,
+        /// Attempts at delivering a webhook notification before giving up.
+        pub webhook_attempts: u32,
+        /// Mail server for email notifications, none are sent when unset.
+        pub smtp: Option<Smtp>,
+        /// Forge the statuses of executed commits are reported to, none are when unset.
+        pub forge: Option<Forge>,
+        /// Directory holding a file per secret, named after it, as Docker and Kubernetes
+        /// mount them.
+        pub secrets_path: PathBuf,
+    }
+
+    #[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Admin {
+        pub username: String,
+        pub password: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct Smtp {
+        pub host: String,
+        pub port: u16,
+        pub security: SmtpSecurity,
+        /// Credentials, the server is used without logging in when unset.
+        pub username: Option<String>,
+        pub password: Option<String>,
+        /// Sender of the notifications, as `address` or `Name <address>`.
+        pub from: String,
+    }
+
+    impl Default for Smtp {
+        fn default() -> Smtp {
+            Smtp {
+                host: "localhost".to_string(),
+                port: 587,
+                security: SmtpSecurity::StartTls,
+                username: None,
+                password: None,
+                from: "osprei@localhost".to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct Forge {
+        /// Base of the GitHub or Gitea compatible API, such as `https://gitea.example.com/api/v1`.
+        pub api_url: String,
+        /// Secret holding the access token.
+        pub token_secret: String,
+        /// Name the statuses are reported under.
+        pub context: String,
+    }
+
+    impl Default for Forge {
+        fn default() -> Forge {
+            Forge {
+                api_url: "https://api.github.com".to_string(),
+                token_secret: "forge_token".to_string(),
+                context: "osprei".to_string(),
+            }
+        }
+    }
+
+    /// How the connection to the mail server is secured.
+    #[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum SmtpSecurity {
+        /// Plain text, only for relays on the same host or network.
+        None,
+        /// Upgraded to TLS after connecting.
+        StartTls,
+        /// TLS from the start.
+        Tls,
+    }
+
+    impl std::str::FromStr for SmtpSecurity {
+        type Err = ();
+
+        fn from_str(value: &str) -> Result<SmtpSecurity, ()> {
+            match value {
+                "none" => Ok(SmtpSecurity::None),
+                "starttls" => Ok(SmtpSecurity::StartTls),
+                "tls" => Ok(SmtpSecurity::Tls),
+                _ => Err(()),
+            }
+        }
+    }
+
+    impl Default for Config {
+        fn default() -> Config {
+            Config {
+                address: None,
+                database_url: "sqlite:osprei.db".to_string(),
+                data_path: "data".into(),
+                docker_url: osprei_execution::DEFAULT_DOCKER_URL.to_string(),
+                max_concurrent_executions: 4,
+                artifact_retention: 10,
+                stage_limits: Limits::default(),
+                session_lifetime: 7 * 24 * 60 * 60,
+                admin: None,
+                public_url: "http://localhost:3000".to_string(),
+                webhook_attempts: 3,
+                smtp: None,
+                forge: None,
+                secrets_path: "/run/secrets".into(),
+            }
+        }
+    }
+
+    impl Config {
+        /// Reads the file if any, applies the environment and validates the result.
+        pub fn load(path: Option<&Path>) -> Result<Config, Error> {
+            let mut config = match path {
+                Some(path) => Config::read(path)?,
+                None => Config::default(),
+            };
+            config.apply_env(|name| std::env::var(name).ok())?;
+            config.validate()?;
+            Ok(config)
+        }
+
+        /// Parses the file as TOML when it has a `.toml` extension, as JSON otherwise.
+        pub fn read(path: &Path) -> Result<Config, Error> {
+            let content = std::fs::read_to_string(path)
+                .map_err(|err| Error::Io(path.to_path_buf(), err))?;
+            if path.extension().is_some_and(|extension| extension == "toml") {
+                Ok(toml::from_str(&content)?)
+            } else {
+                Ok(serde_json::from_str(&content)?)
+            }
+        }
+
+        /// Overrides the settings whose variable is set.
+        pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+            if let Some(address) = var("OSPREI_ADDRESS") {
+                self.address = Some(parse("OSPREI_ADDRESS", &address)?);
+            }
+            if let Some(url) = var("DATABASE_URL") {
+                self.database_url = url;
+            }
+            if let Some((_, path)) = renamed(&var, "OSPREI_DATA_PATH", "DATA_PATH") {
+                self.data_path = path.into();
+            }
+            if let Some(url) = var("DOCKER_HOST") {
+                self.docker_url = url;
+            }
+            if let Some(value) = var("OSPREI_MAX_CONCURRENT_EXECUTIONS") {
+                self.max_concurrent_executions =
+                    parse("OSPREI_MAX_CONCURRENT_EXECUTIONS", &value)?;
+            }
+            let retention = renamed(&var, "OSPREI_ARTIFACT_RETENTION", "ARTIFACT_RETENTION");
+            if let Some((name, value)) = retention {
+                self.artifact_retention = parse(name, &value)?;
+            }
+            if let Some(value) = var("OSPREI_STAGE_TIMEOUT") {
+                self.stage_limits.timeout = Some(parse("OSPREI_STAGE_TIMEOUT", &value)?);
+            }
+            if let Some(value) = var("OSPREI_STAGE_MEMORY") {
+                self.stage_limits.memory = Some(parse("OSPREI_STAGE_MEMORY", &value)?);
+            }
+            if let Some(value) = var("OSPREI_STAGE_CPUS") {
+                self.stage_limits.cpus = Some(parse("OSPREI_STAGE_CPUS", &value)?);
+            }
+            if let Some(value) = var("OSPREI_SESSION_LIFETIME") {
+                self.session_lifetime = parse("OSPREI_SESSION_LIFETIME", &value)?;
+            }
+            if let Some(url) = var("OSPREI_PUBLIC_URL") {
+                self.public_url = url;
+            }
+            if let Some(value) = var("OSPREI_WEBHOOK_ATTEMPTS") {
+                self.webhook_attempts = parse("OSPREI_WEBHOOK_ATTEMPTS", &value)?;
+            }
+            if let Some(password) = var("OSPREI_ADMIN_PASSWORD") {
+                let username = var("OSPREI_ADMIN_USERNAME")
+                    .or_else(|| self.admin.take().map(|admin| admin.username))
+                    .unwrap_or_else(|| "admin".to_string());
+                self.admin = Some(Admin { username, password });
+            }
+            if let Some(host) = var("OSPREI_SMTP_HOST") {
+                self.smtp.get_or_insert_with(Smtp::default).host = host;
+            }
+            if let Some(smtp) = &mut self.smtp {
+                if let Some(value) = var("OSPREI_SMTP_PORT") {
+                    smtp.port = parse("OSPREI_SMTP_PORT", &value)?;
+                }
+                if let Some(value) = var("OSPREI_SMTP_SECURITY") {
+                    smtp.security = parse("OSPREI_SMTP_SECURITY", &value)?;
+                }
+                if let Some(username) = var("OSPREI_SMTP_USERNAME") {
+                    smtp.username = Some(username);
+                }
+                if let Some(password) = var("OSPREI_SMTP_PASSWORD") {
+                    smtp.password = Some(password);
+                }
+                if let Some(from) = var("OSPREI_SMTP_FROM") {
+                    smtp.from = from;
+                }
+            }
+            if let Some(url) = var("OSPREI_FORGE_API_URL") {
+                self.forge.get_or_insert_with(Forge::default).api_url = url;
+            }
+            if let Some(forge) = &mut self.forge {
+                if let Some(name) = var("OSPREI_FORGE_TOKEN_SECRET") {
+                    forge.token_secret = name;
+                }
+                if let Some(context) = var("OSPREI_FORGE_CONTEXT") {
+                    forge.context = context;
+                }
+            }
+            if let Some(path) = var("OSPREI_SECRETS_PATH") {
+                self.secrets_path = path.into();
+            }
+            Ok(())
+        }
+
+        /// Checks every setting, reporting all the problems found.
+        pub fn validate(&self) -> Result<(), Error> {
+            let mut problems = Vec::new();
+            let scheme = self.database_url.split(':').next().unwrap_or_default();
+            if !matches!(scheme, "sqlite" | "postgres" | "postgresql") {
+                problems.push(format!(
+                    "database_url must be a sqlite: or postgres:// url, got {:?}",
+                    self.database_url
+                ));
+            }
+            if self.data_path.as_os_str().is_empty() {
+                problems.push("data_path must not be empty".to_string());
+            } else if self.data_path.exists() && !self.data_path.is_dir() {
+                problems.push(format!(
+                    "data_path {} is not a directory",
+                    self.data_path.display()
+                ));
+            }
+            let docker_scheme = self.docker_url.split("://").next().unwrap_or_default();
+            if !self.docker_url.contains("://")
+                || !matches!(docker_scheme, "unix" | "tcp" | "http" | "https")
+            {
+                problems.push(format!(
+                    "docker_url must be a unix://, tcp:// or http(s):// url, got {:?}",
+                    self.docker_url
+                ));
+            }
+            if self.max_concurrent_executions == 0 {
+                problems.push("max_concurrent_executions must be at least 1".to_string());
+            }
+            if self.artifact_retention < 0 {
+                problems.push("artifact_retention must not be negative".to_string());
+            }
+            if self.stage_limits.timeout == Some(0) {
+                problems.push("stage_limits.timeout must be at least 1 second".to_string());
+            }
+            if self.stage_limits.memory == Some(0) {
+                problems.push("stage_limits.memory must be positive".to_string());
+            }
+            if self.stage_limits.cpus.is_some_and(|cpus| cpus.is_nan() || cpus <= 0.0) {
+                problems.push("stage_limits.cpus must be positive".to_string());
+            }
+            if self.session_lifetime <= 0 {
+                problems.push("session_lifetime must be at least 1 second".to_string());
+            }
+            if !self.public_url.starts_with("http://") && !self.public_url.starts_with("https://") {
+                problems.push(format!(
+                    "public_url must be an http(s):// url, got {:?}",
+                    self.public_url
+                ));
+            }
+            if self.webhook_attempts == 0 {
+                problems.push("webhook_attempts must be at least 1".to_string());
+            }
+            if let Some(admin) = &self.admin {
+                if admin.username.trim().is_empty() {
+                    problems.push("admin.username must not be empty".to_string());
+                }
+                if admin.password.len() < 8 {
+                    problems.push("admin.password must be at least 8 characters".to_string());
+                }
+            }
+            if let Some(smtp) = &self.smtp {
+                if smtp.host.trim().is_empty() {
+                    problems.push("smtp.host must not be empty".to_string());
+                }
+                if smtp.username.is_some() != smtp.password.is_some() {
+                    problems
+                        .push("smtp.username and smtp.password must be set together".to_string());
+                }
+                if smtp.from.parse::<lettre::message::Mailbox>().is_err() {
+                    problems.push(format!(
+                        "smtp.from must be an email address, got {:?}",
+                        smtp.from
+                    ));
+                }
+            }
+            if let Some(forge) = &self.forge {
+                if !forge.api_url.starts_with("http://") && !forge.api_url.starts_with("https://") {
+                    problems.push(format!(
+                        "forge.api_url must be an http(s):// url, got {:?}",
+                        forge.api_url
+                    ));
+                }
+                if !crate::secrets::is_valid_name(&forge.token_secret) {
+                    problems.push(format!(
+                        "forge.token_secret must be a secret name, got {:?}",
+                        forge.token_secret
+                    ));
+                }
+                if forge.context.trim().is_empty() {
+                    problems.push("forge.context must not be empty".to_string());
+                }
+            }
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(Error::Invalid(problems))
+            }
+        }
+
+        /// Settings handed to every execution.
+        pub fn execution_settings(&self) -> osprei_execution::Settings {
+            osprei_execution::Settings {
+                docker_url: self.docker_url.clone(),
+                limits: self.stage_limits.clone(),
+                code_dir: None,
+            }
+        }
+    }
+
+    /// Variable set under its name, or under the name it had before with a warning, along
+    /// with the name it was found under.
+    fn renamed(
+        var: impl Fn(&str) -> Option<String>,
+        name: &'static str,
+        old: &'static str,
+    ) -> Option<(&'static str, String)> {
+        if let Some(value) = var(name) {
+            return Some((name, value));
+        }
+        let value = var(old)?;
+        log::warn!("{old} is deprecated, set {name} instead");
+        Some((old, value))
+    }
+
+    fn parse<T: std::str::FromStr>(name: &'static str, value: &str) -> Result<T, Error> {
+        value
+            .parse()
+            .map_err(|_| Error::Invalid(vec![format!("{name} has an invalid value {value:?}")]))
+    }
+
+    #[derive(Debug)]
+    pub enum Error {
+        Io(PathBuf, std::io::Error),
+        Json(serde_json::Error),
+        Toml(toml::de::Error),
+        Invalid(Vec<String>),
+    }
+
+    impl std::fmt::Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                Error::Io(path, err) => write!(f, "reading {}: {}", path.display(), err),
+                Error::Json(err) => write!(f, "json: {}", err),
+                Error::Toml(err) => write!(f, "toml: {}", err),
+                Error::Invalid(problems) => {
+                    write!(f, "invalid configuration: {}", problems.join("; "))
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl From<serde_json::Error> for Error {
+        fn from(value: serde_json::Error) -> Error {
+            Error::Json(value)
+        }
+    }
+
+    impl From<toml::de::Error> for Error {
+        fn from(value: toml::de::Error) -> Error {
+            Error::Toml(value)
+        }
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn json_and_toml_files_are_read() {
+        let directory = std::env::temp_dir().join(format!("osprei-config-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let json = directory.join("config.json");
+        // Files written before the database setting was renamed still load.
+        let legacy = r#"{"address": "0.0.0.0:8080", "data_path": "/opt/osprei/var", "persistance": "sqlite::memory:"}"#;
+        std::fs::write(&json, legacy).unwrap();
+        let toml = directory.join("config.toml");
+        std::fs::write(
+            &toml,
+            "database_url = \"postgres://localhost/osprei\"\n\n[stage_limits]\ntimeout = 600\n",
+        )
+        .unwrap();
+
+        let config = Config::read(&json).unwrap();
+        assert_eq!(config.address, Some("0.0.0.0:8080".parse().unwrap()));
+        assert_eq!(config.database_url, "sqlite::memory:");
+        assert_eq!(config.data_path, PathBuf::from("/opt/osprei/var"));
+        let config = Config::read(&toml).unwrap();
+        assert_eq!(config.database_url, "postgres://localhost/osprei");
+        assert_eq!(config.stage_limits.timeout, Some(600));
+        assert_eq!(config.docker_url, osprei_execution::DEFAULT_DOCKER_URL);
+
+        std::fs::write(&json, r#"{"adress": "0.0.0.0:8080"}"#).unwrap();
+        assert!(matches!(Config::read(&json), Err(Error::Json(_))));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let mut config = Config::default();
+        config
+            .apply_env(env(&[
+                ("DATABASE_URL", "postgres://db/osprei"),
+                ("OSPREI_MAX_CONCURRENT_EXECUTIONS", "8"),
+                ("OSPREI_STAGE_CPUS", "1.5"),
+                ("OSPREI_ADMIN_PASSWORD", "correct horse"),
+                ("OSPREI_SMTP_HOST", "mail.example.com"),
+                ("OSPREI_SMTP_SECURITY", "tls"),
+                ("OSPREI_FORGE_API_URL", "http://localhost:3001/api/v1"),
+            ]))
+            .unwrap();
+        assert_eq!(config.database_url, "postgres://db/osprei");
+        assert_eq!(config.max_concurrent_executions, 8);
+        assert_eq!(config.stage_limits.cpus, Some(1.5));
+        let admin = config.admin.clone().unwrap();
+        assert_eq!(
+            (admin.username.as_str(), admin.password.as_str()),
+            ("admin", "correct horse")
+        );
+        let smtp = config.smtp.clone().unwrap();
+        assert_eq!(smtp.host, "mail.example.com");
+        assert_eq!((smtp.port, smtp.security), (587, SmtpSecurity::Tls));
+        let forge = config.forge.clone().unwrap();
+        assert_eq!(forge.api_url, "http://localhost:3001/api/v1");
+        assert_eq!(forge.token_secret, "forge_token");
+
+        let result = config.apply_env(env(&[("OSPREI_ADDRESS", "localhost")]));
+        assert!(matches!(result, Err(Error::Invalid(_))));
+
+        // The names used before the prefix are read, unless the new ones are set.
+        config
+            .apply_env(env(&[
+                ("DATA_PATH", "/srv/osprei"),
+                ("ARTIFACT_RETENTION", "3"),
+            ]))
+            .unwrap();
+        assert_eq!(config.data_path, PathBuf::from("/srv/osprei"));
+        assert_eq!(config.artifact_retention, 3);
+        config
+            .apply_env(env(&[
+                ("OSPREI_DATA_PATH", "/var/lib/osprei"),
+                ("DATA_PATH", "/srv/osprei"),
+                ("OSPREI_ARTIFACT_RETENTION", "5"),
+            ]))
+            .unwrap();
+        assert_eq!(config.data_path, PathBuf::from("/var/lib/osprei"));
+        assert_eq!(config.artifact_retention, 5);
+        let result = config.apply_env(env(&[("ARTIFACT_RETENTION", "many")]));
+        match result {
+            Err(Error::Invalid(errors)) => assert!(errors[0].starts_with("ARTIFACT_RETENTION ")),
+            _ => panic!("the invalid retention was not reported"),
+        }
+    }
+
+    #[test]
+    fn every_invalid_setting_is_reported() {
+        assert!(Config::default().validate().is_ok());
+        let config = Config {
+            database_url: "mysql://localhost".to_string(),
+            docker_url: "/var/run/docker.sock".to_string(),
+            max_concurrent_executions: 0,
+            webhook_attempts: 0,
+            stage_limits: Limits {
+                cpus: Some(-1.0),
+                ..Limits::default()
+            },
+            admin: Some(Admin {
+                username: "admin".to_string(),
+                password: "short".to_string(),
+            }),
+            smtp: Some(Smtp {
+                from: "nobody".to_string(),
+                ..Smtp::default()
+            }),
+            forge: Some(Forge {
+                token_secret: "../token".to_string(),
+                ..Forge::default()
+            }),
+            ..Config::default()
+        };
+        match config.validate() {
+            Err(Error::Invalid(problems)) => assert_eq!(problems.len(), 8),
+            result => panic!("unexpected {result:?}"),
+        }
+    }
+}
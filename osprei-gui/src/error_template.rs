@@ -1,25 +1,130 @@
 use cfg_if::cfg_if;
 use http::status::StatusCode;
 use leptos::*;
+use leptos_router::Redirect;
+use osprei_data::ErrorCode;
 use thiserror::Error;
 
 #[cfg(feature = "ssr")]
 use leptos_axum::ResponseOptions;
 
-#[derive(Clone, Debug, Error)]
-pub enum AppError {
-    #[error("Not Found")]
-    NotFound,
+/// Error reported to the user, with a stable code.
+///
+/// Server functions can only return `ServerFnError`, so it travels as a server
+/// error whose message starts with the code, and `AppError::from` recovers it
+/// on the other side.
+#[derive(Clone, Debug, PartialEq, Eq, Error, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[error("{}: {message}", code.key())]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
 }
 
 impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> AppError {
+        AppError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> AppError {
+        AppError::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> AppError {
+        AppError::new(ErrorCode::Unauthorized, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> AppError {
+        AppError::new(ErrorCode::Forbidden, message)
+    }
+
+    pub fn validation(message: impl Into<String>) -> AppError {
+        AppError::new(ErrorCode::Validation, message)
+    }
+
     pub fn status_code(&self) -> StatusCode {
-        match self {
-            AppError::NotFound => StatusCode::NOT_FOUND,
+        match self.code {
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::Validation => StatusCode::BAD_REQUEST,
+            ErrorCode::DockerUnavailable | ErrorCode::Database => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::ImageMissing => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// What the user can do about the error.
+    pub fn hint(&self) -> &'static str {
+        match self.code {
+            ErrorCode::NotFound => "It may have been deleted, check the link.",
+            ErrorCode::Unauthorized => "Log in and try again.",
+            ErrorCode::Forbidden => "Ask an administrator for access.",
+            ErrorCode::Validation => "Fix the input and try again.",
+            ErrorCode::DockerUnavailable => {
+                "Check that the Docker daemon is running and reachable by the server."
+            }
+            ErrorCode::ImageMissing => "Pull the image on the server or fix the stage image.",
+            ErrorCode::Database => "Check the database connection in the server logs.",
+            ErrorCode::Internal => "Check the server logs for details.",
+        }
+    }
+}
+
+impl From<ServerFnError> for AppError {
+    fn from(value: ServerFnError) -> AppError {
+        match value {
+            ServerFnError::ServerError(message) => message
+                .split_once(": ")
+                .and_then(|(key, message)| {
+                    ErrorCode::from_key(key).map(|code| AppError::new(code, message))
+                })
+                .unwrap_or_else(|| AppError::new(ErrorCode::Internal, message)),
+            ServerFnError::Args(message) | ServerFnError::MissingArg(message) => {
+                AppError::validation(message)
+            }
+            value => AppError::new(ErrorCode::Internal, value.to_string()),
         }
     }
 }
 
+cfg_if! { if #[cfg(feature = "ssr")] {
+    impl From<osprei_storage::Error> for AppError {
+        fn from(value: osprei_storage::Error) -> AppError {
+            match value {
+                osprei_storage::Error::NotFound => AppError::not_found("Not found"),
+                osprei_storage::Error::AlreadyExists => AppError::validation("Already exists"),
+                value => AppError::new(ErrorCode::Database, value.to_string()),
+            }
+        }
+    }
+
+    impl From<osprei_execution::Error> for AppError {
+        fn from(value: osprei_execution::Error) -> AppError {
+            let code = match value {
+                osprei_execution::Error::DockerUnavailable(_) => ErrorCode::DockerUnavailable,
+                osprei_execution::Error::ImageMissing(_) => ErrorCode::ImageMissing,
+                _ => ErrorCode::Internal,
+            };
+            AppError::new(code, value.to_string())
+        }
+    }
+}}
+
+/// Message and hint of an error.
+#[component]
+pub fn error_message(error: AppError) -> impl IntoView {
+    view! {
+        <div class=format!("error-message error-{}", error.code.key())>
+            <p>{error.message.clone()}</p>
+            <p class="error-hint">{error.hint()}</p>
+        </div>
+    }
+}
+
 // A basic function to display errors served by the error boundaries.
 // Feel free to do more complicated things here than just displaying the error.
 #[component]
@@ -40,16 +145,32 @@ pub fn ErrorTemplate(
     // Downcast lets us take a type that implements `std::error::Error`
     let errors: Vec<AppError> = errors
         .into_iter()
-        .filter_map(|(_k, v)| v.downcast_ref::<AppError>().cloned())
+        .map(|(_k, v)| {
+            v.downcast_ref::<AppError>()
+                .cloned()
+                .or_else(|| {
+                    v.downcast_ref::<ServerFnErrorErr>()
+                        .cloned()
+                        .map(|err| AppError::from(ServerFnError::from(err)))
+                })
+                .unwrap_or_else(|| AppError::new(ErrorCode::Internal, v.to_string()))
+        })
         .collect();
-    println!("Errors: {errors:#?}");
+
+    // Sessions can expire while browsing, the user has to log in again
+    if errors
+        .iter()
+        .any(|error| error.code == ErrorCode::Unauthorized)
+    {
+        return view! { <Redirect path=crate::auth::LOGIN_PATH/> }.into_view();
+    }
 
     // Only the response code for the first error is actually sent from the server
     // this may be customized by the specific application
     cfg_if! { if #[cfg(feature="ssr")] {
         let response = use_context::<ResponseOptions>();
-        if let Some(response) = response {
-            response.set_status(errors[0].status_code());
+        if let (Some(response), Some(error)) = (response, errors.first()) {
+            response.set_status(error.status_code());
         }
     }}
 
@@ -61,14 +182,14 @@ pub fn ErrorTemplate(
             // a unique key for each item as a reference
             key=|(index, _error)| *index
             // renders each item to a view
-            children=move |error| {
-                let error_string = error.1.to_string();
-                let error_code = error.1.status_code();
+            children=move |(_, error)| {
+                let error_code = error.status_code();
                 view! {
                     <h2>{error_code.to_string()}</h2>
-                    <p>"Error: " {error_string}</p>
+                    <ErrorMessage error/>
                 }
             }
         />
     }
+    .into_view()
 }
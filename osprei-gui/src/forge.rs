@@ -0,0 +1,235 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::config::Forge;
+    use crate::secrets::Secrets;
+    use osprei_data::ExecutionStatus;
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Reports the status of executed commits to a GitHub or Gitea compatible API.
+    #[derive(Clone)]
+    pub struct StatusReporter {
+        client: reqwest::Client,
+        /// Base of the API, without a trailing slash.
+        api_url: String,
+        token_secret: String,
+        context: String,
+        secrets: Secrets,
+    }
+
+    #[derive(serde::Serialize)]
+    struct CommitStatus<'a> {
+        state: &'a str,
+        target_url: &'a str,
+        description: String,
+        context: &'a str,
+    }
+
+    impl StatusReporter {
+        pub fn new(forge: &Forge, secrets: Secrets) -> StatusReporter {
+            StatusReporter {
+                client: reqwest::Client::new(),
+                api_url: forge.api_url.trim_end_matches('/').to_string(),
+                token_secret: forge.token_secret.clone(),
+                context: forge.context.clone(),
+                secrets,
+            }
+        }
+
+        /// Sets the status of the commit in the repository the job checks out, linking to
+        /// `target_url`.
+        pub async fn report(
+            &self,
+            source: &str,
+            commit: &str,
+            status: ExecutionStatus,
+            target_url: &str,
+        ) -> Result<(), Error> {
+            let (owner, name) =
+                repository(source).ok_or_else(|| Error::Repository(source.to_string()))?;
+            let token = self
+                .secrets
+                .get(&self.token_secret)
+                .await
+                .map_err(|err| Error::Secret(self.token_secret.clone(), err))?;
+            let url = format!("{}/repos/{owner}/{name}/statuses/{commit}", self.api_url);
+            let body = CommitStatus {
+                state: state(status),
+                target_url,
+                description: format!("{status}"),
+                context: &self.context,
+            };
+            log::info!("Reporting {} for commit {commit} of {owner}/{name}", body.state);
+            let response = self
+                .client
+                .post(url)
+                .timeout(TIMEOUT)
+                .header("Authorization", format!("token {token}"))
+                .header("Accept", "application/json")
+                .header("User-Agent", "osprei")
+                .json(&body)
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(Error::Rejected(response.status()));
+            }
+            Ok(())
+        }
+    }
+
+    /// State understood by both GitHub and Gitea for the status.
+    fn state(status: ExecutionStatus) -> &'static str {
+        match status {
+            ExecutionStatus::Queued | ExecutionStatus::Running => "pending",
+            ExecutionStatus::Success => "success",
+            ExecutionStatus::Failure | ExecutionStatus::TimedOut => "failure",
+            ExecutionStatus::Cancelled | ExecutionStatus::Interrupted => "error",
+        }
+    }
+
+    /// Owner and name of the repository at the git url, such as `https://host/owner/name.git`
+    /// or `git@host:owner/name.git`.
+    pub fn repository(source: &str) -> Option<(&str, &str)> {
+        let path = source.trim_end_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let mut segments = path.rsplit(['/', ':']);
+        let name = segments.next().filter(|name| !name.is_empty())?;
+        let owner = segments.next().filter(|owner| !owner.is_empty())?;
+        Some((owner, name))
+    }
+
+    #[derive(Debug)]
+    pub enum Error {
+        /// The job source is not a url naming an owner and a repository.
+        Repository(String),
+        Secret(String, std::io::Error),
+        Request(reqwest::Error),
+        Rejected(reqwest::StatusCode),
+    }
+
+    impl std::fmt::Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                Error::Repository(source) => write!(f, "no repository in source {:?}", source),
+                Error::Secret(name, err) => write!(f, "reading secret {}: {}", name, err),
+                Error::Request(err) => write!(f, "request: {}", err),
+                Error::Rejected(status) => write!(f, "forge responded {}", status),
+            }
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl From<reqwest::Error> for Error {
+        fn from(value: reqwest::Error) -> Error {
+            Error::Request(value)
+        }
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::notifications::Notifier;
+    use axum::extract::{Path, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::{routing::post, Json, Router};
+    use osprei_storage::{MemoryStorage, SharedStorage};
+    use std::sync::{Arc, Mutex};
+
+    /// Status posted to the stand-in forge: its path, authorization and body.
+    type Posted = (String, String, serde_json::Value);
+
+    async fn receive(
+        State(posted): State<Arc<Mutex<Vec<Posted>>>>,
+        Path((owner, name, commit)): Path<(String, String, String)>,
+        headers: HeaderMap,
+        Json(body): Json<serde_json::Value>,
+    ) -> StatusCode {
+        let authorization = headers["authorization"].to_str().unwrap().to_string();
+        let path = format!("{owner}/{name}/{commit}");
+        posted.lock().unwrap().push((path, authorization, body));
+        StatusCode::CREATED
+    }
+
+    /// Serves a stand-in forge on a free local port, returning its API url.
+    fn serve(posted: Arc<Mutex<Vec<Posted>>>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/api/v1/repos/:owner/:name/statuses/:commit", post(receive))
+            .with_state(posted);
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+        format!("http://{address}/api/v1/")
+    }
+
+    #[test]
+    fn repositories_are_found_in_git_urls() {
+        for source in [
+            "https://gitea.example.com/owner/name.git",
+            "https://github.com/owner/name/",
+            "git@github.com:owner/name.git",
+            "ssh://git@example.com:2222/owner/name",
+        ] {
+            assert_eq!(repository(source), Some(("owner", "name")), "{source}");
+        }
+        assert_eq!(repository("name"), None);
+        assert_eq!(repository(""), None);
+    }
+
+    #[tokio::test]
+    async fn statuses_are_reported_when_the_commit_is_found_and_when_finished() {
+        let secrets = std::env::temp_dir().join(format!("osprei-secrets-{}", std::process::id()));
+        std::fs::create_dir_all(&secrets).unwrap();
+        std::fs::write(secrets.join("forge_token"), "s3cret\n").unwrap();
+        let posted = Arc::new(Mutex::new(Vec::new()));
+        let config = Config {
+            forge: Some(Forge {
+                api_url: serve(posted.clone()),
+                ..Forge::default()
+            }),
+            secrets_path: secrets.clone(),
+            ..Config::default()
+        };
+        let notifier = Notifier::new(&config);
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let job = storage
+            .create_job("https://gitea.example.com/owner/name.git".to_string())
+            .await
+            .unwrap();
+        let execution = storage.create_execution(job, "manual", None).await.unwrap();
+        storage
+            .set_execution_commit(execution, "abc123")
+            .await
+            .unwrap();
+
+        notifier.commit_found(&*storage, execution, "abc123").await;
+        storage
+            .finish_execution(execution, ExecutionStatus::TimedOut)
+            .await
+            .unwrap();
+        notifier
+            .execution_finished(&*storage, job, execution, None)
+            .await;
+        std::fs::remove_dir_all(secrets).unwrap();
+
+        let posted = posted.lock().unwrap().clone();
+        assert_eq!(posted.len(), 2);
+        let url = format!("http://localhost:3000/execution/{execution}");
+        for (path, authorization, body) in &posted {
+            assert_eq!(path, "owner/name/abc123");
+            assert_eq!(authorization, "token s3cret");
+            assert_eq!(body["context"], "osprei");
+            assert_eq!(body["target_url"], url.as_str());
+        }
+        assert_eq!(posted[0].2["state"], "pending");
+        assert_eq!(posted[1].2["state"], "failure");
+        assert_eq!(posted[1].2["description"], "Timed out");
+    }
+}
@@ -0,0 +1,103 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::server::Runner;
+    use axum::{extract::State, http::StatusCode, Json};
+    use osprei_storage::{SharedStorage, Storage};
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    /// Time each readiness check may take before it counts as failed.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Answers as long as the process serves requests, as `/healthz`.
+    pub async fn healthz() -> Json<Value> {
+        Json(json!({ "status": "ok" }))
+    }
+
+    /// Whether the database and Docker can be used, as `/readyz`, with the outcome of each
+    /// check. Responds 503 unless every check passes.
+    pub async fn readyz(
+        State(storage): State<SharedStorage>,
+        State(runner): State<Runner>,
+    ) -> (StatusCode, Json<Value>) {
+        let (storage, docker) = futures_util::future::join(
+            check_storage(&*storage),
+            check_docker(&runner.settings),
+        )
+        .await;
+        let (database, migrations) = storage;
+        let ready = [&database, &migrations, &docker]
+            .iter()
+            .all(|check| check["ok"] == true);
+        let (status, label) = if ready {
+            (StatusCode::OK, "ready")
+        } else {
+            (StatusCode::SERVICE_UNAVAILABLE, "not ready")
+        };
+        let body = json!({
+            "status": label,
+            "checks": {
+                "database": database,
+                "migrations": migrations,
+                "docker": docker,
+            },
+        });
+        (status, Json(body))
+    }
+
+    /// Outcome of the connection and migration checks.
+    async fn check_storage(storage: &dyn Storage) -> (Value, Value) {
+        match tokio::time::timeout(TIMEOUT, storage.check_migrations()).await {
+            Ok(Ok(())) => (passed(), passed()),
+            Ok(Err(err @ osprei_storage::Error::PendingMigrations(_))) => (passed(), failed(err)),
+            Ok(Err(err)) => (failed(err), failed("database not available")),
+            Err(_) => (failed("timed out"), failed("database not available")),
+        }
+    }
+
+    async fn check_docker(settings: &osprei_execution::Settings) -> Value {
+        match tokio::time::timeout(TIMEOUT, osprei_execution::version(settings)).await {
+            Ok(Ok(version)) => json!({
+                "ok": true,
+                "version": version.version,
+                "api_version": version.api_version,
+            }),
+            Ok(Err(err)) => failed(err),
+            Err(_) => failed("timed out"),
+        }
+    }
+
+    fn passed() -> Value {
+        json!({ "ok": true })
+    }
+
+    fn failed(error: impl std::fmt::Display) -> Value {
+        json!({ "ok": false, "error": error.to_string() })
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use osprei_storage::MemoryStorage;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn readiness_reports_each_check() {
+        let Json(health) = healthz().await;
+        assert_eq!(health["status"], "ok");
+
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let mut runner = Runner::new(&Config::default());
+        runner.settings.docker_url = "tcp://127.0.0.1:1".to_string();
+        let (status, Json(body)) = readyz(State(storage), State(runner)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not ready");
+        assert_eq!(body["checks"]["database"]["ok"], true);
+        assert_eq!(body["checks"]["migrations"]["ok"], true);
+        assert_eq!(body["checks"]["docker"]["ok"], false);
+        assert!(body["checks"]["docker"]["error"].is_string());
+    }
+}
@@ -1,9 +1,23 @@
 use cfg_if::cfg_if;
+pub mod api;
 pub mod app;
+pub mod artifacts;
+pub mod auth;
+pub mod badge;
+pub mod config;
 pub mod error_template;
 pub mod fileserv;
+pub mod forge;
+pub mod health;
+pub mod logs;
+pub mod mail;
+pub mod metrics;
+pub mod notifications;
 pub mod pages;
+pub mod permissions;
+pub mod secrets;
 pub mod server;
+pub mod state;
 pub mod widget;
 
 cfg_if! { if #[cfg(feature = "hydrate")] {
@@ -0,0 +1,235 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::auth::Authenticated;
+    use osprei_storage::SharedStorage;
+    use axum::{
+        extract::{Path, State},
+        http::{header, StatusCode},
+        response::{
+            sse::{Event, KeepAlive, Sse},
+            IntoResponse, Response,
+        },
+    };
+    use futures_util::{stream, StreamExt};
+    use std::{convert::Infallible, path::PathBuf, time::Duration};
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    pub async fn download_log(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+    ) -> Response {
+        let stage = match storage.stage_execution(id).await {
+            Ok(stage) => stage,
+            Err(err) => {
+                log::warn!("Stage execution ({id}) not found: {err}");
+                return StatusCode::NOT_FOUND.into_response();
+            }
+        };
+        if let Err(err) = caller.require_execution(&*storage, stage.execution).await {
+            return err.into_response();
+        }
+        let location = osprei_storage::stage_execution::log_path(&stage);
+        match tokio::fs::read(&location).await {
+            Ok(content) => (
+                [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+                content,
+            )
+                .into_response(),
+            Err(err) => {
+                log::error!("Error reading log {}: {err}", location.display());
+                StatusCode::NOT_FOUND.into_response()
+            }
+        }
+    }
+
+    /// Streams the log of a stage as server-sent events, one per line.
+    ///
+    /// An `end` event is sent once the stage is done and the whole log has been sent.
+    pub async fn stream_log(
+        Authenticated(caller): Authenticated,
+        State(storage): State<SharedStorage>,
+        Path(id): Path<i64>,
+    ) -> Response {
+        let stage = match storage.stage_execution(id).await {
+            Ok(stage) => stage,
+            Err(err) => {
+                log::warn!("Stage execution ({id}) not found: {err}");
+                return StatusCode::NOT_FOUND.into_response();
+            }
+        };
+        if let Err(err) = caller.require_execution(&*storage, stage.execution).await {
+            return err.into_response();
+        }
+        let tail = LogTail {
+            storage,
+            id,
+            path: osprei_storage::stage_execution::log_path(&stage),
+            offset: 0,
+            pending: Vec::new(),
+            finished: false,
+        };
+        let events = stream::unfold(tail, |mut tail| async move {
+            let events = tail.next_events().await?;
+            Some((stream::iter(events), tail))
+        })
+        .flatten()
+        .map(Ok::<_, Infallible>);
+        Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response()
+    }
+
+    struct LogTail {
+        storage: SharedStorage,
+        id: i64,
+        path: PathBuf,
+        offset: u64,
+        pending: Vec<u8>,
+        finished: bool,
+    }
+
+    impl LogTail {
+        async fn next_events(&mut self) -> Option<Vec<Event>> {
+            if self.finished {
+                return None;
+            }
+            loop {
+                let content = self.read().await;
+                if !content.is_empty() {
+                    self.pending.extend(content);
+                    let lines = self.complete_lines();
+                    if !lines.is_empty() {
+                        return Some(lines.into_iter().map(line_event).collect());
+                    }
+                    continue;
+                }
+                if self.is_done().await {
+                    return Some(self.finish().await);
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+
+        /// Sends what is left of the log and the `end` event, reading the file once more as
+        /// the stage may have written to it between the last read and finishing.
+        async fn finish(&mut self) -> Vec<Event> {
+            self.finished = true;
+            let content = self.read().await;
+            self.pending.extend(content);
+            let mut events: Vec<_> = self.complete_lines().into_iter().map(line_event).collect();
+            if !self.pending.is_empty() {
+                let line = String::from_utf8_lossy(&self.pending).to_string();
+                events.push(line_event(line));
+            }
+            events.push(Event::default().event("end").data(""));
+            events
+        }
+
+        async fn read(&mut self) -> Vec<u8> {
+            let mut content = Vec::new();
+            let Ok(mut file) = tokio::fs::File::open(&self.path).await else {
+                return content;
+            };
+            if file.seek(std::io::SeekFrom::Start(self.offset)).await.is_ok() {
+                if let Ok(read) = file.read_to_end(&mut content).await {
+                    self.offset += read as u64;
+                }
+            }
+            content
+        }
+
+        fn complete_lines(&mut self) -> Vec<String> {
+            let Some(end) = self.pending.iter().rposition(|byte| *byte == b'\n') else {
+                return Vec::new();
+            };
+            let rest = self.pending.split_off(end + 1);
+            let complete = std::mem::replace(&mut self.pending, rest);
+            String::from_utf8_lossy(&complete)
+                .lines()
+                .map(|line| line.trim_end_matches('\r').to_string())
+                .collect()
+        }
+
+        /// Whether the stage will not write any more output.
+        async fn is_done(&self) -> bool {
+            let Ok(stage) = self.storage.stage_execution(self.id).await else {
+                return true;
+            };
+            match stage.status {
+                Some(status) => status.is_finished(),
+                None => self
+                    .storage
+                    .execution_status(stage.execution)
+                    .await
+                    .map_or(true, |status| status.is_finished()),
+            }
+        }
+    }
+
+    fn line_event(line: String) -> Event {
+        Event::default().data(line)
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use osprei_data::ExecutionStatus;
+    use osprei_storage::MemoryStorage;
+    use std::sync::Arc;
+
+    /// Body of a stream of the events, as sent to the browser.
+    async fn sent(events: Vec<Event>) -> String {
+        let events = stream::iter(events).map(Ok::<_, Infallible>);
+        let response = Sse::new(events).into_response();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn output_written_as_the_stage_finishes_is_sent() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let checkout = storage.stages_for_job(job).await.unwrap()[0].id;
+        let execution = storage.create_execution(job, "manual", None).await.unwrap();
+        let stages = vec![(checkout, "checkout".to_string())];
+        storage
+            .create_stage_executions(execution, stages)
+            .await
+            .unwrap();
+        storage.start_stage_execution(execution, 0).await.unwrap();
+        let id = storage.stage_executions(execution).await.unwrap()[0].id;
+        let path = std::env::temp_dir().join(format!("osprei-tail-{}.log", std::process::id()));
+        std::fs::write(&path, "first\n").unwrap();
+        let mut tail = LogTail {
+            storage: storage.clone(),
+            id,
+            path: path.clone(),
+            offset: 0,
+            pending: Vec::new(),
+            finished: false,
+        };
+        assert_eq!(
+            sent(tail.next_events().await.unwrap()).await,
+            "data:first\n\n"
+        );
+
+        // The stage writes its last output after the tail read the log, then finishes.
+        std::fs::write(&path, "first\nsecond\nlast").unwrap();
+        storage
+            .finish_stage_execution(execution, 0, ExecutionStatus::Success)
+            .await
+            .unwrap();
+        let events = tail.finish().await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            sent(events).await,
+            "data:second\n\ndata:last\n\nevent:end\ndata:\n\n"
+        );
+        assert!(tail.next_events().await.is_none());
+    }
+}
@@ -0,0 +1,433 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::config::{Smtp, SmtpSecurity};
+    use askama::Template;
+    use lettre::message::{header::ContentType, Mailbox};
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{address::Envelope, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+    use osprei_data::ExecutionStatus;
+    use osprei_storage::{Filter, Storage};
+
+    /// Lines of the log of the failed stage quoted in mails.
+    const LOG_TAIL_LINES: usize = 30;
+    /// Earlier executions looked through for the previous outcome of the job.
+    const HISTORY: i64 = 20;
+
+    /// How an execution changed the outcome of its job.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Change {
+        /// Failed while the previous execution passed, or on the first run.
+        Broken,
+        /// Passed while the previous execution failed.
+        Fixed,
+        Unchanged,
+    }
+
+    impl Change {
+        /// Compares the outcome with the previous one, if the job ran before.
+        pub fn between(previous: Option<ExecutionStatus>, current: ExecutionStatus) -> Change {
+            let passed = current == ExecutionStatus::Success;
+            match previous {
+                Some(ExecutionStatus::Success) if !passed => Change::Broken,
+                None if !passed => Change::Broken,
+                Some(status) if passed && status != ExecutionStatus::Success => Change::Fixed,
+                _ => Change::Unchanged,
+            }
+        }
+    }
+
+    #[derive(Template)]
+    #[template(path = "execution_mail.txt")]
+    struct ExecutionMail<'a> {
+        headline: &'a str,
+        source: &'a str,
+        url: &'a str,
+        status: ExecutionStatus,
+        git_ref: Option<&'a str>,
+        commit: Option<&'a str>,
+        failed_stage: Option<&'a str>,
+        log_tail: &'a str,
+    }
+
+    /// Mails the subscribers of jobs about their executions.
+    #[derive(Clone)]
+    pub struct Mailer {
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: Mailbox,
+    }
+
+    impl Mailer {
+        pub fn new(smtp: &Smtp) -> Result<Mailer, Error> {
+            let builder = match smtp.security {
+                SmtpSecurity::None => {
+                    AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+                }
+                SmtpSecurity::StartTls => {
+                    AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)?
+                }
+                SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)?,
+            };
+            let mut builder = builder.port(smtp.port);
+            if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+                builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+            }
+            Ok(Mailer {
+                transport: builder.build(),
+                from: smtp.from.parse()?,
+            })
+        }
+
+        /// Mails the recipients of the job about the execution, when it broke or fixed the job
+        /// or when they asked about every run. `author` is the email of the commit author.
+        pub async fn execution_finished(
+            &self,
+            storage: &dyn Storage,
+            execution_id: i64,
+            url: &str,
+            author: Option<&str>,
+        ) -> Result<(), Error> {
+            let execution = storage.execution(execution_id).await?;
+            if execution.status == ExecutionStatus::Cancelled {
+                return Ok(());
+            }
+            let settings = storage.mail_settings(execution.job).await?;
+            let mut recipients = settings.recipients;
+            if let Some(author) = author.filter(|_| settings.commit_author) {
+                if !recipients.iter().any(|recipient| recipient == author) {
+                    recipients.push(author.to_string());
+                }
+            }
+            let mailboxes: Vec<Mailbox> = recipients
+                .iter()
+                .filter_map(|recipient| match recipient.parse() {
+                    Ok(mailbox) => Some(mailbox),
+                    Err(err) => {
+                        log::warn!("Skipping mail recipient {recipient:?}: {err}");
+                        None
+                    }
+                })
+                .collect();
+            if mailboxes.is_empty() {
+                return Ok(());
+            }
+            let filter = Filter {
+                job: Some(execution.job),
+                ..Filter::default()
+            };
+            let previous = storage
+                .list_executions(&filter, HISTORY, 0)
+                .await?
+                .into_iter()
+                .filter(|previous| previous.id < execution_id)
+                .map(|previous| previous.status)
+                .find(|status| {
+                    !matches!(
+                        status,
+                        ExecutionStatus::Queued
+                            | ExecutionStatus::Running
+                            | ExecutionStatus::Cancelled
+                    )
+                });
+            let change = Change::between(previous, execution.status);
+            if change == Change::Unchanged && !settings.every_run {
+                return Ok(());
+            }
+            let headline = match change {
+                Change::Broken => format!("{} is failing", execution.source),
+                Change::Fixed => format!("{} is fixed", execution.source),
+                Change::Unchanged => format!("{}: {}", execution.source, execution.status),
+            };
+            let failed_stage = storage
+                .stage_executions(execution_id)
+                .await?
+                .into_iter()
+                .find(|stage| {
+                    stage.status.is_some_and(|status| status != ExecutionStatus::Success)
+                });
+            let log_tail = match &failed_stage {
+                Some(stage) => log_tail(&osprei_storage::stage_execution::log_path(stage)).await,
+                None => String::new(),
+            };
+            let body = ExecutionMail {
+                headline: &headline,
+                source: &execution.source,
+                url,
+                status: execution.status,
+                git_ref: execution.git_ref.as_deref(),
+                commit: execution.commit_hash.as_deref(),
+                failed_stage: failed_stage.as_ref().map(|stage| stage.name.as_str()),
+                log_tail: &log_tail,
+            }
+            .render()?;
+            // Recipients are hidden from each other, the mail is only addressed to the sender.
+            let envelope = Envelope::new(
+                Some(self.from.email.clone()),
+                mailboxes.iter().map(|mailbox| mailbox.email.clone()).collect(),
+            )?;
+            let mut message = Message::builder()
+                .from(self.from.clone())
+                .to(self.from.clone())
+                .envelope(envelope)
+                .subject(format!("[osprei] {headline} (execution {execution_id})"))
+                .header(ContentType::TEXT_PLAIN);
+            for mailbox in mailboxes {
+                message = message.bcc(mailbox);
+            }
+            let message = message.body(body)?;
+            log::info!("Mailing about execution ({execution_id})");
+            self.transport.send(message).await?;
+            Ok(())
+        }
+    }
+
+    /// Last lines of the log, empty when it cannot be read.
+    async fn log_tail(path: &std::path::Path) -> String {
+        let content = tokio::fs::read(path).await.unwrap_or_default();
+        let content = String::from_utf8_lossy(&content);
+        let lines: Vec<_> = content.lines().collect();
+        lines[lines.len().saturating_sub(LOG_TAIL_LINES)..].join("\n")
+    }
+
+    #[derive(Debug)]
+    pub enum Error {
+        Storage(osprei_storage::Error),
+        Address(lettre::address::AddressError),
+        Message(lettre::error::Error),
+        Smtp(lettre::transport::smtp::Error),
+        Template(askama::Error),
+    }
+
+    impl std::fmt::Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                Error::Storage(err) => write!(f, "storage: {}", err),
+                Error::Address(err) => write!(f, "address: {}", err),
+                Error::Message(err) => write!(f, "message: {}", err),
+                Error::Smtp(err) => write!(f, "smtp: {}", err),
+                Error::Template(err) => write!(f, "template: {}", err),
+            }
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl From<osprei_storage::Error> for Error {
+        fn from(value: osprei_storage::Error) -> Error {
+            Error::Storage(value)
+        }
+    }
+
+    impl From<lettre::address::AddressError> for Error {
+        fn from(value: lettre::address::AddressError) -> Error {
+            Error::Address(value)
+        }
+    }
+
+    impl From<lettre::error::Error> for Error {
+        fn from(value: lettre::error::Error) -> Error {
+            Error::Message(value)
+        }
+    }
+
+    impl From<lettre::transport::smtp::Error> for Error {
+        fn from(value: lettre::transport::smtp::Error) -> Error {
+            Error::Smtp(value)
+        }
+    }
+
+    impl From<askama::Error> for Error {
+        fn from(value: askama::Error) -> Error {
+            Error::Template(value)
+        }
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use osprei_storage::{MailSettings, MemoryStorage, SharedStorage};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    /// Mail accepted by the sink.
+    #[derive(Debug, Default)]
+    struct Received {
+        recipients: Vec<String>,
+        data: String,
+    }
+
+    type Inbox = Arc<Mutex<Vec<Received>>>;
+
+    /// Stand-in mail server accepting every mail, returning its port and what
+    /// it received.
+    async fn sink() -> (u16, Inbox) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let inbox = Inbox::default();
+        let received = inbox.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(session(stream, received.clone()));
+            }
+        });
+        (port, inbox)
+    }
+
+    async fn session(stream: tokio::net::TcpStream, inbox: Inbox) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"220 sink\r\n").await?;
+        let mut mail = Received::default();
+        while let Some(line) = lines.next_line().await? {
+            let command = line.to_ascii_uppercase();
+            if let Some(recipient) = command.strip_prefix("RCPT TO:") {
+                let recipient = recipient.trim_matches(|c| c == '<' || c == '>' || c == ' ');
+                mail.recipients.push(recipient.to_lowercase());
+            } else if command == "DATA" {
+                writer.write_all(b"354 end with .\r\n").await?;
+                while let Some(line) = lines.next_line().await? {
+                    if line == "." {
+                        break;
+                    }
+                    mail.data.push_str(&line);
+                    mail.data.push('\n');
+                }
+                inbox.lock().unwrap().push(std::mem::take(&mut mail));
+            } else if command == "QUIT" {
+                writer.write_all(b"221 bye\r\n").await?;
+                break;
+            }
+            writer.write_all(b"250 ok\r\n").await?;
+        }
+        Ok(())
+    }
+
+    fn mailer(port: u16) -> Mailer {
+        let smtp = Smtp {
+            host: "127.0.0.1".to_string(),
+            port,
+            security: SmtpSecurity::None,
+            from: "Osprei <osprei@example.com>".to_string(),
+            ..Smtp::default()
+        };
+        Mailer::new(&smtp).unwrap()
+    }
+
+    /// Records a finished execution of the job whose `test` stage ended with
+    /// the status.
+    async fn execution(storage: &dyn Storage, job: i64, status: ExecutionStatus) -> i64 {
+        let execution = storage.create_execution(job, "manual", None).await.unwrap();
+        let checkout = storage.stages_for_job(job).await.unwrap()[0].id;
+        let names = vec![
+            (checkout, "checkout".to_string()),
+            (checkout, "test".to_string()),
+        ];
+        storage
+            .create_stage_executions(execution, names)
+            .await
+            .unwrap();
+        for (position, status) in [(0, ExecutionStatus::Success), (1, status)] {
+            storage
+                .start_stage_execution(execution, position)
+                .await
+                .unwrap();
+            storage
+                .finish_stage_execution(execution, position, status)
+                .await
+                .unwrap();
+        }
+        storage.finish_execution(execution, status).await.unwrap();
+        execution
+    }
+
+    #[test]
+    fn changes_compare_with_the_previous_outcome() {
+        use ExecutionStatus::*;
+        assert_eq!(Change::between(None, Failure), Change::Broken);
+        assert_eq!(Change::between(Some(Success), TimedOut), Change::Broken);
+        assert_eq!(Change::between(Some(Failure), Success), Change::Fixed);
+        assert_eq!(Change::between(Some(Failure), Failure), Change::Unchanged);
+        assert_eq!(Change::between(Some(Success), Success), Change::Unchanged);
+        assert_eq!(Change::between(None, Success), Change::Unchanged);
+    }
+
+    #[tokio::test]
+    async fn recipients_are_mailed_when_the_outcome_changes() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let settings = MailSettings {
+            recipients: vec!["dev@example.com".to_string()],
+            commit_author: true,
+            every_run: false,
+        };
+        storage.set_mail_settings(job, &settings).await.unwrap();
+        let (port, inbox) = sink().await;
+        let mailer = mailer(port);
+        let send = |execution: i64| {
+            let storage = storage.clone();
+            let mailer = mailer.clone();
+            async move {
+                let url = format!("http://osprei.example.com/execution/{execution}");
+                mailer
+                    .execution_finished(&*storage, execution, &url, Some("author@example.com"))
+                    .await
+                    .unwrap();
+            }
+        };
+
+        let broken = execution(&*storage, job, ExecutionStatus::Failure).await;
+        let test = storage.stage_executions(broken).await.unwrap().remove(1);
+        let log = osprei_storage::stage_execution::log_path(&test);
+        std::fs::create_dir_all(log.parent().unwrap()).unwrap();
+        let output: String = (1..=40).map(|line| format!("line {line}\n")).collect();
+        std::fs::write(&log, output).unwrap();
+        send(broken).await;
+        std::fs::remove_dir_all(log.parent().unwrap()).unwrap();
+        let still_broken = execution(&*storage, job, ExecutionStatus::Failure).await;
+        send(still_broken).await;
+        let fixed = execution(&*storage, job, ExecutionStatus::Success).await;
+        send(fixed).await;
+
+        let mails = std::mem::take(&mut *inbox.lock().unwrap());
+        assert_eq!(mails.len(), 2);
+        assert_eq!(
+            mails[0].recipients,
+            ["dev@example.com", "author@example.com"]
+        );
+        let data = &mails[0].data;
+        assert!(data.contains(&format!(
+            "Subject: [osprei] source is failing (execution {broken})"
+        )));
+        assert!(data.contains(&format!("http://osprei.example.com/execution/{broken}")));
+        assert!(data.contains("Last lines of the test log:"));
+        assert!(data.contains("line 40"));
+        assert!(data.contains("line 11") && !data.contains("line 10\n"));
+        assert!(data.contains("To: Osprei <osprei@example.com>"));
+        assert!(!data.contains("dev@example.com") && !data.contains("Bcc"));
+        assert!(mails[1].data.contains("source is fixed"));
+        assert!(!mails[1].data.contains("Last lines"));
+
+        let settings = MailSettings {
+            commit_author: false,
+            every_run: true,
+            ..settings
+        };
+        storage.set_mail_settings(job, &settings).await.unwrap();
+        let passed = execution(&*storage, job, ExecutionStatus::Success).await;
+        send(passed).await;
+        let mails = std::mem::take(&mut *inbox.lock().unwrap());
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].recipients, ["dev@example.com"]);
+        assert!(mails[0].data.contains("source: Success"));
+
+        let settings = MailSettings {
+            recipients: vec!["not an address".to_string()],
+            ..settings
+        };
+        storage.set_mail_settings(job, &settings).await.unwrap();
+        let unaddressed = execution(&*storage, job, ExecutionStatus::Success).await;
+        send(unaddressed).await;
+        assert!(inbox.lock().unwrap().is_empty());
+    }
+}
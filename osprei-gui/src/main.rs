@@ -1,24 +1,106 @@
 #[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() {
-    use axum::{routing::post, Router};
+    use axum::{
+        middleware,
+        routing::{get, post},
+        Router,
+    };
     use leptos::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
+    use osprei_gui::api;
     use osprei_gui::app::*;
+    use osprei_gui::artifacts::download_artifact;
+    use osprei_gui::auth::{bootstrap_admin, require_login, Sessions};
+    use osprei_gui::badge::job_badge;
+    use osprei_gui::config::Config;
     use osprei_gui::fileserv::file_and_error_handler;
+    use osprei_gui::health::{healthz, readyz};
+    use osprei_gui::logs::{download_log, stream_log};
+    use osprei_gui::metrics::render_metrics;
+    use osprei_gui::server::Runner;
+    use osprei_gui::state::{server_fn_handler, AppState};
 
     simple_logger::init_with_level(log::Level::Info).expect("couldn't initialize logging");
+    if let Err(err) = osprei_gui::metrics::install() {
+        log::error!("Could not install the metrics recorder: {err}");
+        std::process::exit(1);
+    }
+
+    // The configuration file is the first argument, or `OSPREI_CONFIG`.
+    let config_path = std::env::args_os()
+        .nth(1)
+        .or_else(|| std::env::var_os("OSPREI_CONFIG"))
+        .map(std::path::PathBuf::from);
+    let config = match Config::load(config_path.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            log::error!("Could not load configuration: {err}");
+            std::process::exit(1);
+        }
+    };
+    if let Err(err) = std::fs::create_dir_all(&config.data_path) {
+        log::error!(
+            "Could not create data directory {}: {err}",
+            config.data_path.display()
+        );
+        std::process::exit(1);
+    }
+    osprei_storage::set_data_path(config.data_path.clone());
 
     let conf = get_configuration(None).await.unwrap();
-    let leptos_options = conf.leptos_options;
+    let mut leptos_options = conf.leptos_options;
+    if let Some(address) = config.address {
+        leptos_options.site_addr = address;
+    }
     let addr = leptos_options.site_addr;
     let routes = generate_route_list(App);
 
+    let storage = match osprei_storage::connect(&config.database_url).await {
+        Ok(storage) => storage,
+        Err(err) => {
+            log::error!("Could not open storage: {err}");
+            std::process::exit(1);
+        }
+    };
+    // Executions of a previous run of the server cannot be resumed.
+    match storage.interrupt_executions().await {
+        Ok(0) => {}
+        Ok(count) => log::warn!("Marked {count} unfinished executions as interrupted"),
+        Err(err) => {
+            log::error!("Could not interrupt unfinished executions: {err}");
+            std::process::exit(1);
+        }
+    }
+    if let Err(err) = bootstrap_admin(&*storage, &config).await {
+        log::error!("Could not create the administrator: {err}");
+        std::process::exit(1);
+    }
+    let state = AppState {
+        leptos_options,
+        storage,
+        runner: Runner::new(&config),
+        sessions: Sessions::new(&config),
+    };
+    let context_state = state.clone();
+
+    // Pages and downloads send visitors without a session to the login page, the
+    // API and server functions check the session themselves. Badges, metrics
+    // and health checks are public.
     let app = Router::new()
-        .route("/api/*fn_name", post(leptos_axum::handle_server_fns))
-        .leptos_routes(&leptos_options, routes, App)
+        .route("/artifacts/:id", get(download_artifact))
+        .route("/logs/:id", get(download_log))
+        .route("/logs/:id/stream", get(stream_log))
+        .leptos_routes_with_context(&state, routes, move || context_state.provide(), App)
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_login))
+        .route("/badge/job/:file", get(job_badge))
+        .route("/metrics", get(render_metrics))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .nest("/api/v1", api::router())
+        .route("/api/*fn_name", post(server_fn_handler))
         .fallback(file_and_error_handler)
-        .with_state(leptos_options);
+        .with_state(state);
 
     log::info!("listening on http://{}", &addr);
     axum::Server::bind(&addr)
@@ -0,0 +1,84 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use axum::{
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+    };
+    use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+    use osprei_data::ExecutionStatus;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    const EXECUTIONS: &str = "osprei_executions_total";
+    const EXECUTION_DURATION: &str = "osprei_execution_duration_seconds";
+    const STAGE_DURATION: &str = "osprei_stage_duration_seconds";
+    const QUEUED: &str = "osprei_queued_executions";
+    const RUNNING: &str = "osprei_running_executions";
+
+    /// Upper bounds of the duration histograms, from database calls to long executions.
+    const BUCKETS: [f64; 12] = [
+        0.001, 0.005, 0.025, 0.1, 0.5, 1.0, 5.0, 30.0, 60.0, 300.0, 900.0, 3600.0,
+    ];
+
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+    /// Installs the recorder every metric is collected by, once at startup.
+    pub fn install() -> Result<(), BuildError> {
+        if HANDLE.get().is_some() {
+            return Ok(());
+        }
+        let handle = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), &BUCKETS)?
+            .install_recorder()?;
+        let _ = HANDLE.set(handle);
+        Ok(())
+    }
+
+    /// Metrics in the Prometheus text format, as `/metrics`.
+    pub async fn render_metrics() -> Response {
+        match HANDLE.get() {
+            Some(handle) => (
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                handle.render(),
+            )
+                .into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    /// The execution waits for a free slot.
+    pub fn execution_queued() {
+        metrics::increment_gauge!(QUEUED, 1.0);
+    }
+
+    /// The execution got a slot and runs until `execution_stopped`.
+    pub fn execution_started() {
+        metrics::decrement_gauge!(QUEUED, 1.0);
+        metrics::increment_gauge!(RUNNING, 1.0);
+    }
+
+    pub fn execution_stopped() {
+        metrics::decrement_gauge!(RUNNING, 1.0);
+    }
+
+    /// Counts the outcome of an execution of the job that ran for `duration`.
+    pub fn execution_finished(job_id: i64, status: ExecutionStatus, duration: Duration) {
+        let job = job_id.to_string();
+        metrics::increment_counter!(
+            EXECUTIONS,
+            "job" => job.clone(),
+            "status" => status.key(),
+        );
+        metrics::histogram!(EXECUTION_DURATION, duration.as_secs_f64(), "job" => job);
+    }
+
+    pub fn stage_finished(job_id: i64, stage: String, duration: Duration) {
+        metrics::histogram!(
+            STAGE_DURATION,
+            duration.as_secs_f64(),
+            "job" => job_id.to_string(),
+            "stage" => stage,
+        );
+    }
+}}
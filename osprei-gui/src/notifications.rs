@@ -0,0 +1,413 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::config::Config;
+    use crate::forge::StatusReporter;
+    use crate::mail::Mailer;
+    use crate::secrets::Secrets;
+    use osprei_data::webhook::{Event, Execution, Job, Notification};
+    use osprei_data::ExecutionStatus;
+    use osprei_storage::{Delivery, Storage, Webhook};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Tells the webhooks, mail recipients and forge about executions.
+    #[derive(Clone)]
+    pub struct Notifier {
+        client: reqwest::Client,
+        /// Unset when no mail server is configured.
+        pub mailer: Option<Mailer>,
+        /// Unset when no forge is configured.
+        pub reporter: Option<StatusReporter>,
+        /// Base of the links to execution pages.
+        public_url: String,
+        /// Attempts at each delivery.
+        attempts: u32,
+        /// Wait before retrying a failed attempt, doubled after each one.
+        pub retry_delay: Duration,
+    }
+
+    impl Notifier {
+        pub fn new(config: &Config) -> Notifier {
+            let mailer = config.smtp.as_ref().and_then(|smtp| match Mailer::new(smtp) {
+                Ok(mailer) => Some(mailer),
+                Err(err) => {
+                    log::error!("Email notifications disabled: {err}");
+                    None
+                }
+            });
+            let reporter = config
+                .forge
+                .as_ref()
+                .map(|forge| StatusReporter::new(forge, Secrets::new(&config.secrets_path)));
+            Notifier {
+                client: reqwest::Client::new(),
+                mailer,
+                reporter,
+                public_url: config.public_url.trim_end_matches('/').to_string(),
+                attempts: config.webhook_attempts.max(1),
+                retry_delay: Duration::from_secs(1),
+            }
+        }
+
+        /// Page of the execution in the web interface.
+        pub fn execution_url(&self, execution_id: i64) -> String {
+            format!("{}/execution/{execution_id}", self.public_url)
+        }
+
+        /// Reports the commit the execution checked out as pending to the forge.
+        pub async fn commit_found(&self, storage: &dyn Storage, execution_id: i64, commit: &str) {
+            let Some(reporter) = &self.reporter else {
+                return;
+            };
+            let source = match storage.execution(execution_id).await {
+                Ok(execution) => execution.source,
+                Err(err) => {
+                    log::error!("Error loading execution ({execution_id}): {err}");
+                    return;
+                }
+            };
+            let url = self.execution_url(execution_id);
+            let result = reporter
+                .report(&source, commit, ExecutionStatus::Running, &url)
+                .await;
+            if let Err(err) = result {
+                log::error!("Error reporting execution ({execution_id}) to the forge: {err}");
+            }
+        }
+
+        /// Notifies the webhooks, mail recipients and forge that the execution finished.
+        /// `author` is the email of the author of the executed commit, when known.
+        pub async fn execution_finished(
+            &self,
+            storage: &dyn Storage,
+            job_id: i64,
+            execution_id: i64,
+            author: Option<&str>,
+        ) {
+            let mail = async {
+                let Some(mailer) = &self.mailer else {
+                    return;
+                };
+                let url = self.execution_url(execution_id);
+                let result = mailer
+                    .execution_finished(storage, execution_id, &url, author)
+                    .await;
+                if let Err(err) = result {
+                    log::error!("Error mailing about execution ({execution_id}): {err}");
+                }
+            };
+            let status = async {
+                let Some(reporter) = &self.reporter else {
+                    return;
+                };
+                let execution = match storage.execution(execution_id).await {
+                    Ok(execution) => execution,
+                    Err(err) => {
+                        log::error!("Error loading execution ({execution_id}): {err}");
+                        return;
+                    }
+                };
+                let Some(commit) = execution.commit_hash else {
+                    return;
+                };
+                let url = self.execution_url(execution_id);
+                let result = reporter
+                    .report(&execution.source, &commit, execution.status, &url)
+                    .await;
+                if let Err(err) = result {
+                    log::error!("Error reporting execution ({execution_id}) to the forge: {err}");
+                }
+            };
+            let webhooks = self.notify_webhooks(storage, job_id, execution_id);
+            futures_util::future::join3(webhooks, mail, status).await;
+        }
+
+        /// Notifies the webhooks of the job, and those of every job.
+        async fn notify_webhooks(&self, storage: &dyn Storage, job_id: i64, execution_id: i64) {
+            let notification = match self.notification(storage, job_id, execution_id).await {
+                Ok(notification) => notification,
+                Err(err) => {
+                    log::error!("Error describing execution ({execution_id}): {err}");
+                    return;
+                }
+            };
+            let webhooks = match storage.job_webhooks(job_id).await {
+                Ok(webhooks) => webhooks,
+                Err(err) => {
+                    log::error!("Error loading webhooks of job ({job_id}): {err}");
+                    return;
+                }
+            };
+            let deliveries = webhooks.iter().map(|webhook| {
+                self.deliver(storage, webhook, Some(execution_id), &notification)
+            });
+            for result in futures_util::future::join_all(deliveries).await {
+                if let Err(err) = result {
+                    log::error!("Error recording webhook delivery: {err}");
+                }
+            }
+        }
+
+        /// Posts a test notification to the webhook.
+        pub async fn test(
+            &self,
+            storage: &dyn Storage,
+            webhook: &Webhook,
+        ) -> Result<Delivery, osprei_storage::Error> {
+            let source = match webhook.job {
+                Some(job) => storage.job_source(job).await?,
+                None => String::new(),
+            };
+            let notification = Notification {
+                event: Event::Test,
+                job: Job {
+                    id: webhook.job,
+                    source,
+                },
+                execution: None,
+            };
+            self.deliver(storage, webhook, None, &notification).await
+        }
+
+        async fn notification(
+            &self,
+            storage: &dyn Storage,
+            job_id: i64,
+            execution_id: i64,
+        ) -> Result<Notification, osprei_storage::Error> {
+            let execution = storage.execution(execution_id).await?;
+            let failed_stage = storage
+                .stage_executions(execution_id)
+                .await?
+                .into_iter()
+                .find(|stage| stage.status.is_some_and(|status| status != ExecutionStatus::Success))
+                .map(|stage| stage.name);
+            Ok(Notification {
+                event: Event::ExecutionFinished,
+                job: Job {
+                    id: Some(job_id),
+                    source: execution.source,
+                },
+                execution: Some(Execution {
+                    id: execution_id,
+                    url: self.execution_url(execution_id),
+                    status: execution.status,
+                    duration: execution.duration,
+                    triggered_by: execution.triggered_by,
+                    git_ref: execution.git_ref,
+                    commit: execution.commit_hash,
+                    failed_stage,
+                }),
+            })
+        }
+
+        /// Posts the notification until it is accepted or the attempts run out, then records
+        /// the outcome.
+        async fn deliver(
+            &self,
+            storage: &dyn Storage,
+            webhook: &Webhook,
+            execution_id: Option<i64>,
+            notification: &Notification,
+        ) -> Result<Delivery, osprei_storage::Error> {
+            let mut delay = self.retry_delay;
+            let mut attempts = 0;
+            let (status_code, error) = loop {
+                attempts += 1;
+                let (status_code, error) = self.post(&webhook.url, notification).await;
+                if error.is_none() || attempts >= self.attempts {
+                    break (status_code, error);
+                }
+                log::warn!("Webhook ({}) attempt {attempts} failed, retrying", webhook.id);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            };
+            if let Some(error) = &error {
+                log::error!("Webhook ({}) delivery failed: {error}", webhook.id);
+            }
+            let attempts = attempts as i64;
+            let id = storage
+                .record_delivery(webhook.id, execution_id, attempts, status_code, error.as_deref())
+                .await?;
+            Ok(Delivery {
+                id,
+                webhook: webhook.id,
+                execution: execution_id,
+                attempts,
+                status_code,
+                error,
+                delivered_at: now(),
+            })
+        }
+
+        /// Status of the response, and why the attempt failed unless it was a success.
+        async fn post(
+            &self,
+            url: &str,
+            notification: &Notification,
+        ) -> (Option<i64>, Option<String>) {
+            let response = self
+                .client
+                .post(url)
+                .timeout(TIMEOUT)
+                .json(notification)
+                .send()
+                .await;
+            match response {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16() as i64), None)
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    (Some(status.as_u16() as i64), Some(format!("responded {status}")))
+                }
+                Err(err) => (None, Some(err.to_string())),
+            }
+        }
+    }
+
+    fn now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default()
+    }
+}}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use osprei_storage::{MemoryStorage, SharedStorage};
+    use std::sync::{Arc, Mutex};
+
+    /// Stand-in for a webhook receiver, failing the first `failures` requests.
+    #[derive(Clone, Default)]
+    struct Receiver {
+        failures: usize,
+        received: Arc<Mutex<Vec<serde_json::Value>>>,
+    }
+
+    async fn receive(
+        State(receiver): State<Receiver>,
+        Json(body): Json<serde_json::Value>,
+    ) -> StatusCode {
+        let mut received = receiver.received.lock().unwrap();
+        received.push(body);
+        if received.len() <= receiver.failures {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+
+    /// Serves the receiver on a free local port, returning its url.
+    fn serve(receiver: Receiver) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver);
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+        format!("http://{address}/hook")
+    }
+
+    fn notifier() -> Notifier {
+        Notifier {
+            retry_delay: Duration::from_millis(10),
+            ..Notifier::new(&Config::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_executions_are_posted_with_retries() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let execution = storage.create_execution(job, "manual", None).await.unwrap();
+        let checkout = storage.stages_for_job(job).await.unwrap()[0].id;
+        let names = vec![
+            (checkout, "checkout".to_string()),
+            (checkout, "test".to_string()),
+        ];
+        storage
+            .create_stage_executions(execution, names)
+            .await
+            .unwrap();
+        storage
+            .set_execution_commit(execution, "abc123")
+            .await
+            .unwrap();
+        for (position, status) in [(0, ExecutionStatus::Success), (1, ExecutionStatus::Failure)] {
+            storage
+                .start_stage_execution(execution, position)
+                .await
+                .unwrap();
+            storage
+                .finish_stage_execution(execution, position, status)
+                .await
+                .unwrap();
+        }
+        storage
+            .finish_execution(execution, ExecutionStatus::Failure)
+            .await
+            .unwrap();
+        let receiver = Receiver {
+            failures: 1,
+            ..Receiver::default()
+        };
+        let url = serve(receiver.clone());
+        let webhook = storage.create_webhook(None, &url).await.unwrap();
+        // Nothing listens on the discard port, so every attempt fails.
+        let unreachable = storage
+            .create_webhook(Some(job), "http://127.0.0.1:9/hook")
+            .await
+            .unwrap();
+
+        notifier()
+            .execution_finished(&*storage, job, execution, None)
+            .await;
+
+        let received = receiver.received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1]["event"], "execution_finished");
+        assert_eq!(received[1]["job"]["source"], "source");
+        let payload = &received[1]["execution"];
+        assert_eq!(payload["id"], execution);
+        assert_eq!(payload["status"], "failure");
+        assert_eq!(payload["commit"], "abc123");
+        assert_eq!(payload["failed_stage"], "test");
+        let url = format!("http://localhost:3000/execution/{execution}");
+        assert_eq!(payload["url"], url.as_str());
+        let delivery = &storage.deliveries(webhook, 10).await.unwrap()[0];
+        assert_eq!(delivery.execution, Some(execution));
+        assert_eq!((delivery.attempts, delivery.status_code), (2, Some(204)));
+        assert_eq!(delivery.error, None);
+        let failed = &storage.deliveries(unreachable, 10).await.unwrap()[0];
+        assert_eq!((failed.attempts, failed.status_code), (3, None));
+        assert!(failed.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_notifications_have_no_execution() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let receiver = Receiver::default();
+        let id = storage
+            .create_webhook(None, &serve(receiver.clone()))
+            .await
+            .unwrap();
+        let webhook = storage.webhook(id).await.unwrap();
+
+        let delivery = notifier().test(&*storage, &webhook).await.unwrap();
+        assert_eq!(delivery.error, None);
+        assert_eq!(delivery.execution, None);
+        let received = receiver.received.lock().unwrap().clone();
+        assert_eq!(received[0]["event"], "test");
+        assert_eq!(received[0]["execution"], serde_json::Value::Null);
+        assert_eq!(storage.deliveries(id, 10).await.unwrap()[0].id, delivery.id);
+    }
+}
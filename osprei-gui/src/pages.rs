@@ -1,5 +1,39 @@
 mod home;
 pub use home::Home;
 
+mod login;
+pub use login::Login;
+
 mod job;
 pub use job::Job;
+
+mod execution;
+pub use execution::Execution;
+
+mod stage;
+pub use stage::Stage;
+
+mod tokens;
+pub use tokens::Tokens;
+
+mod access;
+pub use access::Access;
+
+mod webhooks;
+pub use webhooks::Webhooks;
+
+use crate::error_template::AppError;
+use leptos::*;
+use leptos_router::*;
+
+/// Numeric `id` parameter of the route, `None` when it is missing or not a
+/// number.
+fn id_param() -> impl Fn() -> Option<i64> + Copy {
+    let params = use_params_map();
+    move || params.with(|p| p.get("id").and_then(|id| id.parse().ok()))
+}
+
+/// Error for a route whose id does not match anything.
+fn not_found(what: &str) -> ServerFnError {
+    AppError::not_found(format!("{what} does not exist")).into()
+}
@@ -0,0 +1,159 @@
+use crate::error_template::{AppError, ErrorMessage};
+use crate::server::*;
+use crate::widget::*;
+use leptos::*;
+use leptos_router::*;
+use osprei_data::Role;
+
+#[component]
+pub fn access() -> impl IntoView {
+    let create_group = create_server_action::<CreateGroup>();
+    let set_job_group = create_server_action::<SetJobGroup>();
+    let grant_role = create_server_action::<GrantRole>();
+    let revoke_grant = create_server_action::<RevokeGrant>();
+    let access = create_resource(
+        move || {
+            (
+                create_group.version().get(),
+                set_job_group.version().get(),
+                grant_role.version().get(),
+                revoke_grant.version().get(),
+            )
+        },
+        |_| load_access(),
+    );
+    let errors = move || {
+        [
+            create_group.value().get(),
+            set_job_group.value().get(),
+            grant_role.value().get(),
+            revoke_grant.value().get(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(Result::err)
+        .map(|err| view! { <ErrorMessage error=AppError::from(err)/> })
+        .collect_view()
+    };
+
+    view! {
+        <div>
+            <h2>"Access"</h2>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                {move || {
+                    access
+                        .get()
+                        .map(|access| match access {
+                            Ok(access) => {
+                                view! {
+                                    <AccessForms
+                                        access
+                                        create_group
+                                        set_job_group
+                                        grant_role
+                                        revoke_grant
+                                    />
+                                }
+                            }
+                            Err(err) => view! { <ErrorMessage error=AppError::from(err)/> },
+                        })
+                }}
+            </Transition>
+            {errors}
+        </div>
+    }
+}
+
+#[component]
+fn access_forms(
+    access: Access,
+    create_group: Action<CreateGroup, Result<(), ServerFnError>>,
+    set_job_group: Action<SetJobGroup, Result<(), ServerFnError>>,
+    grant_role: Action<GrantRole, Result<(), ServerFnError>>,
+    revoke_grant: Action<RevokeGrant, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let Access {
+        admin,
+        groups,
+        jobs,
+        grants,
+    } = access;
+    let targets = jobs
+        .iter()
+        .map(|job| {
+            let label = format!("Job {}: {}", job.id, job.source);
+            view! { <option value=format!("job:{}", job.id)>{label}</option> }
+        })
+        .chain(groups.iter().map(|group| {
+            let label = format!("Group {}", group.name);
+            view! { <option value=format!("group:{}", group.id)>{label}</option> }
+        }))
+        .collect_view();
+    let roles = Role::ALL
+        .into_iter()
+        .map(|role| view! { <option value=role.key()>{role.to_string()}</option> })
+        .collect_view();
+    let grant_table = view! { <GrantTable grants groups=groups.clone() action=revoke_grant/> };
+    let groups_section = admin.then(move || {
+        let rows = jobs
+            .into_iter()
+            .map(|job| {
+                let options = groups
+                    .iter()
+                    .map(|group| {
+                        view! {
+                            <option value=group.id selected=job.group == Some(group.id)>
+                                {group.name.clone()}
+                            </option>
+                        }
+                    })
+                    .collect_view();
+                view! {
+                    <tr>
+                        <td>{job.id}</td>
+                        <td>{job.source.clone()}</td>
+                        <td>
+                            <ActionForm action=set_job_group>
+                                <input type="text" value=job.id hidden=true name="job_id"/>
+                                <select name="group">
+                                    <option value="" selected=job.group.is_none()>
+                                        "No group"
+                                    </option>
+                                    {options}
+                                </select>
+                                <input class="run-button" type="submit" value="Move"/>
+                            </ActionForm>
+                        </td>
+                    </tr>
+                }
+            })
+            .collect_view();
+        view! {
+            <h3>"Groups"</h3>
+            <table class="job-table">
+                <tr>
+                    <th>"Id"</th>
+                    <th>"Source"</th>
+                    <th>"Group"</th>
+                </tr>
+                {rows}
+            </table>
+            <ActionForm class="token-form" action=create_group>
+                <label>"Name" <input type="text" name="name" required/></label>
+                <input type="submit" value="Create group"/>
+            </ActionForm>
+        }
+    });
+
+    view! {
+        <h3>"Roles"</h3>
+        {grant_table}
+        <ActionForm class="token-form" action=grant_role>
+            <label>"User" <input type="text" name="username" required/></label>
+            <label>"On" <select name="target">{targets}</select></label>
+            <label>"Role" <select name="role">{roles}</select></label>
+            <input type="submit" value="Grant"/>
+        </ActionForm>
+        {groups_section}
+    }
+}
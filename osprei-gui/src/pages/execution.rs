@@ -0,0 +1,64 @@
+use super::{id_param, not_found};
+use crate::error_template::ErrorTemplate;
+use crate::server::*;
+use crate::widget::{ArtifactList, ExecutionSummary, StageExecutionTable};
+use leptos::*;
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[component]
+pub fn execution() -> impl IntoView {
+    let execution_id = id_param();
+
+    let (refresh, set_refresh) = create_signal(0);
+    let execution = create_resource(
+        move || (execution_id(), refresh.get()),
+        |(id, _)| async move {
+            match id {
+                Some(id) => load_execution(id).await,
+                None => Err(not_found("Execution")),
+            }
+        },
+    );
+
+    create_effect(move |_| {
+        let running = execution
+            .get()
+            .and_then(|execution| execution.ok())
+            .map(|execution| !execution.status.is_finished())
+            .unwrap_or(false);
+        if running {
+            set_timeout(
+                move || set_refresh.update(|refresh| *refresh += 1),
+                REFRESH_INTERVAL,
+            );
+        }
+    });
+
+    view! {
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            <ErrorBoundary fallback=|errors| view! { <ErrorTemplate errors/> }>
+                {move || {
+                    execution
+                        .get()
+                        .map(|execution| {
+                            execution
+                                .map(|execution| {
+                                    let id = execution.id;
+                                    let stages = execution.stages.clone();
+                                    view! {
+                                        <h2>"Execution " {id}</h2>
+                                        <ExecutionSummary execution/>
+                                        <h3>"Stages"</h3>
+                                        <StageExecutionTable stages/>
+                                        <h3>"Artifacts"</h3>
+                                        <ArtifactList execution_id=id/>
+                                    }
+                                })
+                        })
+                }}
+            </ErrorBoundary>
+        </Transition>
+    }
+}
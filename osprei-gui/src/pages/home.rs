@@ -1,3 +1,4 @@
+use crate::error_template::{AppError, ErrorMessage};
 use crate::server::*;
 use crate::widget::*;
 use leptos::*;
@@ -11,15 +12,34 @@ pub fn home() -> impl IntoView {
         move || (add_job.version().get(), execute_job.version().get()),
         |_| async { load_jobs().await },
     );
-    let executions = create_resource(
-        move || execute_job.version().get(),
-        |_| async { load_executions().await },
-    );
+    let user = create_resource(|| (), |_| load_current_user());
+    let admin = move || {
+        user.get()
+            .and_then(Result::ok)
+            .flatten()
+            .is_some_and(|user| user.admin)
+    };
+    let refresh = Signal::derive(move || execute_job.version().get());
+    let run_error = move || {
+        execute_job
+            .value()
+            .get()
+            .and_then(Result::err)
+            .map(|err| view! { <ErrorMessage error=AppError::from(err)/> })
+    };
+    let add_error = move || {
+        add_job
+            .value()
+            .get()
+            .and_then(Result::err)
+            .map(|err| view! { <ErrorMessage error=AppError::from(err)/> })
+    };
 
     view! {
         <Suspense fallback=move || view! { <p>"Loading..."</p> }>
             <div>
                 <h2>"Jobs"</h2>
+                {run_error}
                 {move || {
                     jobs.get()
                         .map(|jobs| {
@@ -28,21 +48,17 @@ pub fn home() -> impl IntoView {
                             })
                         })
                 }}
-                <ActionForm class="add-job-form" action=add_job>
-                    <label>"Source" <input type="text" name="source"/></label>
-                    <input type="submit" value="Add"/>
-                </ActionForm>
+                <Show when=admin fallback=|| ()>
+                    <ActionForm class="add-job-form" action=add_job>
+                        <label>"Source" <input type="text" name="source"/></label>
+                        <input type="submit" value="Add"/>
+                    </ActionForm>
+                </Show>
+                {add_error}
             </div>
             <div>
                 <h2>"Executions"</h2>
-                {move || {
-                    executions
-                        .get()
-                        .map(|executions| {
-                            executions.map(|executions| view! { <ExecutionTable executions/> })
-                        })
-                }}
-
+                <ExecutionHistory refresh/>
             </div>
         </Suspense>
     }
@@ -1,33 +1,72 @@
+use super::{id_param, not_found};
+use crate::error_template::ErrorTemplate;
 use crate::server::*;
+use crate::widget::ExecutionHistory;
+use crate::widget::MailForm;
 use crate::widget::StageForm;
 use crate::widget::Stages;
+use crate::widget::StatusBadge;
 use leptos::*;
-use leptos_router::*;
+use osprei_data::Role;
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
 
 #[component]
 pub fn job() -> impl IntoView {
-    let params = use_params_map();
-    let job_id = move || params.with(|p| p.get("id").cloned().unwrap_or_default());
+    let job_id = id_param();
 
     let (dependency, set_dependency) = create_signal(None::<i64>);
 
     let add_stage = create_server_action::<AddStage>();
 
+    // Polls the execution history so new runs show up without a reload
+    let (refresh, set_refresh) = create_signal(0);
+    create_effect(move |_| {
+        refresh.track();
+        set_timeout(
+            move || set_refresh.update(|refresh| *refresh += 1),
+            REFRESH_INTERVAL,
+        );
+    });
+
     let source = create_resource(job_id, |id| async move {
-        load_job_source(id.parse().unwrap()).await
+        match id {
+            Some(id) => load_job_source(id).await,
+            None => Err(not_found("Job")),
+        }
     });
     let status = create_resource(job_id, |id| async move {
-        load_job_status(id.parse().unwrap()).await
+        match id {
+            Some(id) => load_job_status(id).await,
+            None => Ok(None),
+        }
     });
-    let stages = create_resource(job_id, |id| async move {
-        load_stages(id.parse().unwrap()).await
+    let role = create_resource(job_id, |id| async move {
+        match id {
+            Some(id) => load_job_role(id).await,
+            None => Ok(Role::Viewer),
+        }
     });
+    let stages = create_resource(
+        move || (job_id(), add_stage.version().get()),
+        |(id, _)| async move {
+            match id {
+                Some(id) => load_stages(id).await,
+                None => Ok(Vec::new()),
+            }
+        },
+    );
 
     view! {
         <Suspense fallback=move || view! { <p>"Loading..."</p> }>
             <ErrorBoundary fallback=|errors| view! { <ErrorTemplate errors/> }>
                 <p>{move || source.get()}</p>
-                <p>{move || status.get()}</p>
+                {move || {
+                    status
+                        .get()
+                        .map(|status| status.map(|status| view! { <p><StatusBadge status/></p> }))
+                }}
                 {move || {
                     stages
                         .get()
@@ -38,25 +77,33 @@ pub fn job() -> impl IntoView {
                                 })
                         })
                 }}
-                {move || view! { <StageForm dependency=dependency.get() action=add_stage/> }}
+                {move || {
+                    job_id()
+                        .map(|job_id| {
+                            let role = role.get().and_then(Result::ok);
+                            let maintainer = role >= Some(Role::Maintainer);
+                            let stage_form = maintainer
+                                .then(|| {
+                                    let dependency = dependency.get();
+                                    view! { <StageForm job_id dependency action=add_stage/> }
+                                });
+                            let mail_form = maintainer.then(|| view! { <MailForm job_id/> });
+                            view! {
+                                {stage_form}
+                                {mail_form}
+                            }
+                        })
+                }}
+                {move || {
+                    job_id()
+                        .map(|job| {
+                            view! {
+                                <h2>"Executions"</h2>
+                                <ExecutionHistory job refresh/>
+                            }
+                        })
+                }}
             </ErrorBoundary>
         </Suspense>
     }
 }
-
-#[component]
-fn error_template(#[prop(optional)] errors: Option<RwSignal<Errors>>) -> impl IntoView {
-    match errors {
-        Some(errors) => {
-            let errors = move || {
-                errors
-                    .get()
-                    .into_iter()
-                    .map(|error| view! { <li>{error.1.to_string()}</li> })
-                    .collect_view()
-            };
-            view! { <ul>{errors}</ul> }.into_view()
-        }
-        None => view! { <p>"No errors"</p> }.into_view(),
-    }
-}
@@ -0,0 +1,33 @@
+use crate::error_template::{AppError, ErrorMessage};
+use leptos::*;
+use leptos_router::*;
+
+#[component]
+pub fn login() -> impl IntoView {
+    let login = create_server_action::<crate::server::Login>();
+    let error = move || {
+        login
+            .value()
+            .get()
+            .and_then(Result::err)
+            .map(|err| view! { <ErrorMessage error=AppError::from(err)/> })
+    };
+
+    view! {
+        <div>
+            <h2>"Log in"</h2>
+            <ActionForm class="login-form" action=login>
+                <label>
+                    "Username"
+                    <input type="text" name="username" autocomplete="username" required/>
+                </label>
+                <label>
+                    "Password"
+                    <input type="password" name="password" autocomplete="current-password" required/>
+                </label>
+                <input type="submit" value="Log in"/>
+            </ActionForm>
+            {error}
+        </div>
+    }
+}
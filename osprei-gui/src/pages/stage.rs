@@ -0,0 +1,48 @@
+use super::{id_param, not_found};
+use crate::error_template::ErrorTemplate;
+use crate::server::*;
+use crate::widget::LogViewer;
+use leptos::*;
+use leptos_router::*;
+
+#[component]
+pub fn stage() -> impl IntoView {
+    let stage_id = id_param();
+
+    let stage = create_resource(stage_id, |id| async move {
+        match id {
+            Some(id) => load_stage_execution(id).await,
+            None => Err(not_found("Stage execution")),
+        }
+    });
+
+    view! {
+        <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+            <ErrorBoundary fallback=|errors| view! { <ErrorTemplate errors/> }>
+                {move || {
+                    stage
+                        .get()
+                        .map(|stage| {
+                            stage
+                                .map(|stage| {
+                                    let id = stage.id;
+                                    view! {
+                                        <h2>{stage.name}</h2>
+                                        <p>
+                                            <A href=format!("/execution/{}", stage.execution)>
+                                                "Back to execution"
+                                            </A>
+                                            " | "
+                                            <a href=format!("/logs/{id}") rel="external">
+                                                "Raw"
+                                            </a>
+                                        </p>
+                                        <LogViewer stage_id=id/>
+                                    }
+                                })
+                        })
+                }}
+            </ErrorBoundary>
+        </Suspense>
+    }
+}
@@ -0,0 +1,67 @@
+use crate::error_template::{AppError, ErrorMessage};
+use crate::server::*;
+use crate::widget::*;
+use leptos::*;
+use leptos_router::*;
+use osprei_data::TokenScope;
+
+#[component]
+pub fn tokens() -> impl IntoView {
+    let create_token = create_server_action::<CreateToken>();
+    let revoke_token = create_server_action::<RevokeToken>();
+    let tokens = create_resource(
+        move || (create_token.version().get(), revoke_token.version().get()),
+        |_| load_tokens(),
+    );
+    let scopes = TokenScope::ALL
+        .into_iter()
+        .map(|scope| view! { <option value=scope.key()>{scope.to_string()}</option> })
+        .collect_view();
+    let created = move || match create_token.value().get()? {
+        Ok(secret) => Some(
+            view! {
+                <div class="new-token">
+                    <p>"Copy the token now, it will not be shown again:"</p>
+                    <code>{secret}</code>
+                </div>
+            }
+            .into_view(),
+        ),
+        Err(err) => Some(view! { <ErrorMessage error=AppError::from(err)/> }),
+    };
+    let revoke_error = move || {
+        revoke_token
+            .value()
+            .get()
+            .and_then(Result::err)
+            .map(|err| view! { <ErrorMessage error=AppError::from(err)/> })
+    };
+
+    view! {
+        <div>
+            <h2>"API tokens"</h2>
+            <p>"Scripts send a token as " <code>"Authorization: Bearer <token>"</code> "."</p>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                {move || {
+                    tokens
+                        .get()
+                        .map(|tokens| {
+                            tokens.map(|tokens| view! { <TokenTable tokens action=revoke_token/> })
+                        })
+                }}
+            </Transition>
+            {revoke_error}
+            <h3>"New token"</h3>
+            <ActionForm class="token-form" action=create_token>
+                <label>"Name" <input type="text" name="name" required/></label>
+                <label>"Scope" <select name="scope">{scopes}</select></label>
+                <label>
+                    "Jobs" <input type="text" name="jobs" placeholder="All, or ids such as 1, 3"/>
+                </label>
+                <label>"Service token" <input type="checkbox" name="service" value="on"/></label>
+                <input type="submit" value="Create"/>
+            </ActionForm>
+            {created}
+        </div>
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error_template::{AppError, ErrorMessage};
+use crate::server::*;
+use crate::widget::*;
+use leptos::*;
+
+#[component]
+pub fn webhooks() -> impl IntoView {
+    let add_webhook = create_server_action::<AddWebhook>();
+    let test_webhook = create_server_action::<TestWebhook>();
+    let delete_webhook = create_server_action::<DeleteWebhook>();
+    let webhooks = create_resource(
+        move || {
+            (
+                add_webhook.version().get(),
+                test_webhook.version().get(),
+                delete_webhook.version().get(),
+            )
+        },
+        |_| load_webhooks(),
+    );
+    let tested = move || match test_webhook.value().get()? {
+        Ok(Delivery { error: None, .. }) => {
+            Some(view! { <p>"Test notification delivered."</p> }.into_view())
+        }
+        Ok(Delivery {
+            error: Some(error), ..
+        }) => Some(
+            view! { <p class="error-message">"Test notification failed: " {error}</p> }.into_view(),
+        ),
+        Err(err) => Some(view! { <ErrorMessage error=AppError::from(err)/> }),
+    };
+    let errors = move || {
+        [add_webhook.value().get(), delete_webhook.value().get()]
+            .into_iter()
+            .flatten()
+            .filter_map(Result::err)
+            .map(|err| view! { <ErrorMessage error=AppError::from(err)/> })
+            .collect_view()
+    };
+
+    view! {
+        <div>
+            <h2>"Webhooks"</h2>
+            <p>
+                "A JSON notification is posted to each webhook when an execution of its job "
+                "finishes."
+            </p>
+            {tested}
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                {move || {
+                    webhooks
+                        .get()
+                        .map(|webhooks| {
+                            webhooks
+                                .map(|webhooks| {
+                                    view! {
+                                        <WebhookList
+                                            webhooks
+                                            test=test_webhook
+                                            delete=delete_webhook
+                                        />
+                                    }
+                                })
+                        })
+                }}
+            </Transition>
+            {errors}
+            <h3>"New webhook"</h3>
+            <leptos_router::ActionForm class="token-form" action=add_webhook>
+                <label>"Url" <input type="url" name="url" required/></label>
+                <label>
+                    "Job" <input type="text" name="job" placeholder="Every job, or an id"/>
+                </label>
+                <input type="submit" value="Add"/>
+            </leptos_router::ActionForm>
+        </div>
+    }
+}
@@ -0,0 +1,135 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use osprei_data::Role;
+    use osprei_storage::{Grant, Storage, User};
+    use crate::error_template::AppError;
+    use crate::server::missing;
+
+    /// Roles granted to a user, looked up once per request.
+    pub struct Permissions {
+        /// Administrators have every role on every job.
+        admin: bool,
+        grants: Vec<Grant>,
+    }
+
+    impl Permissions {
+        pub async fn load(storage: &dyn Storage, user: &User) -> Result<Permissions, AppError> {
+            let grants = if user.admin {
+                Vec::new()
+            } else {
+                storage.user_grants(user.id).await?
+            };
+            Ok(Permissions {
+                admin: user.admin,
+                grants,
+            })
+        }
+
+        pub fn is_admin(&self) -> bool {
+            self.admin
+        }
+
+        /// Role on a job of the group, `None` when the user cannot see it.
+        pub fn role(&self, job: i64, group: Option<i64>) -> Option<Role> {
+            if self.admin {
+                return Some(Role::Admin);
+            }
+            self.grants
+                .iter()
+                .filter(|grant| grant.job == Some(job) || (group.is_some() && grant.group == group))
+                .map(|grant| grant.role)
+                .max()
+        }
+
+        /// Role on every job of the group.
+        pub fn group_role(&self, group: i64) -> Option<Role> {
+            if self.admin {
+                return Some(Role::Admin);
+            }
+            self.grants
+                .iter()
+                .filter(|grant| grant.group == Some(group))
+                .map(|grant| grant.role)
+                .max()
+        }
+
+        /// Highest role on any job or group.
+        pub fn highest_role(&self) -> Option<Role> {
+            if self.admin {
+                return Some(Role::Admin);
+            }
+            self.grants.iter().map(|grant| grant.role).max()
+        }
+
+        /// Role on the job, failing with a 403 when it is below `required`.
+        pub async fn require(
+            &self,
+            storage: &dyn Storage,
+            job: i64,
+            required: Role,
+        ) -> Result<Role, AppError> {
+            let group = storage
+                .job_group(job)
+                .await
+                .map_err(missing(format!("Job {job}")))?;
+            match self.role(job, group) {
+                Some(role) if role >= required => Ok(role),
+                _ => Err(forbidden(required, &format!("job {job}"))),
+            }
+        }
+
+        /// Role on the job of the execution, failing with a 403 when it is below `required`.
+        pub async fn require_for_execution(
+            &self,
+            storage: &dyn Storage,
+            execution: i64,
+            required: Role,
+        ) -> Result<Role, AppError> {
+            let job = storage
+                .execution(execution)
+                .await
+                .map_err(missing(format!("Execution {execution}")))?
+                .job;
+            self.require(storage, job, required).await
+        }
+
+        /// Fails with a 403 unless the user has the role on some job or group.
+        pub fn require_any(&self, required: Role) -> Result<(), AppError> {
+            match self.highest_role() {
+                Some(role) if role >= required => Ok(()),
+                _ => Err(forbidden(required, "any job")),
+            }
+        }
+
+        pub fn require_admin(&self) -> Result<(), AppError> {
+            if self.admin {
+                Ok(())
+            } else {
+                Err(AppError::forbidden("Only administrators can do that"))
+            }
+        }
+
+        /// Jobs the user can see, `None` for administrators who see them all.
+        pub async fn visible_jobs(
+            &self,
+            storage: &dyn Storage,
+        ) -> Result<Option<Vec<i64>>, AppError> {
+            if self.admin {
+                return Ok(None);
+            }
+            let jobs = storage
+                .job_groups()
+                .await?
+                .into_iter()
+                .filter(|&(id, group)| self.role(id, group).is_some())
+                .map(|(id, _)| id)
+                .collect();
+            Ok(Some(jobs))
+        }
+    }
+
+    fn forbidden(required: Role, on: &str) -> AppError {
+        AppError::forbidden(format!("You need the {} role on {on}", required.key()))
+    }
+}}
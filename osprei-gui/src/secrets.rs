@@ -0,0 +1,38 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use std::path::PathBuf;
+
+    /// Secrets kept as files of a directory, each named after its secret.
+    #[derive(Debug, Clone)]
+    pub struct Secrets {
+        path: PathBuf,
+    }
+
+    impl Secrets {
+        pub fn new(path: impl Into<PathBuf>) -> Secrets {
+            Secrets { path: path.into() }
+        }
+
+        /// Value of the secret, without the line break files usually end with. Read on every
+        /// call so secrets can be rotated without a restart.
+        pub async fn get(&self, name: &str) -> std::io::Result<String> {
+            if !is_valid_name(name) {
+                let message = format!("invalid secret name {name:?}");
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, message));
+            }
+            let value = tokio::fs::read_to_string(self.path.join(name)).await?;
+            Ok(value.trim_end_matches(['\r', '\n']).to_string())
+        }
+    }
+
+    /// Whether the name can only refer to a file right inside the secrets directory.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name != "."
+            && name != ".."
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    }
+}}
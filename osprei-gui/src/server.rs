@@ -1,54 +1,228 @@
+#[cfg(feature = "ssr")]
+use crate::error_template::AppError;
 use crate::widget;
 use leptos::server;
 use leptos::ServerFnError;
+use osprei_data::{ExecutionStatus, Role};
 
-const SQLX_IMAGE: &str = "ghcr.io/musergi/sqlx:latest";
-const RUST_IMAGE: &str = "rust:latest";
+/// Storage handle provided to every server function.
+#[cfg(feature = "ssr")]
+fn storage() -> Result<osprei_storage::SharedStorage, ServerFnError> {
+    leptos::use_context::<osprei_storage::SharedStorage>()
+        .ok_or_else(|| ServerFnError::ServerError("storage not available".to_string()))
+}
 
-#[server]
-pub async fn load_jobs() -> Result<Vec<widget::Job>, ServerFnError> {
-    let ids = osprei_storage::job::ids().await?;
-    let mut jobs = Vec::new();
-    for id in ids {
-        let source = load_job_source(id).await?;
-        let status = load_job_status(id).await?;
-        let job = widget::Job { id, source, status };
-        jobs.push(job);
+/// Runner provided to every server function.
+#[cfg(feature = "ssr")]
+fn runner() -> Result<Runner, ServerFnError> {
+    leptos::use_context::<Runner>()
+        .ok_or_else(|| ServerFnError::ServerError("runner not available".to_string()))
+}
+
+/// Session settings provided to every server function.
+#[cfg(feature = "ssr")]
+fn sessions() -> Result<crate::auth::Sessions, ServerFnError> {
+    leptos::use_context::<crate::auth::Sessions>()
+        .ok_or_else(|| ServerFnError::ServerError("sessions not available".to_string()))
+}
+
+/// User of the session of the request, checked first by every server function.
+#[cfg(feature = "ssr")]
+pub(crate) async fn user() -> Result<osprei_storage::User, ServerFnError> {
+    let storage = storage()?;
+    let headers = leptos::use_context::<leptos_axum::RequestParts>()
+        .map(|parts| parts.headers)
+        .unwrap_or_default();
+    Ok(crate::auth::authenticate(&*storage, &headers).await?)
+}
+
+/// Roles of the user of the request.
+#[cfg(feature = "ssr")]
+pub(crate) async fn permissions() -> Result<crate::permissions::Permissions, ServerFnError> {
+    let user = user().await?;
+    Ok(crate::permissions::Permissions::load(&*storage()?, &user).await?)
+}
+
+/// Starts executions with the configured settings, a limited number at once.
+#[cfg(feature = "ssr")]
+#[derive(Clone)]
+pub struct Runner {
+    pub settings: osprei_execution::Settings,
+    pub artifact_retention: i64,
+    pub notifier: crate::notifications::Notifier,
+    slots: std::sync::Arc<tokio::sync::Semaphore>,
+}
+
+#[cfg(feature = "ssr")]
+impl Runner {
+    pub fn new(config: &crate::config::Config) -> Runner {
+        Runner {
+            settings: config.execution_settings(),
+            artifact_retention: config.artifact_retention,
+            notifier: crate::notifications::Notifier::new(config),
+            slots: std::sync::Arc::new(tokio::sync::Semaphore::new(
+                config.max_concurrent_executions,
+            )),
+        }
+    }
+}
+
+/// Converts storage errors, naming what was looked up when it does not exist.
+#[cfg(feature = "ssr")]
+pub(crate) fn missing(what: String) -> impl FnOnce(osprei_storage::Error) -> AppError {
+    move |err| match err {
+        osprei_storage::Error::NotFound => AppError::not_found(format!("{what} does not exist")),
+        err => err.into(),
     }
-    Ok(jobs)
 }
 
 #[server]
-pub async fn load_executions() -> Result<Vec<widget::Execution>, ServerFnError> {
-    let ids = load_execution_list().await?;
-    let mut executions = Vec::new();
-    for id in ids {
-        let status = load_execution_status(id).await?;
-        let duration = load_execution_duration(id).await?;
-        let execution = widget::Execution {
+pub async fn load_jobs() -> Result<Vec<widget::Job>, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let groups = storage.job_groups().await.map_err(AppError::from)?;
+    let mut jobs = Vec::new();
+    for (id, group) in groups {
+        let Some(role) = permissions.role(id, group) else {
+            continue;
+        };
+        let source = storage.job_source(id).await.map_err(AppError::from)?;
+        let status = storage.job_status(id).await.map_err(AppError::from)?;
+        let last_execution = storage
+            .job_last_execution(id)
+            .await
+            .map_err(AppError::from)?;
+        let last_run = match last_execution {
+            Some(execution) => {
+                storage
+                    .execution(execution)
+                    .await
+                    .map_err(AppError::from)?
+                    .start_time
+            }
+            None => None,
+        };
+        let job = widget::Job {
             id,
+            source,
             status,
-            duration,
+            last_execution,
+            last_run,
+            role,
         };
-        executions.push(execution);
+        jobs.push(job);
     }
-    Ok(executions)
+    Ok(jobs)
+}
+
+#[server(LoadExecutions, "/api", "Cbor")]
+pub async fn load_executions(
+    filter: widget::ExecutionFilter,
+    page: i64,
+) -> Result<widget::ExecutionPage, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let widget::ExecutionFilter {
+        job,
+        status,
+        git_ref,
+        since,
+        until,
+    } = filter;
+    let jobs = match job {
+        Some(job) => {
+            permissions.require(&*storage, job, Role::Viewer).await?;
+            None
+        }
+        None => permissions.visible_jobs(&*storage).await?,
+    };
+    let filter = osprei_storage::Filter {
+        job,
+        status,
+        git_ref,
+        since,
+        until,
+        jobs,
+    };
+    let count = storage
+        .count_executions(&filter)
+        .await
+        .map_err(AppError::from)?;
+    let pages = (count + widget::EXECUTION_PAGE_SIZE - 1) / widget::EXECUTION_PAGE_SIZE;
+    let page = page.clamp(0, (pages - 1).max(0));
+    let executions = storage
+        .list_executions(
+            &filter,
+            widget::EXECUTION_PAGE_SIZE,
+            page * widget::EXECUTION_PAGE_SIZE,
+        )
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .map(
+            |osprei_storage::Execution {
+                 id,
+                 job,
+                 source,
+                 git_ref,
+                 commit_hash,
+                 start_time,
+                 status,
+                 duration,
+                 ..
+             }| widget::Execution {
+                id,
+                job_id: job,
+                source,
+                status,
+                git_ref,
+                commit: commit_hash,
+                start_time,
+                duration,
+            },
+        )
+        .collect();
+    Ok(widget::ExecutionPage {
+        executions,
+        page,
+        pages,
+    })
 }
 
 #[server]
 pub async fn load_stages(job_id: i64) -> Result<Vec<widget::Stage>, ServerFnError> {
-    let stages = osprei_storage::stages::for_job(job_id)
-        .await?
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    permissions.require(&*storage, job_id, Role::Viewer).await?;
+    let latest = storage
+        .latest_stage_executions(job_id)
+        .await
+        .map_err(AppError::from)?;
+    let stages = storage
+        .stages_for_job(job_id)
+        .await
+        .map_err(AppError::from)?
         .into_iter()
         .map(
             |osprei_storage::Stage {
                  id,
                  dependency,
                  definition,
-             }| widget::Stage {
-                id,
-                dependency,
-                description: definition.name,
+             }| {
+                let execution = latest
+                    .iter()
+                    .find(|execution| execution.stage == Some(id))
+                    .map(|execution| {
+                        let status = execution.status.unwrap_or(ExecutionStatus::Queued);
+                        (execution.id, status)
+                    });
+                widget::Stage {
+                    id,
+                    dependency,
+                    description: definition.name,
+                    last_execution: execution.as_ref().map(|(id, _)| *id),
+                    status: execution.map(|(_, status)| status),
+                }
             },
         )
         .collect();
@@ -57,110 +231,1570 @@ pub async fn load_stages(job_id: i64) -> Result<Vec<widget::Stage>, ServerFnErro
 
 #[server]
 pub async fn load_job_list() -> Result<Vec<i64>, ServerFnError> {
-    let jobs = osprei_storage::job::ids().await?;
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let jobs = match permissions.visible_jobs(&*storage).await? {
+        Some(jobs) => jobs,
+        None => storage.job_ids().await.map_err(AppError::from)?,
+    };
     Ok(jobs)
 }
 
-#[server(AddStage)]
+#[server(AddStage, "/api", "Cbor")]
 pub async fn add_stage(
     job_id: i64,
-    name: String,
     dependency: i64,
-    template: String,
+    definition: osprei_data::StageDefinition,
 ) -> Result<(), ServerFnError> {
-    log::info!("AddStage id:{job_id} name:{name} depends_on:{dependency} template:{template}");
-    let osprei_data::Template {
-        image, environment, ..
-    } = osprei_storage::templates::for_name(template)
-        .await
-        .map_err(|err| {
-            log::error!("Error fetching template: {err}");
-            err
-        })?;
-    let definition = osprei_data::StageDefinition {
-        name,
-        image,
-        environment,
-        working_dir: osprei_storage::stages::CHECKOUT_DIR.to_string(),
-    };
-    osprei_storage::stages::create(job_id, dependency, definition).await?;
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    permissions
+        .require(&*storage, job_id, Role::Maintainer)
+        .await?;
+    log::info!(
+        "AddStage id:{job_id} name:{} depends_on:{dependency}",
+        definition.name
+    );
+    let errors = definition.validate();
+    if !errors.is_empty() {
+        let message = errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(AppError::validation(message).into());
+    }
+    let stages = storage
+        .stages_for_job(job_id)
+        .await
+        .map_err(AppError::from)?;
+    if !stages.iter().any(|stage| stage.id == dependency) {
+        let message = format!("dependency: stage {dependency} is not part of job {job_id}");
+        return Err(AppError::validation(message).into());
+    }
+    storage
+        .create_stage(job_id, Some(dependency), &definition)
+        .await
+        .map_err(AppError::from)?;
     Ok(())
 }
 
+#[server]
+pub async fn load_template(name: String) -> Result<osprei_data::Template, ServerFnError> {
+    permissions().await?.require_any(Role::Maintainer)?;
+    let storage = storage()?;
+    let template = storage
+        .template(&name)
+        .await
+        .map_err(missing(format!("Template {name}")))?;
+    Ok(template)
+}
+
 #[server]
 pub async fn load_templates() -> Result<Vec<String>, ServerFnError> {
-    let templates = osprei_storage::templates::names().await?;
+    permissions().await?.require_any(Role::Maintainer)?;
+    let storage = storage()?;
+    let templates = storage.template_names().await.map_err(AppError::from)?;
     Ok(templates)
 }
 
 #[server(AddJob)]
 pub async fn add_job(source: String) -> Result<(), ServerFnError> {
-    osprei_storage::job::create(source).await?;
+    permissions().await?.require_admin()?;
+    let storage = storage()?;
+    osprei_data::validate_source(&source).map_err(|err| AppError::validation(err.to_string()))?;
+    storage.create_job(source).await.map_err(AppError::from)?;
     Ok(())
 }
 
 #[server(ExecuteJob)]
 pub async fn execute_job(job_id: i64) -> Result<(), ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    permissions.require(&*storage, job_id, Role::Runner).await?;
+    let runner = runner()?;
     log::info!("Running job with id {}", job_id);
-    let stages: Vec<_> = osprei_storage::stages::for_job(job_id)
-        .await?
-        .into_iter()
-        .map(|stage| stage.definition)
+    start_execution(runner, storage, job_id, "manual", None).await?;
+    Ok(())
+}
+
+/// Records a new execution of the job and runs it in the background, once
+/// Docker answers.
+#[cfg(feature = "ssr")]
+pub(crate) async fn start_execution(
+    runner: Runner,
+    storage: osprei_storage::SharedStorage,
+    job_id: i64,
+    triggered_by: &str,
+    git_ref: Option<&str>,
+) -> Result<i64, AppError> {
+    osprei_execution::check(&runner.settings).await?;
+    let (execution_id, stages) = prepare_execution(&*storage, job_id, triggered_by, git_ref)
+        .await
+        .map_err(missing(format!("Job {job_id}")))?;
+    tokio::spawn(run_execution(runner, storage, job_id, execution_id, stages));
+    Ok(execution_id)
+}
+
+/// Creates an execution of the job with a pending entry per stage, returning
+/// the stages to run.
+///
+/// The checkout stage is pointed at `git_ref` when set.
+#[cfg(feature = "ssr")]
+async fn prepare_execution(
+    storage: &dyn osprei_storage::Storage,
+    job_id: i64,
+    triggered_by: &str,
+    git_ref: Option<&str>,
+) -> Result<(i64, Vec<osprei_data::StageDefinition>), osprei_storage::Error> {
+    storage.job_source(job_id).await?;
+    let mut stages = storage.stages_for_job(job_id).await?;
+    if let Some(git_ref) = git_ref {
+        for stage in stages.iter_mut().filter(|stage| stage.dependency.is_none()) {
+            osprei_storage::stages::with_ref(&mut stage.definition, git_ref);
+        }
+    }
+    let execution_id = storage
+        .create_execution(job_id, triggered_by, git_ref)
+        .await?;
+    let names = stages
+        .iter()
+        .map(|stage| (stage.id, stage.definition.name.clone()))
         .collect();
-    let execution_id = osprei_storage::execution::create(job_id).await?;
-    tokio::spawn(async move {
-        match osprei_execution::execute(stages).await {
-            Ok(()) => {
-                let _ = osprei_storage::execution::success(execution_id).await;
+    storage.create_stage_executions(execution_id, names).await?;
+    let stages = stages.into_iter().map(|stage| stage.definition).collect();
+    Ok((execution_id, stages))
+}
+
+#[cfg(feature = "ssr")]
+async fn run_execution(
+    runner: Runner,
+    storage: osprei_storage::SharedStorage,
+    job_id: i64,
+    execution_id: i64,
+    stages: Vec<osprei_data::StageDefinition>,
+) {
+    crate::metrics::execution_queued();
+    let _slot = runner.slots.acquire().await;
+    crate::metrics::execution_started();
+    if let Err(err) = storage.set_execution_running(execution_id).await {
+        log::error!("Error marking execution ({execution_id}) as running: {err}");
+    }
+    let output = osprei_execution::Output {
+        artifact_dir: osprei_storage::artifacts::directory(execution_id),
+        log_dir: osprei_storage::stage_execution::log_directory(execution_id),
+    };
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let execution = osprei_execution::execute(&runner.settings, stages, output, sender);
+    let retention = runner.artifact_retention;
+    track_execution(
+        storage,
+        retention,
+        runner.notifier.clone(),
+        job_id,
+        execution_id,
+        receiver,
+        execution,
+    )
+    .await;
+    crate::metrics::execution_stopped();
+}
+
+/// Records the events and outcome of a running execution, then collects its
+/// artifacts.
+///
+/// Returns the task notifying the webhooks, which may retry for a while.
+#[cfg(feature = "ssr")]
+async fn track_execution(
+    storage: osprei_storage::SharedStorage,
+    artifact_retention: i64,
+    notifier: crate::notifications::Notifier,
+    job_id: i64,
+    execution_id: i64,
+    mut events: tokio::sync::mpsc::UnboundedReceiver<osprei_execution::Event>,
+    execution: impl std::future::Future<Output = Result<(), osprei_execution::Error>>,
+) -> tokio::task::JoinHandle<()> {
+    let started = std::time::Instant::now();
+    let recorder_storage = storage.clone();
+    let recorder_notifier = notifier.clone();
+    let recorder = tokio::spawn(async move {
+        let mut author = None;
+        let mut pending = None;
+        let mut stages_started = std::collections::HashMap::new();
+        let mut stage_durations = Vec::new();
+        while let Some(event) = events.recv().await {
+            match &event {
+                osprei_execution::Event::Commit(commit) => {
+                    let storage = recorder_storage.clone();
+                    let notifier = recorder_notifier.clone();
+                    let commit = commit.clone();
+                    pending = Some(tokio::spawn(async move {
+                        notifier
+                            .commit_found(&*storage, execution_id, &commit)
+                            .await
+                    }));
+                }
+                osprei_execution::Event::Author(email) => author = Some(email.clone()),
+                osprei_execution::Event::StageStarted(position) => {
+                    stages_started.insert(*position, std::time::Instant::now());
+                }
+                osprei_execution::Event::StageFinished(position, _) => {
+                    if let Some(start) = stages_started.remove(position) {
+                        stage_durations.push((*position as i64, start.elapsed()));
+                    }
+                }
             }
-            Err(err) => {
-                log::error!("Execution error: {err}");
-                let _ = osprei_storage::execution::failure(execution_id).await;
+            if let Err(err) = record_event(&*recorder_storage, execution_id, event).await {
+                log::error!("Error recording execution event: {err}");
             }
         }
+        // The pending status must reach the forge before the final one.
+        if let Some(pending) = pending {
+            let _ = pending.await;
+        }
+        (author, stage_durations)
     });
-    Ok(())
+    let status = match execution.await {
+        Ok(()) => ExecutionStatus::Success,
+        Err(err) => {
+            log::error!("Execution error: {err}");
+            match err {
+                osprei_execution::Error::TimedOut => ExecutionStatus::TimedOut,
+                _ => ExecutionStatus::Failure,
+            }
+        }
+    };
+    let _ = storage.finish_execution(execution_id, status).await;
+    crate::metrics::execution_finished(job_id, status, started.elapsed());
+    let (author, stage_durations) = recorder.await.unwrap_or_default();
+    if let Ok(stages) = storage.stage_executions(execution_id).await {
+        for (position, duration) in stage_durations {
+            if let Some(stage) = stages.iter().find(|stage| stage.position == position) {
+                crate::metrics::stage_finished(job_id, stage.name.clone(), duration);
+            }
+        }
+    }
+    if let Err(err) = osprei_storage::artifacts::record(&*storage, execution_id).await {
+        log::error!("Error recording artifacts: {err}");
+    }
+    if let Err(err) = osprei_storage::artifacts::prune(&*storage, job_id, artifact_retention).await
+    {
+        log::error!("Error pruning artifacts: {err}");
+    }
+    tokio::spawn(async move {
+        notifier
+            .execution_finished(&*storage, job_id, execution_id, author.as_deref())
+            .await
+    })
+}
+
+#[cfg(feature = "ssr")]
+async fn record_event(
+    storage: &dyn osprei_storage::Storage,
+    execution_id: i64,
+    event: osprei_execution::Event,
+) -> Result<(), osprei_storage::Error> {
+    match event {
+        osprei_execution::Event::Commit(commit) => {
+            storage.set_execution_commit(execution_id, &commit).await
+        }
+        // Only handed to the notifier once the execution finishes.
+        osprei_execution::Event::Author(_) => Ok(()),
+        osprei_execution::Event::StageStarted(position) => {
+            storage
+                .start_stage_execution(execution_id, position as i64)
+                .await
+        }
+        osprei_execution::Event::StageFinished(position, success) => {
+            let status = if success {
+                ExecutionStatus::Success
+            } else {
+                ExecutionStatus::Failure
+            };
+            storage
+                .finish_stage_execution(execution_id, position as i64, status)
+                .await
+        }
+    }
+}
+
+#[server]
+pub async fn load_artifacts(execution_id: i64) -> Result<Vec<widget::Artifact>, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    permissions
+        .require_for_execution(&*storage, execution_id, Role::Viewer)
+        .await?;
+    let artifacts = storage
+        .artifacts(execution_id)
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .map(|osprei_storage::Artifact { id, path, size, .. }| widget::Artifact { id, path, size })
+        .collect();
+    Ok(artifacts)
 }
 
 #[server]
 pub async fn load_job_source(id: i64) -> Result<String, ServerFnError> {
-    let source = osprei_storage::job::source(id).await?;
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    permissions.require(&*storage, id, Role::Viewer).await?;
+    let source = storage
+        .job_source(id)
+        .await
+        .map_err(missing(format!("Job {id}")))?;
     Ok(source)
 }
 
 #[server]
-pub async fn load_job_status(id: i64) -> Result<String, ServerFnError> {
-    let status = osprei_storage::job::status(id).await?;
-    let message = match status {
-        None => "Not executed".to_string(),
-        Some(osprei_storage::ExecutionStatus::Running) => "Running".to_string(),
-        Some(osprei_storage::ExecutionStatus::Success) => "Success".to_string(),
-        Some(osprei_storage::ExecutionStatus::Failure) => "Failure".to_string(),
-        Some(osprei_storage::ExecutionStatus::Unknown) => "Unknown".to_string(),
+pub async fn load_job_status(id: i64) -> Result<Option<ExecutionStatus>, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    permissions.require(&*storage, id, Role::Viewer).await?;
+    let status = storage.job_status(id).await.map_err(AppError::from)?;
+    Ok(status)
+}
+
+#[server]
+pub async fn load_stage_execution(id: i64) -> Result<widget::StageExecution, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let stage = storage
+        .stage_execution(id)
+        .await
+        .map_err(missing(format!("Stage execution {id}")))?;
+    permissions
+        .require_for_execution(&*storage, stage.execution, Role::Viewer)
+        .await?;
+    Ok(stage_execution_widget(stage))
+}
+
+#[cfg(feature = "ssr")]
+fn stage_execution_widget(stage: osprei_storage::StageExecution) -> widget::StageExecution {
+    let osprei_storage::StageExecution {
+        id,
+        execution,
+        name,
+        status,
+        duration,
+        ..
+    } = stage;
+    widget::StageExecution {
+        id,
+        execution,
+        name,
+        started: status.is_some(),
+        status: status.unwrap_or(ExecutionStatus::Queued),
+        duration,
+    }
+}
+
+#[server]
+pub async fn load_execution(id: i64) -> Result<widget::ExecutionDetail, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    permissions
+        .require_for_execution(&*storage, id, Role::Viewer)
+        .await?;
+    let osprei_storage::Execution {
+        id,
+        job,
+        source,
+        triggered_by,
+        commit_hash,
+        start_time,
+        end_time,
+        status,
+        duration,
+        ..
+    } = storage
+        .execution(id)
+        .await
+        .map_err(missing(format!("Execution {id}")))?;
+    let stages = storage
+        .stage_executions(id)
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .map(stage_execution_widget)
+        .collect();
+    Ok(widget::ExecutionDetail {
+        id,
+        job_id: job,
+        source,
+        triggered_by,
+        commit: commit_hash,
+        start_time,
+        end_time,
+        status,
+        duration,
+        stages,
+    })
+}
+
+#[server(Login)]
+pub async fn login(username: String, password: String) -> Result<(), ServerFnError> {
+    let storage = storage()?;
+    let sessions = sessions()?;
+    let user = match storage.user_by_name(&username).await {
+        Ok(user) => Some(user),
+        Err(osprei_storage::Error::NotFound) => None,
+        Err(err) => return Err(AppError::from(err).into()),
+    };
+    let password_hash = user.as_ref().map(|user| user.password_hash.clone());
+    // Hashing takes a while, so it runs off the async workers
+    let valid = tokio::task::spawn_blocking(move || {
+        let password_hash = match &password_hash {
+            Some(password_hash) => password_hash,
+            None => crate::auth::dummy_password_hash(),
+        };
+        osprei_storage::users::verify_password(&password, password_hash)
+    })
+    .await
+    .map_err(|err| AppError::new(osprei_data::ErrorCode::Internal, err.to_string()))?;
+    let Some(user) = user.filter(|_| valid) else {
+        log::warn!("Failed login of {username}");
+        return Err(AppError::unauthorized("Invalid username or password").into());
+    };
+    let token = osprei_storage::users::new_token();
+    storage
+        .create_session(
+            &osprei_storage::users::hash_token(&token),
+            user.id,
+            sessions.lifetime,
+        )
+        .await
+        .map_err(AppError::from)?;
+    log::info!("Logged in {username}");
+    set_cookie(&sessions.cookie(&token));
+    leptos_axum::redirect("/");
+    Ok(())
+}
+
+#[server(Logout)]
+pub async fn logout() -> Result<(), ServerFnError> {
+    let storage = storage()?;
+    let sessions = sessions()?;
+    let headers = leptos::use_context::<leptos_axum::RequestParts>()
+        .map(|parts| parts.headers)
+        .unwrap_or_default();
+    if let Some(token) = crate::auth::session_token(&headers) {
+        storage
+            .delete_session(&osprei_storage::users::hash_token(token))
+            .await
+            .map_err(AppError::from)?;
+    }
+    set_cookie(&sessions.cleared_cookie());
+    leptos_axum::redirect(crate::auth::LOGIN_PATH);
+    Ok(())
+}
+
+/// The logged in user, `None` without a valid session.
+#[server]
+pub async fn load_current_user() -> Result<Option<widget::CurrentUser>, ServerFnError> {
+    let user = match user().await {
+        Ok(user) => user,
+        Err(err) => match AppError::from(err) {
+            err if err.code == osprei_data::ErrorCode::Unauthorized => return Ok(None),
+            err => return Err(err.into()),
+        },
+    };
+    let permissions = crate::permissions::Permissions::load(&*storage()?, &user).await?;
+    Ok(Some(widget::CurrentUser {
+        username: user.username,
+        admin: user.admin,
+        manages_access: permissions.highest_role() == Some(Role::Admin),
+        manages_webhooks: permissions.highest_role() >= Some(Role::Maintainer),
+    }))
+}
+
+/// Role of the user on the job, to only show the actions they can perform.
+#[server]
+pub async fn load_job_role(id: i64) -> Result<Role, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    Ok(permissions.require(&*storage, id, Role::Viewer).await?)
+}
+
+/// Groups, jobs and grants the user can grant roles on.
+#[server]
+pub async fn load_access() -> Result<widget::Access, ServerFnError> {
+    let permissions = permissions().await?;
+    permissions.require_any(Role::Admin)?;
+    let storage = storage()?;
+    let groups: Vec<_> = storage
+        .groups()
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .filter(|group| permissions.group_role(group.id) == Some(Role::Admin))
+        .map(|osprei_storage::Group { id, name }| widget::Group { id, name })
+        .collect();
+    let mut jobs = Vec::new();
+    for (id, group) in storage.job_groups().await.map_err(AppError::from)? {
+        if permissions.role(id, group) == Some(Role::Admin) {
+            let source = storage.job_source(id).await.map_err(AppError::from)?;
+            jobs.push(widget::AccessJob { id, source, group });
+        }
+    }
+    let grants = storage
+        .grants()
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .filter(|grant| {
+            grant
+                .job
+                .is_some_and(|job| jobs.iter().any(|found| found.id == job))
+                || grant
+                    .group
+                    .is_some_and(|group| groups.iter().any(|found| found.id == group))
+        })
+        .map(
+            |osprei_storage::Grant {
+                 id,
+                 username,
+                 job,
+                 group,
+                 role,
+                 ..
+             }| widget::Grant {
+                id,
+                username,
+                job,
+                group,
+                role,
+            },
+        )
+        .collect();
+    Ok(widget::Access {
+        admin: permissions.is_admin(),
+        groups,
+        jobs,
+        grants,
+    })
+}
+
+#[server(CreateGroup)]
+pub async fn create_group(name: String) -> Result<(), ServerFnError> {
+    permissions().await?.require_admin()?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("name: name is required").into());
+    }
+    storage()?
+        .create_group(name)
+        .await
+        .map_err(AppError::from)?;
+    Ok(())
+}
+
+/// Moves the job to the group with the id in `group`, or out of any group when
+/// it is empty.
+#[server(SetJobGroup)]
+pub async fn set_job_group(job_id: i64, group: String) -> Result<(), ServerFnError> {
+    permissions().await?.require_admin()?;
+    let group =
+        match group.as_str() {
+            "" => None,
+            group => Some(group.parse::<i64>().map_err(|_| {
+                AppError::validation(format!("group: {group:?} is not a group id"))
+            })?),
+        };
+    storage()?
+        .set_job_group(job_id, group)
+        .await
+        .map_err(missing(format!("Job {job_id}")))?;
+    Ok(())
+}
+
+/// Grants the role to the user on `target`, written as `job:<id>` or
+/// `group:<id>`.
+#[server(GrantRole)]
+pub async fn grant_role(
+    username: String,
+    target: String,
+    role: String,
+) -> Result<(), ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let (job, group) = match target.split_once(':') {
+        Some(("job", id)) => (id.parse::<i64>().ok(), None),
+        Some(("group", id)) => (None, id.parse::<i64>().ok()),
+        _ => (None, None),
     };
-    Ok(message)
+    if job.is_none() && group.is_none() {
+        return Err(AppError::validation(format!("target: unknown target {target:?}")).into());
+    }
+    let role = Role::from_key(&role)
+        .ok_or_else(|| AppError::validation(format!("role: unknown role {role:?}")))?;
+    require_grant_admin(&permissions, &*storage, job, group).await?;
+    let user = storage
+        .user_by_name(username.trim())
+        .await
+        .map_err(missing(format!("User {}", username.trim())))?;
+    storage
+        .grant_role(user.id, job, group, role)
+        .await
+        .map_err(AppError::from)?;
+    Ok(())
 }
 
+#[server(RevokeGrant)]
+pub async fn revoke_grant(id: i64) -> Result<(), ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let grant = storage
+        .grant(id)
+        .await
+        .map_err(missing(format!("Grant {id}")))?;
+    require_grant_admin(&permissions, &*storage, grant.job, grant.group).await?;
+    storage.delete_grant(id).await.map_err(AppError::from)?;
+    Ok(())
+}
+
+/// Fails unless the user has the admin role on the job or group roles are
+/// granted on.
+#[cfg(feature = "ssr")]
+async fn require_grant_admin(
+    permissions: &crate::permissions::Permissions,
+    storage: &dyn osprei_storage::Storage,
+    job: Option<i64>,
+    group: Option<i64>,
+) -> Result<(), AppError> {
+    if let Some(job) = job {
+        permissions.require(storage, job, Role::Admin).await?;
+    }
+    if let Some(group) = group {
+        if permissions.group_role(group) != Some(Role::Admin) {
+            let message = format!("You need the admin role on group {group}");
+            return Err(AppError::forbidden(message));
+        }
+    }
+    Ok(())
+}
+
+/// API tokens the user manages: their own, and every token for administrators.
 #[server]
-pub async fn load_execution_list() -> Result<Vec<i64>, ServerFnError> {
-    let executions = osprei_storage::execution::ids().await?;
-    Ok(executions)
+pub async fn load_tokens() -> Result<Vec<widget::ApiToken>, ServerFnError> {
+    let user = user().await?;
+    let storage = storage()?;
+    let tokens = storage
+        .tokens()
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .filter(|token| user.admin || token.user_id == user.id)
+        .map(
+            |osprei_storage::ApiToken {
+                 id,
+                 name,
+                 username,
+                 service,
+                 scope,
+                 jobs,
+                 created_at,
+                 last_used,
+                 ..
+             }| widget::ApiToken {
+                id,
+                name,
+                owner: username,
+                service,
+                scope,
+                jobs,
+                created_at,
+                last_used,
+            },
+        )
+        .collect();
+    Ok(tokens)
+}
+
+/// Creates an API token of the user, returning its secret which cannot be shown
+/// again.
+///
+/// `jobs` lists the ids the token is restricted to, separated by commas or
+/// spaces.
+#[server(CreateToken)]
+pub async fn create_token(
+    name: String,
+    scope: String,
+    jobs: String,
+    service: Option<String>,
+) -> Result<String, ServerFnError> {
+    let user = user().await?;
+    let storage = storage()?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("name: name is required").into());
+    }
+    let scope = osprei_data::TokenScope::from_key(&scope)
+        .ok_or_else(|| AppError::validation(format!("scope: unknown scope {scope:?}")))?;
+    let service = service.is_some();
+    if (service || scope == osprei_data::TokenScope::Admin) && !user.admin {
+        let message = "Only administrators can create service or admin tokens";
+        return Err(AppError::forbidden(message).into());
+    }
+    let mut job_ids = Vec::new();
+    for job in jobs.split([',', ' ']).filter(|job| !job.is_empty()) {
+        let id = job
+            .parse::<i64>()
+            .map_err(|_| AppError::validation(format!("jobs: {job:?} is not a job id")))?;
+        storage
+            .job_source(id)
+            .await
+            .map_err(missing(format!("Job {id}")))?;
+        job_ids.push(id);
+    }
+    let secret = osprei_storage::users::new_token();
+    storage
+        .create_token(
+            &osprei_storage::users::hash_token(&secret),
+            user.id,
+            name,
+            service,
+            scope,
+            &job_ids,
+        )
+        .await
+        .map_err(AppError::from)?;
+    Ok(secret)
+}
+
+#[server(RevokeToken)]
+pub async fn revoke_token(id: i64) -> Result<(), ServerFnError> {
+    let user = user().await?;
+    let storage = storage()?;
+    let token = storage
+        .token(id)
+        .await
+        .map_err(missing(format!("Token {id}")))?;
+    if !user.admin && token.user_id != user.id {
+        return Err(AppError::forbidden("Only administrators can revoke tokens of others").into());
+    }
+    storage.delete_token(id).await.map_err(AppError::from)?;
+    log::info!("Revoked token {} of {}", token.name, token.username);
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+fn set_cookie(cookie: &str) {
+    if let (Some(response), Ok(value)) = (
+        leptos::use_context::<leptos_axum::ResponseOptions>(),
+        http::HeaderValue::from_str(cookie),
+    ) {
+        response.append_header(http::header::SET_COOKIE, value);
+    }
 }
 
+/// Deliveries shown per webhook.
+#[cfg(feature = "ssr")]
+const RECENT_DELIVERIES: i64 = 10;
+
+/// Webhooks the user manages: those of the jobs they maintain, and every
+/// webhook for administrators.
 #[server]
-pub async fn load_execution_status(id: i64) -> Result<String, ServerFnError> {
-    let status = osprei_storage::execution::status(id).await?;
-    let message = match status {
-        osprei_storage::ExecutionStatus::Running => "Running".to_string(),
-        osprei_storage::ExecutionStatus::Success => "Success".to_string(),
-        osprei_storage::ExecutionStatus::Failure => "Failure".to_string(),
-        osprei_storage::ExecutionStatus::Unknown => "Unknown".to_string(),
+pub async fn load_webhooks() -> Result<Vec<widget::Webhook>, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let mut webhooks = Vec::new();
+    for webhook in storage.webhooks().await.map_err(AppError::from)? {
+        if require_webhook_access(&permissions, &*storage, webhook.job)
+            .await
+            .is_err()
+        {
+            continue;
+        }
+        let deliveries = storage
+            .deliveries(webhook.id, RECENT_DELIVERIES)
+            .await
+            .map_err(AppError::from)?
+            .into_iter()
+            .map(to_delivery)
+            .collect();
+        webhooks.push(widget::Webhook {
+            id: webhook.id,
+            job: webhook.job,
+            url: webhook.url,
+            deliveries,
+        });
+    }
+    Ok(webhooks)
+}
+
+/// Adds a webhook of the job with the id in `job`, or of every job when it is
+/// empty.
+#[server(AddWebhook)]
+pub async fn add_webhook(url: String, job: String) -> Result<(), ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let url = url.trim();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(AppError::validation("url: url must start with http:// or https://").into());
+    }
+    let job = match job.trim() {
+        "" => None,
+        job => Some(
+            job.parse::<i64>()
+                .map_err(|_| AppError::validation(format!("job: {job:?} is not a job id")))?,
+        ),
     };
-    Ok(message)
+    require_webhook_access(&permissions, &*storage, job).await?;
+    storage
+        .create_webhook(job, url)
+        .await
+        .map_err(AppError::from)?;
+    Ok(())
 }
 
+#[server(DeleteWebhook)]
+pub async fn delete_webhook(id: i64) -> Result<(), ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let webhook = storage
+        .webhook(id)
+        .await
+        .map_err(missing(format!("Webhook {id}")))?;
+    require_webhook_access(&permissions, &*storage, webhook.job).await?;
+    storage.delete_webhook(id).await.map_err(AppError::from)?;
+    Ok(())
+}
+
+/// Posts a test notification to the webhook, returning how the delivery went.
+#[server(TestWebhook)]
+pub async fn test_webhook(id: i64) -> Result<widget::Delivery, ServerFnError> {
+    let permissions = permissions().await?;
+    let storage = storage()?;
+    let webhook = storage
+        .webhook(id)
+        .await
+        .map_err(missing(format!("Webhook {id}")))?;
+    require_webhook_access(&permissions, &*storage, webhook.job).await?;
+    let delivery = runner()?
+        .notifier
+        .test(&*storage, &webhook)
+        .await
+        .map_err(AppError::from)?;
+    Ok(to_delivery(delivery))
+}
+
+/// Who is mailed about executions of the job.
 #[server]
-pub async fn load_execution_duration(id: i64) -> Result<Option<i64>, ServerFnError> {
-    let duration = osprei_storage::execution::duration(id).await?;
-    Ok(duration)
+pub async fn load_mail_settings(job_id: i64) -> Result<widget::MailSettings, ServerFnError> {
+    let storage = storage()?;
+    permissions()
+        .await?
+        .require(&*storage, job_id, Role::Maintainer)
+        .await?;
+    let settings = storage
+        .mail_settings(job_id)
+        .await
+        .map_err(missing(format!("Job {job_id}")))?;
+    Ok(widget::MailSettings {
+        recipients: settings.recipients,
+        commit_author: settings.commit_author,
+        every_run: settings.every_run,
+    })
+}
+
+/// Replaces who is mailed about executions of the job.
+///
+/// `recipients` lists email addresses separated by commas or spaces.
+#[server(SetMailSettings)]
+pub async fn set_mail_settings(
+    job_id: i64,
+    recipients: String,
+    commit_author: Option<String>,
+    every_run: Option<String>,
+) -> Result<(), ServerFnError> {
+    let storage = storage()?;
+    permissions()
+        .await?
+        .require(&*storage, job_id, Role::Maintainer)
+        .await?;
+    let mut addresses = Vec::new();
+    for recipient in recipients
+        .split([',', ' '])
+        .filter(|recipient| !recipient.is_empty())
+    {
+        if recipient.parse::<lettre::Address>().is_err() {
+            let message = format!("recipients: {recipient:?} is not an email address");
+            return Err(AppError::validation(message).into());
+        }
+        addresses.push(recipient.to_string());
+    }
+    let settings = osprei_storage::MailSettings {
+        recipients: addresses,
+        commit_author: commit_author.is_some(),
+        every_run: every_run.is_some(),
+    };
+    storage
+        .set_mail_settings(job_id, &settings)
+        .await
+        .map_err(missing(format!("Job {job_id}")))?;
+    Ok(())
+}
+
+/// Fails unless the user maintains the job, or is an administrator for webhooks
+/// of every job.
+#[cfg(feature = "ssr")]
+async fn require_webhook_access(
+    permissions: &crate::permissions::Permissions,
+    storage: &dyn osprei_storage::Storage,
+    job: Option<i64>,
+) -> Result<(), AppError> {
+    match job {
+        Some(job) => permissions.require(storage, job, Role::Maintainer).await?,
+        None => return permissions.require_admin(),
+    };
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+fn to_delivery(delivery: osprei_storage::Delivery) -> widget::Delivery {
+    let osprei_storage::Delivery {
+        id,
+        execution,
+        attempts,
+        status_code,
+        error,
+        delivered_at,
+        ..
+    } = delivery;
+    widget::Delivery {
+        id,
+        execution,
+        attempts,
+        status_code,
+        error,
+        delivered_at,
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use osprei_data::{ErrorCode, StageDefinition};
+    use osprei_execution::Event;
+    use osprei_storage::{MemoryStorage, SharedStorage, Storage};
+    use std::sync::Arc;
+
+    /// Runtime with a fresh in-memory storage provided, as the server does per
+    /// request, for a request sent with the session of an administrator.
+    async fn provide_storage() -> (leptos::RuntimeId, SharedStorage) {
+        let runtime = leptos::create_runtime();
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        // Hashing is slow without optimizations, so every test shares the hash.
+        static PASSWORD_HASH: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+        let password_hash =
+            PASSWORD_HASH.get_or_init(|| osprei_storage::users::hash_password("password"));
+        let user = storage
+            .create_user("user", password_hash, true)
+            .await
+            .unwrap();
+        let token_hash = osprei_storage::users::hash_token("token");
+        storage.create_session(&token_hash, user, 60).await.unwrap();
+        leptos::provide_context(storage.clone());
+        leptos::provide_context(crate::auth::Sessions {
+            lifetime: 60,
+            secure: false,
+        });
+        let mut runner = Runner::new(&crate::config::Config::default());
+        runner.notifier.retry_delay = std::time::Duration::from_millis(10);
+        leptos::provide_context(runner);
+        provide_cookie("osprei_session=token");
+        (runtime, storage)
+    }
+
+    /// Creates a user who is not an administrator and sends the next requests
+    /// with their session.
+    async fn log_in_member(storage: &SharedStorage, username: &str) -> i64 {
+        let user = storage.create_user(username, "", false).await.unwrap();
+        let token_hash = osprei_storage::users::hash_token(username);
+        storage.create_session(&token_hash, user, 60).await.unwrap();
+        provide_cookie(&format!("osprei_session={username}"));
+        user
+    }
+
+    /// Replaces the request by one sending the cookie.
+    fn provide_cookie(cookie: &str) {
+        let (parts, _) = http::Request::builder()
+            .header(http::header::COOKIE, cookie)
+            .body(())
+            .unwrap()
+            .into_parts();
+        leptos::provide_context(leptos_axum::RequestParts::from(parts));
+        leptos::provide_context(leptos_axum::ResponseOptions::default());
+    }
+
+    fn stage(name: &str, image: &str) -> StageDefinition {
+        StageDefinition {
+            name: name.to_string(),
+            image: image.to_string(),
+            command: Some("cargo".to_string()),
+            arguments: vec!["test".to_string()],
+            environment: Vec::new(),
+            working_dir: "/workspace/code".to_string(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// Runs an execution of the job whose stages emit the events and end with
+    /// the result.
+    async fn run(
+        storage: &SharedStorage,
+        job_id: i64,
+        events: Vec<Event>,
+        result: Result<(), osprei_execution::Error>,
+    ) -> i64 {
+        let (execution_id, _) = prepare_execution(&**storage, job_id, "manual", None)
+            .await
+            .unwrap();
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        for event in events {
+            sender.send(event).unwrap();
+        }
+        drop(sender);
+        let notifier = runner().unwrap().notifier;
+        let execution = async { result };
+        let notifications = track_execution(
+            storage.clone(),
+            10,
+            notifier,
+            job_id,
+            execution_id,
+            receiver,
+            execution,
+        )
+        .await;
+        notifications.await.unwrap();
+        execution_id
+    }
+
+    #[tokio::test]
+    async fn added_jobs_are_listed_with_a_checkout_stage() {
+        let (runtime, _) = provide_storage().await;
+        for source in ["", "repo", "https://example.com/", "-c x=y"] {
+            let error = add_job(source.to_string()).await.unwrap_err();
+            assert_eq!(
+                AppError::from(error).code,
+                ErrorCode::Validation,
+                "{source}"
+            );
+        }
+        add_job("https://example.com/repo.git".to_string())
+            .await
+            .unwrap();
+
+        let jobs = load_jobs().await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].source, "https://example.com/repo.git");
+        assert_eq!(jobs[0].status, None);
+        assert_eq!(jobs[0].last_execution, None);
+
+        let stages = load_stages(jobs[0].id).await.unwrap();
+        assert_eq!(stages.len(), 1);
+        assert_eq!(stages[0].description, "checkout");
+        assert_eq!(stages[0].status, None);
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn invalid_stages_are_rejected() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        let checkout = storage.stages_for_job(job_id).await.unwrap()[0].id;
+
+        let error = add_stage(job_id, checkout, stage("test", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Validation);
+        let other_job = storage.create_job("other".to_string()).await.unwrap();
+        let other_checkout = storage.stages_for_job(other_job).await.unwrap()[0].id;
+        for dependency in [other_checkout, i64::MAX] {
+            let error = add_stage(job_id, dependency, stage("test", "rust:latest"))
+                .await
+                .unwrap_err();
+            assert_eq!(AppError::from(error).code, ErrorCode::Validation);
+        }
+        add_stage(job_id, checkout, stage("test", "rust:latest"))
+            .await
+            .unwrap();
+
+        let stages = load_stages(job_id).await.unwrap();
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages[1].dependency, Some(checkout));
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn missing_rows_are_not_found_errors() {
+        let (runtime, storage) = provide_storage().await;
+        let error = AppError::from(load_job_source(42).await.err().unwrap());
+        assert_eq!(error.code, ErrorCode::NotFound);
+        assert_eq!(error.message, "Job 42 does not exist");
+        let error = AppError::from(load_execution(42).await.err().unwrap());
+        assert_eq!(error.code, ErrorCode::NotFound);
+        assert!(matches!(
+            prepare_execution(&*storage, 42, "manual", None).await,
+            Err(osprei_storage::Error::NotFound)
+        ));
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn executions_of_a_ref_check_it_out() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        let (execution_id, stages) = prepare_execution(&*storage, job_id, "api", Some("v1.0"))
+            .await
+            .unwrap();
+        let variables: Vec<_> = stages[0]
+            .environment
+            .iter()
+            .map(|variable| (variable.name.as_str(), variable.value.as_str()))
+            .collect();
+        assert_eq!(variables, vec![("SOURCE", "source"), ("GIT_REF", "v1.0")]);
+        let execution = storage.execution(execution_id).await.unwrap();
+        assert_eq!(execution.git_ref.as_deref(), Some("v1.0"));
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn successful_executions_record_every_stage() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        let checkout = storage.stages_for_job(job_id).await.unwrap()[0].id;
+        storage
+            .create_stage(job_id, Some(checkout), &stage("test", "rust:latest"))
+            .await
+            .unwrap();
+
+        let events = vec![
+            Event::StageStarted(0),
+            Event::Commit("0123abcd".to_string()),
+            Event::StageFinished(0, true),
+            Event::StageStarted(1),
+            Event::StageFinished(1, true),
+        ];
+        let execution_id = run(&storage, job_id, events, Ok(())).await;
+
+        let execution = load_execution(execution_id).await.unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Success);
+        assert_eq!(execution.commit.as_deref(), Some("0123abcd"));
+        let statuses: Vec<_> = execution.stages.iter().map(|stage| stage.status).collect();
+        assert_eq!(
+            statuses,
+            vec![ExecutionStatus::Success, ExecutionStatus::Success]
+        );
+        assert!(execution.end_time >= execution.start_time);
+        assert_eq!(
+            load_job_status(job_id).await.unwrap(),
+            Some(ExecutionStatus::Success)
+        );
+        let jobs = load_jobs().await.unwrap();
+        assert_eq!(jobs[0].last_run, execution.start_time);
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn failed_executions_keep_later_stages_pending() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        let checkout = storage.stages_for_job(job_id).await.unwrap()[0].id;
+        storage
+            .create_stage(job_id, Some(checkout), &stage("test", "rust:latest"))
+            .await
+            .unwrap();
+
+        let events = vec![Event::StageStarted(0), Event::StageFinished(0, false)];
+        let execution_id = run(
+            &storage,
+            job_id,
+            events,
+            Err(osprei_execution::Error::Execution),
+        )
+        .await;
+
+        let execution = load_execution(execution_id).await.unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Failure);
+        let statuses: Vec<_> = execution.stages.iter().map(|stage| stage.status).collect();
+        assert_eq!(
+            statuses,
+            vec![ExecutionStatus::Failure, ExecutionStatus::Queued]
+        );
+        let stages = load_stages(job_id).await.unwrap();
+        assert_eq!(stages[0].status, Some(ExecutionStatus::Failure));
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn executions_stay_queued_until_a_slot_is_free() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        let (execution_id, stages) = prepare_execution(&*storage, job_id, "manual", None)
+            .await
+            .unwrap();
+        let mut runner = runner().unwrap();
+        runner.settings.docker_url = "tcp://127.0.0.1:1".to_string();
+        runner.slots = Arc::new(tokio::sync::Semaphore::new(0));
+
+        let execution = tokio::spawn(run_execution(
+            runner.clone(),
+            storage.clone(),
+            job_id,
+            execution_id,
+            stages,
+        ));
+        tokio::task::yield_now().await;
+        let status = load_execution(execution_id).await.unwrap().status;
+        assert_eq!(status, ExecutionStatus::Queued);
+
+        runner.slots.add_permits(1);
+        execution.await.unwrap();
+        // Docker does not answer, so the execution fails once it runs.
+        let status = load_execution(execution_id).await.unwrap().status;
+        assert_eq!(status, ExecutionStatus::Failure);
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn executions_storage_and_docker_are_measured() {
+        crate::metrics::install().unwrap();
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        let events = vec![Event::StageStarted(0), Event::StageFinished(0, false)];
+        run(
+            &storage,
+            job_id,
+            events,
+            Err(osprei_execution::Error::TimedOut),
+        )
+        .await;
+        let metered = osprei_storage::Metered::new(MemoryStorage::new());
+        metered.job_ids().await.unwrap();
+        let settings = osprei_execution::Settings {
+            docker_url: "tcp://127.0.0.1:1".to_string(),
+            ..Default::default()
+        };
+        osprei_execution::check(&settings).await.unwrap_err();
+
+        let response = crate::metrics::render_metrics().await;
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let metrics = String::from_utf8(body.to_vec()).unwrap();
+        for expected in [
+            format!("osprei_executions_total{{job=\"{job_id}\",status=\"timed-out\"}}"),
+            format!("osprei_execution_duration_seconds_count{{job=\"{job_id}\"}}"),
+            format!("osprei_stage_duration_seconds_count{{job=\"{job_id}\",stage=\"checkout\"}}"),
+            "osprei_storage_duration_seconds_count{operation=\"job_ids\"}".to_string(),
+            "osprei_docker_errors_total{operation=\"ping\"}".to_string(),
+        ] {
+            assert!(metrics.contains(&expected), "{expected} in {metrics}");
+        }
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn execution_history_is_filtered_and_paged() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        let other = storage.create_job("other".to_string()).await.unwrap();
+        for _ in 0..widget::EXECUTION_PAGE_SIZE {
+            run(&storage, job_id, Vec::new(), Ok(())).await;
+        }
+        let failed = run(
+            &storage,
+            job_id,
+            Vec::new(),
+            Err(osprei_execution::Error::Execution),
+        )
+        .await;
+        run(&storage, other, Vec::new(), Ok(())).await;
+
+        let filter = widget::ExecutionFilter {
+            job: Some(job_id),
+            ..Default::default()
+        };
+        let first = load_executions(filter.clone(), 0).await.unwrap();
+        assert_eq!(first.pages, 2);
+        assert_eq!(first.executions.len() as i64, widget::EXECUTION_PAGE_SIZE);
+        assert_eq!(first.executions[0].id, failed);
+        let second = load_executions(filter.clone(), 1).await.unwrap();
+        assert_eq!(second.executions.len(), 1);
+
+        let failures = widget::ExecutionFilter {
+            status: Some(ExecutionStatus::Failure),
+            ..filter
+        };
+        let page = load_executions(failures, 0).await.unwrap();
+        assert_eq!(page.pages, 1);
+        assert_eq!(page.executions.len(), 1);
+        assert_eq!(page.executions[0].status, ExecutionStatus::Failure);
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn logins_set_a_session_cookie() {
+        let (runtime, _) = provide_storage().await;
+        let user = load_current_user().await.unwrap().unwrap();
+        assert_eq!(user.username, "user");
+        assert!(user.admin);
+
+        provide_cookie("osprei_session=stolen");
+        assert_eq!(
+            load_current_user().await.unwrap().map(|user| user.username),
+            None
+        );
+        let error = AppError::from(load_jobs().await.err().unwrap());
+        assert_eq!(error.code, ErrorCode::Unauthorized);
+        for (username, password) in [("user", "wrong"), ("nobody", "password")] {
+            let error = login(username.to_string(), password.to_string()).await;
+            assert_eq!(
+                AppError::from(error.unwrap_err()).code,
+                ErrorCode::Unauthorized
+            );
+        }
+
+        login("user".to_string(), "password".to_string())
+            .await
+            .unwrap();
+        let response = leptos::use_context::<leptos_axum::ResponseOptions>().unwrap();
+        let cookie = response.0.read().headers[http::header::SET_COOKIE].clone();
+        let cookie = cookie.to_str().unwrap();
+        assert!(!cookie.contains("Secure"));
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        assert!(cookie.starts_with("osprei_session="));
+        provide_cookie(&cookie);
+        assert!(load_jobs().await.unwrap().is_empty());
+
+        logout().await.unwrap();
+        assert_eq!(
+            load_current_user().await.unwrap().map(|user| user.username),
+            None
+        );
+        runtime.dispose();
+
+        let config = crate::config::Config {
+            public_url: "https://ci.example.com".to_string(),
+            ..crate::config::Config::default()
+        };
+        let sessions = crate::auth::Sessions::new(&config);
+        assert!(sessions
+            .cookie("token")
+            .ends_with("HttpOnly; SameSite=Lax; Secure"));
+        assert!(sessions.cleared_cookie().ends_with("; Secure"));
+    }
+
+    #[tokio::test]
+    async fn tokens_are_created_listed_and_revoked() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        log_in_member(&storage, "member").await;
+        let error = create_token("ci".to_string(), "admin".to_string(), String::new(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Forbidden);
+        let error = create_token("ci".to_string(), "read".to_string(), "42".to_string(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::NotFound);
+
+        let secret = create_token(
+            "ci".to_string(),
+            "trigger".to_string(),
+            job_id.to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+        let tokens = load_tokens().await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].owner, "member");
+        assert_eq!(tokens[0].scope, osprei_data::TokenScope::Trigger);
+        assert_eq!(tokens[0].jobs, vec![job_id]);
+        let hash = osprei_storage::users::hash_token(&secret);
+        assert_eq!(storage.use_token(&hash).await.unwrap().id, tokens[0].id);
+
+        // Tokens of other users are neither listed nor revocable.
+        let admin = storage.user_by_name("user").await.unwrap().id;
+        let other = osprei_storage::users::hash_token("other");
+        let id = storage
+            .create_token(
+                &other,
+                admin,
+                "deploy",
+                true,
+                osprei_data::TokenScope::Admin,
+                &[],
+            )
+            .await
+            .unwrap();
+        assert_eq!(load_tokens().await.unwrap().len(), 1);
+        let error = revoke_token(id).await.unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Forbidden);
+
+        revoke_token(tokens[0].id).await.unwrap();
+        assert!(load_tokens().await.unwrap().is_empty());
+        assert!(storage.use_token(&hash).await.is_err());
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn roles_limit_what_members_see_and_do() {
+        let (runtime, storage) = provide_storage().await;
+        let viewed = storage.create_job("viewed".to_string()).await.unwrap();
+        let grouped = storage.create_job("grouped".to_string()).await.unwrap();
+        let hidden = storage.create_job("hidden".to_string()).await.unwrap();
+        create_group("team".to_string()).await.unwrap();
+        let group = storage.groups().await.unwrap()[0].id;
+        set_job_group(grouped, group.to_string()).await.unwrap();
+        run(&storage, hidden, Vec::new(), Ok(())).await;
+        let member = log_in_member(&storage, "member").await;
+        storage
+            .grant_role(member, Some(viewed), None, Role::Viewer)
+            .await
+            .unwrap();
+        storage
+            .grant_role(member, None, Some(group), Role::Runner)
+            .await
+            .unwrap();
+
+        let jobs = load_jobs().await.unwrap();
+        let roles: Vec<_> = jobs.iter().map(|job| (job.id, job.role)).collect();
+        assert_eq!(roles, vec![(viewed, Role::Viewer), (grouped, Role::Runner)]);
+        let page = load_executions(widget::ExecutionFilter::default(), 0)
+            .await
+            .unwrap();
+        assert!(page.executions.is_empty());
+        let error = AppError::from(load_job_source(hidden).await.unwrap_err());
+        assert_eq!(error.code, ErrorCode::Forbidden);
+        let user = load_current_user().await.unwrap().unwrap();
+        assert!(!user.admin && !user.manages_access);
+
+        let error = AppError::from(execute_job(viewed).await.unwrap_err());
+        assert_eq!(error.code, ErrorCode::Forbidden);
+        let checkout = storage.stages_for_job(grouped).await.unwrap()[0].id;
+        let error = add_stage(grouped, checkout, stage("test", "rust:latest"))
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Forbidden);
+        let error = AppError::from(load_templates().await.unwrap_err());
+        assert_eq!(error.code, ErrorCode::Forbidden);
+        let error = create_group("mine".to_string()).await.unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Forbidden);
+        let target = format!("group:{group}");
+        let error = grant_role("member".to_string(), target, "admin".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Forbidden);
+
+        // Admins of a job grant roles on it, and only see the grants they manage.
+        storage
+            .grant_role(member, Some(viewed), None, Role::Admin)
+            .await
+            .unwrap();
+        storage.create_user("other", "", false).await.unwrap();
+        grant_role(
+            "other".to_string(),
+            format!("job:{viewed}"),
+            "runner".to_string(),
+        )
+        .await
+        .unwrap();
+        let access = load_access().await.unwrap();
+        assert!(!access.admin);
+        assert!(access.groups.is_empty());
+        let jobs: Vec<_> = access.jobs.iter().map(|job| job.id).collect();
+        assert_eq!(jobs, vec![viewed]);
+        let grants: Vec<_> = access
+            .grants
+            .iter()
+            .map(|grant| (grant.username.as_str(), grant.role))
+            .collect();
+        assert_eq!(
+            grants,
+            vec![("member", Role::Admin), ("other", Role::Runner)]
+        );
+        revoke_grant(access.grants[1].id).await.unwrap();
+        assert_eq!(load_access().await.unwrap().grants.len(), 1);
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn webhooks_are_notified_and_managed_by_maintainers() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        // Nothing listens on the discard port, so deliveries fail.
+        add_webhook("http://127.0.0.1:9/hook".to_string(), String::new())
+            .await
+            .unwrap();
+        let error = add_webhook("ftp://example.com".to_string(), String::new())
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Validation);
+        let execution_id = run(&storage, job_id, Vec::new(), Ok(())).await;
+
+        let webhooks = load_webhooks().await.unwrap();
+        assert_eq!(webhooks.len(), 1);
+        let delivery = &webhooks[0].deliveries[0];
+        assert_eq!(delivery.execution, Some(execution_id));
+        assert_eq!(delivery.attempts, 3);
+        assert!(delivery.error.is_some());
+        let delivery = test_webhook(webhooks[0].id).await.unwrap();
+        assert_eq!(delivery.execution, None);
+        assert_eq!(load_webhooks().await.unwrap()[0].deliveries.len(), 2);
+
+        let member = log_in_member(&storage, "member").await;
+        storage
+            .grant_role(member, Some(job_id), None, Role::Maintainer)
+            .await
+            .unwrap();
+        assert!(load_webhooks().await.unwrap().is_empty());
+        let error = delete_webhook(webhooks[0].id).await.unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Forbidden);
+        let error = add_webhook("http://example.com".to_string(), String::new())
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Forbidden);
+        add_webhook("http://example.com".to_string(), job_id.to_string())
+            .await
+            .unwrap();
+        let webhooks = load_webhooks().await.unwrap();
+        assert_eq!(webhooks[0].job, Some(job_id));
+        delete_webhook(webhooks[0].id).await.unwrap();
+        assert!(load_webhooks().await.unwrap().is_empty());
+        runtime.dispose();
+    }
+
+    #[tokio::test]
+    async fn mail_settings_are_validated_and_kept_from_maintainers() {
+        let (runtime, storage) = provide_storage().await;
+        let job_id = storage.create_job("source".to_string()).await.unwrap();
+        let recipients = "dev@example.com, ops@example.com".to_string();
+        set_mail_settings(job_id, recipients, Some("on".to_string()), None)
+            .await
+            .unwrap();
+        let settings = load_mail_settings(job_id).await.unwrap();
+        assert_eq!(settings.recipients, ["dev@example.com", "ops@example.com"]);
+        assert!(settings.commit_author && !settings.every_run);
+        let error = set_mail_settings(job_id, "dev at example".to_string(), None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Validation);
+
+        let member = log_in_member(&storage, "member").await;
+        storage
+            .grant_role(member, Some(job_id), None, Role::Runner)
+            .await
+            .unwrap();
+        let error = AppError::from(load_mail_settings(job_id).await.err().unwrap());
+        assert_eq!(error.code, ErrorCode::Forbidden);
+        let error = set_mail_settings(job_id, String::new(), None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(AppError::from(error).code, ErrorCode::Forbidden);
+        runtime.dispose();
+    }
 }
@@ -0,0 +1,67 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use axum::{
+        body::Body,
+        extract::{FromRef, Path, RawQuery, State},
+        http::{HeaderMap, Request},
+        response::IntoResponse,
+    };
+    use leptos::{provide_context, LeptosOptions};
+    use osprei_storage::SharedStorage;
+    use crate::auth::Sessions;
+    use crate::server::Runner;
+
+    /// State shared by every route of the server.
+    #[derive(Clone)]
+    pub struct AppState {
+        pub leptos_options: LeptosOptions,
+        pub storage: SharedStorage,
+        pub runner: Runner,
+        pub sessions: Sessions,
+    }
+
+    impl FromRef<AppState> for LeptosOptions {
+        fn from_ref(state: &AppState) -> LeptosOptions {
+            state.leptos_options.clone()
+        }
+    }
+
+    impl FromRef<AppState> for SharedStorage {
+        fn from_ref(state: &AppState) -> SharedStorage {
+            state.storage.clone()
+        }
+    }
+
+    impl FromRef<AppState> for Runner {
+        fn from_ref(state: &AppState) -> Runner {
+            state.runner.clone()
+        }
+    }
+
+    impl AppState {
+        /// Makes the state available to server functions and components.
+        pub fn provide(&self) {
+            provide_context(self.storage.clone());
+            provide_context(self.runner.clone());
+            provide_context(self.sessions.clone());
+        }
+    }
+
+    pub async fn server_fn_handler(
+        State(state): State<AppState>,
+        path: Path<String>,
+        headers: HeaderMap,
+        raw_query: RawQuery,
+        request: Request<Body>,
+    ) -> impl IntoResponse {
+        leptos_axum::handle_server_fns_with_context(
+            path,
+            headers,
+            raw_query,
+            move || state.provide(),
+            request,
+        )
+        .await
+    }
+}}
@@ -7,6 +7,25 @@ mod execution_table;
 pub use execution_table::Execution;
 pub use execution_table::ExecutionTable;
 
+mod execution_history;
+pub use execution_history::ExecutionFilter;
+pub use execution_history::ExecutionHistory;
+pub use execution_history::ExecutionPage;
+pub use execution_history::EXECUTION_PAGE_SIZE;
+
+mod execution_detail;
+pub use execution_detail::ExecutionDetail;
+pub use execution_detail::ExecutionSummary;
+pub use execution_detail::StageExecution;
+pub use execution_detail::StageExecutionTable;
+
+mod artifact_list;
+pub use artifact_list::Artifact;
+pub use artifact_list::ArtifactList;
+
+mod log_viewer;
+pub use log_viewer::LogViewer;
+
 mod stages;
 pub use stages::Stage;
 pub use stages::Stages;
@@ -14,9 +33,41 @@ pub use stages::Stages;
 mod stage_form;
 pub use stage_form::StageForm;
 
+mod status_badge;
+pub use status_badge::StatusBadge;
+
+mod time;
+pub use time::format_elapsed;
+pub use time::now;
+pub use time::Timestamp;
+
 mod card;
 pub use card::ActionButtons;
 pub use card::Card;
 
 mod button;
 pub use button::*;
+
+mod user_menu;
+pub use user_menu::CurrentUser;
+pub use user_menu::UserMenu;
+
+mod token_table;
+pub use token_table::ApiToken;
+pub use token_table::TokenTable;
+
+mod access;
+pub use access::Access;
+pub use access::AccessJob;
+pub use access::Grant;
+pub use access::GrantTable;
+pub use access::Group;
+
+mod webhook_list;
+pub use webhook_list::Delivery;
+pub use webhook_list::Webhook;
+pub use webhook_list::WebhookList;
+
+mod mail_form;
+pub use mail_form::MailForm;
+pub use mail_form::MailSettings;
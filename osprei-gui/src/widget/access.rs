@@ -0,0 +1,107 @@
+use crate::server::RevokeGrant;
+use leptos::*;
+use leptos_router::*;
+use osprei_data::Role;
+
+/// Groups, jobs and grants a user can grant roles on.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Access {
+    /// Whether the user can create groups and move jobs between them.
+    pub admin: bool,
+    pub groups: Vec<Group>,
+    pub jobs: Vec<AccessJob>,
+    pub grants: Vec<Grant>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct AccessJob {
+    pub id: i64,
+    pub source: String,
+    pub group: Option<i64>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Group {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Grant {
+    pub id: i64,
+    pub username: String,
+    pub job: Option<i64>,
+    pub group: Option<i64>,
+    pub role: Role,
+}
+
+type RevokeGrantAction = Action<RevokeGrant, Result<(), ServerFnError>>;
+
+#[component]
+pub fn grant_table(
+    grants: Vec<Grant>,
+    groups: Vec<Group>,
+    action: RevokeGrantAction,
+) -> impl IntoView {
+    if grants.is_empty() {
+        return view! { <p>"No roles granted yet."</p> }.into_view();
+    }
+    let rows = grants
+        .into_iter()
+        .map(|grant| {
+            let target = match (grant.job, grant.group) {
+                (Some(job), _) => {
+                    view! { <A href=format!("/job/{job}")>"Job " {job}</A> }.into_view()
+                }
+                (None, Some(group)) => {
+                    let name = groups
+                        .iter()
+                        .find(|found| found.id == group)
+                        .map_or_else(|| group.to_string(), |found| found.name.clone());
+                    format!("Group {name}").into_view()
+                }
+                (None, None) => ().into_view(),
+            };
+            view! { <Row grant target action/> }
+        })
+        .collect_view();
+    view! {
+        <table class="job-table grant-table">
+            <Header/>
+            {rows}
+        </table>
+    }
+    .into_view()
+}
+
+#[component]
+fn header() -> impl IntoView {
+    view! {
+        <tr>
+            <th>"User"</th>
+            <th>"On"</th>
+            <th>"Role"</th>
+            <th>"Action"</th>
+        </tr>
+    }
+}
+
+#[component]
+fn row(grant: Grant, target: View, action: RevokeGrantAction) -> impl IntoView {
+    let Grant {
+        id, username, role, ..
+    } = grant;
+    view! {
+        <tr>
+            <td>{username}</td>
+            <td>{target}</td>
+            <td>{role.to_string()}</td>
+            <td>
+                <ActionForm action>
+                    <input type="text" value=id hidden=true name="id"/>
+                    <input class="run-button" type="submit" value="Revoke"/>
+                </ActionForm>
+            </td>
+        </tr>
+    }
+}
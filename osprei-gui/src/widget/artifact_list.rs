@@ -0,0 +1,55 @@
+use crate::server::load_artifacts;
+use leptos::*;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Artifact {
+    pub id: i64,
+    pub path: String,
+    pub size: i64,
+}
+
+#[component]
+pub fn artifact_list(execution_id: i64) -> impl IntoView {
+    let artifacts = create_resource(
+        move || execution_id,
+        |id| async move { load_artifacts(id).await },
+    );
+    move || {
+        artifacts.get().map(|artifacts| {
+            artifacts.map(|artifacts| {
+                let items = artifacts
+                    .into_iter()
+                    .map(|artifact| view! { <ArtifactLink artifact/> })
+                    .collect_view();
+                view! { <ul class="artifact-list">{items}</ul> }
+            })
+        })
+    }
+}
+
+#[component]
+fn artifact_link(artifact: Artifact) -> impl IntoView {
+    let Artifact { id, path, size } = artifact;
+    let href = format!("/artifacts/{id}");
+    view! {
+        <li>
+            <a href=href rel="external">{path}</a>
+            " (" {format_size(size)} ")"
+        </li>
+    }
+}
+
+fn format_size(size: i64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{size} {}", UNITS[0])
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
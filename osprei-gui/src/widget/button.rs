@@ -2,17 +2,13 @@ use leptos::*;
 use leptos_router::*;
 use serde::{de::DeserializeOwned, Serialize};
 
+#[derive(Default)]
 pub enum ButtonType {
+    #[default]
     Primary,
     Secondary,
 }
 
-impl Default for ButtonType {
-    fn default() -> Self {
-        Self::Primary
-    }
-}
-
 #[component]
 pub fn button(
     #[prop(optional)] button_type: Option<ButtonType>,
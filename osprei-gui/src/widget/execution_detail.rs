@@ -0,0 +1,124 @@
+use crate::widget::{StatusBadge, Timestamp};
+use leptos::*;
+use leptos_router::*;
+use osprei_data::ExecutionStatus;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExecutionDetail {
+    pub id: i64,
+    pub job_id: i64,
+    pub source: String,
+    pub triggered_by: String,
+    pub commit: Option<String>,
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub status: ExecutionStatus,
+    pub duration: Option<i64>,
+    pub stages: Vec<StageExecution>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct StageExecution {
+    pub id: i64,
+    pub execution: i64,
+    pub name: String,
+    pub started: bool,
+    /// `Queued` until the stage starts.
+    pub status: ExecutionStatus,
+    pub duration: Option<i64>,
+}
+
+#[component]
+pub fn execution_summary(execution: ExecutionDetail) -> impl IntoView {
+    let ExecutionDetail {
+        job_id,
+        source,
+        triggered_by,
+        commit,
+        start_time,
+        end_time,
+        status,
+        duration,
+        ..
+    } = execution;
+    view! {
+        <table class="execution-summary">
+            <tr>
+                <th>"Job"</th>
+                <td><A href=format!("/job/{job_id}")>{source}</A></td>
+            </tr>
+            <tr>
+                <th>"Status"</th>
+                <td><StatusBadge status=Some(status)/></td>
+            </tr>
+            <tr>
+                <th>"Trigger"</th>
+                <td>{triggered_by}</td>
+            </tr>
+            <tr>
+                <th>"Commit"</th>
+                <td>{commit.unwrap_or_else(|| "-".to_string())}</td>
+            </tr>
+            <tr>
+                <th>"Started"</th>
+                <td><Timestamp time=start_time/></td>
+            </tr>
+            <tr>
+                <th>"Finished"</th>
+                <td><Timestamp time=end_time/></td>
+            </tr>
+            <tr>
+                <th>"Duration"</th>
+                <td>{format_duration(duration)}</td>
+            </tr>
+        </table>
+    }
+}
+
+#[component]
+pub fn stage_execution_table(stages: Vec<StageExecution>) -> impl IntoView {
+    let rows = stages
+        .into_iter()
+        .map(|stage| view! { <Row stage/> })
+        .collect_view();
+    view! {
+        <table class="job-table">
+            <tr>
+                <th>"Stage"</th>
+                <th>"Status"</th>
+                <th>"Duration"</th>
+                <th>"Logs"</th>
+            </tr>
+            {rows}
+        </table>
+    }
+}
+
+#[component]
+fn row(stage: StageExecution) -> impl IntoView {
+    let StageExecution {
+        id,
+        name,
+        started,
+        status,
+        duration,
+        ..
+    } = stage;
+    let logs = started.then(|| {
+        view! { <A href=format!("/stage/{id}")>"Logs"</A> }
+    });
+    view! {
+        <tr>
+            <td>{name}</td>
+            <td><StatusBadge status=Some(status)/></td>
+            <td>{format_duration(duration)}</td>
+            <td>{logs}</td>
+        </tr>
+    }
+}
+
+pub fn format_duration(duration: Option<i64>) -> String {
+    duration
+        .map(|duration| format!("{duration} secs"))
+        .unwrap_or_default()
+}
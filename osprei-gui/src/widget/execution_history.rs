@@ -0,0 +1,124 @@
+use crate::server::load_executions;
+use crate::widget::{Execution, ExecutionTable};
+use leptos::*;
+use osprei_data::ExecutionStatus;
+
+pub const EXECUTION_PAGE_SIZE: i64 = 20;
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExecutionFilter {
+    pub job: Option<i64>,
+    pub status: Option<ExecutionStatus>,
+    pub git_ref: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExecutionPage {
+    pub executions: Vec<Execution>,
+    pub page: i64,
+    pub pages: i64,
+}
+
+/// Paged list of executions, optionally restricted to a job, with filters.
+#[component]
+pub fn execution_history(
+    #[prop(optional)] job: Option<i64>,
+    #[prop(into)] refresh: Signal<usize>,
+) -> impl IntoView {
+    let status = create_rw_signal(String::new());
+    let git_ref = create_rw_signal(String::new());
+    let since = create_rw_signal(String::new());
+    let until = create_rw_signal(String::new());
+    let page = create_rw_signal(0i64);
+
+    let filter = create_memo(move |_| ExecutionFilter {
+        job,
+        status: status.get().parse().ok(),
+        git_ref: non_empty(git_ref.get().trim().to_string()),
+        since: non_empty(since.get()),
+        until: non_empty(until.get()),
+    });
+    // Changing the filter goes back to the first page
+    create_effect(move |_| {
+        filter.track();
+        page.set(0);
+    });
+
+    let executions = create_resource(
+        move || (filter.get(), page.get(), refresh.get()),
+        |(filter, page, _)| async move { load_executions(filter, page).await },
+    );
+
+    let status_options = ExecutionStatus::ALL
+        .iter()
+        .map(|status| view! { <option value=status.key()>{status.to_string()}</option> })
+        .collect_view();
+
+    view! {
+        <div class="execution-filters">
+            <label>
+                "Status"
+                <select on:change=move |ev| status.set(event_target_value(&ev))>
+                    <option value="">"Any"</option>
+                    {status_options}
+                </select>
+            </label>
+            <label>
+                "Ref"
+                <input type="text" prop:value=git_ref on:change=move |ev| git_ref.set(event_target_value(&ev))/>
+            </label>
+            <label>
+                "From"
+                <input type="date" prop:value=since on:change=move |ev| since.set(event_target_value(&ev))/>
+            </label>
+            <label>
+                "To"
+                <input type="date" prop:value=until on:change=move |ev| until.set(event_target_value(&ev))/>
+            </label>
+        </div>
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            {move || {
+                executions
+                    .get()
+                    .map(|executions| {
+                        executions
+                            .map(|ExecutionPage { executions, page: current, pages }| {
+                                view! {
+                                    <ExecutionTable executions/>
+                                    <Pager current pages page/>
+                                }
+                            })
+                    })
+            }}
+        </Transition>
+    }
+}
+
+#[component]
+fn pager(current: i64, pages: i64, page: RwSignal<i64>) -> impl IntoView {
+    view! {
+        <div class="pager">
+            <button
+                class="button secondary"
+                disabled=current <= 0
+                on:click=move |_| page.set(current - 1)
+            >
+                "Previous"
+            </button>
+            <span>"Page " {current + 1} " of " {pages.max(1)}</span>
+            <button
+                class="button secondary"
+                disabled=current + 1 >= pages
+                on:click=move |_| page.set(current + 1)
+            >
+                "Next"
+            </button>
+        </div>
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
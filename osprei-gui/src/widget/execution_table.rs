@@ -1,9 +1,17 @@
+use crate::widget::{execution_detail::format_duration, StatusBadge, Timestamp};
 use leptos::*;
+use leptos_router::*;
+use osprei_data::ExecutionStatus;
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Execution {
     pub id: i64,
-    pub status: String,
+    pub job_id: i64,
+    pub source: String,
+    pub status: ExecutionStatus,
+    pub git_ref: Option<String>,
+    pub commit: Option<String>,
+    pub start_time: Option<i64>,
     pub duration: Option<i64>,
 }
 
@@ -26,7 +34,10 @@ fn header() -> impl IntoView {
     view! {
         <tr>
             <th>"Id"</th>
+            <th>"Job"</th>
             <th>"Status"</th>
+            <th>"Ref"</th>
+            <th>"Started"</th>
             <th>"Duration"</th>
         </tr>
     }
@@ -36,17 +47,27 @@ fn header() -> impl IntoView {
 fn row(execution: Execution) -> impl IntoView {
     let Execution {
         id,
+        job_id,
+        source,
         status,
+        git_ref,
+        commit,
+        start_time,
         duration,
     } = execution;
-    let duration_string = duration
-        .map(|duration| format!("{duration} secs"))
-        .unwrap_or_default();
+    let reference = match (git_ref, commit) {
+        (Some(git_ref), _) => git_ref,
+        (None, Some(commit)) => commit.chars().take(8).collect(),
+        (None, None) => String::new(),
+    };
     view! {
         <tr>
-            <td>{id}</td>
-            <td>{status}</td>
-            <td>{duration_string}</td>
+            <td><A href=format!("/execution/{id}")>{id}</A></td>
+            <td><A href=format!("/job/{job_id}")>{source}</A></td>
+            <td><StatusBadge status=Some(status)/></td>
+            <td>{reference}</td>
+            <td><Timestamp time=start_time/></td>
+            <td>{format_duration(duration)}</td>
         </tr>
     }
 }
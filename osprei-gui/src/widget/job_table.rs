@@ -1,12 +1,19 @@
 use crate::{server::*, widget::*};
 use leptos::*;
 use leptos_router::*;
+use osprei_data::{ExecutionStatus, Role};
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Job {
     pub id: i64,
     pub source: String,
-    pub status: String,
+    /// Status of the last execution, `None` if it never ran.
+    pub status: Option<ExecutionStatus>,
+    pub last_execution: Option<i64>,
+    /// Start of the last execution, in seconds since the unix epoch.
+    pub last_run: Option<i64>,
+    /// Role of the user on the job, deciding which actions are shown.
+    pub role: Role,
 }
 
 type RunJobAction = Action<ExecuteJob, Result<(), ServerFnError>>;
@@ -30,30 +37,54 @@ pub fn capitalize(s: &str) -> String {
     }
 }
 
+/// Name of the repository at the source, capitalized, or the whole source when
+/// it names none.
+pub fn job_title(source: &str) -> String {
+    let path = source.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let name = path.rsplit(['/', ':']).next().unwrap_or_default();
+    if name.is_empty() || name == path {
+        source.to_string()
+    } else {
+        capitalize(name)
+    }
+}
+
 #[component]
 pub fn job_card(job: Job, action: RunJobAction) -> impl IntoView {
-    let Job { id, source, status } = job;
-    let title = source
-        .split_once("://")
-        .unwrap()
-        .1
-        .split("/")
-        .last()
-        .unwrap()
-        .split_once(".")
-        .unwrap()
-        .0;
-    let title = capitalize(title);
+    let Job {
+        id,
+        source,
+        status,
+        last_execution,
+        last_run,
+        role,
+    } = job;
+    let title = job_title(&source);
     let link = format!("/job/{id}");
+    let last_run_link = last_execution.map(|execution| {
+        let link = format!("/execution/{execution}");
+        view! { <LinkButton button_type=ButtonType::Secondary link text="Last run"/> }
+    });
+    let run_button = (role >= Role::Runner).then(|| {
+        view! {
+            <FormButton text="Run" action>
+                <input type="text" hidden=true name="job_id" value={id}/>
+            </FormButton>
+        }
+    });
+    let last_run = match last_run {
+        Some(time) => format!("Last run {}", format_elapsed(time, now())),
+        None => "Never run".to_string(),
+    };
     view! {
         <Card title>
-            <p>{status}</p>
-            <p>"1h ago"</p>
+            <p><StatusBadge status/></p>
+            <p>{last_run}</p>
             <ActionButtons>
                 <LinkButton button_type=ButtonType::Secondary link text="Details"/>
-                <FormButton text="Run" action>
-                    <input type="text" hidden=true name="job_id" value={id}/>
-                </FormButton>
+                {last_run_link}
+                {run_button}
             </ActionButtons>
         </Card>
     }
@@ -90,13 +121,20 @@ fn header() -> impl IntoView {
 
 #[component]
 fn row(job: Job, action: Action<ExecuteJob, Result<(), ServerFnError>>) -> impl IntoView {
-    let Job { id, source, status } = job;
+    let Job {
+        id,
+        source,
+        status,
+        role,
+        ..
+    } = job;
+    let run_button = (role >= Role::Runner).then(|| view! { <RunButton id action/> });
     view! {
         <td>{id}</td>
         <td>{source}</td>
-        <td>{status}</td>
+        <td><StatusBadge status/></td>
         <td>
-            <RunButton id action/>
+            {run_button}
             <DetailsButton id/>
         </td>
     }
@@ -120,3 +158,24 @@ fn details_button(id: i64) -> impl IntoView {
         </A>
     }
 }
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_name_the_repository_or_fall_back_to_the_source() {
+        for source in [
+            "https://github.com/owner/repo.git",
+            "https://host/owner/repo",
+            "https://host/owner/repo/",
+            "git@github.com:owner/repo.git",
+            "git@github.com:repo",
+        ] {
+            assert_eq!(job_title(source), "Repo", "{source}");
+        }
+        assert_eq!(job_title("repo"), "repo");
+        assert_eq!(job_title("https://"), "https://");
+        assert_eq!(job_title(""), "");
+    }
+}
@@ -0,0 +1,275 @@
+use leptos::html::Div;
+use leptos::*;
+
+const ERROR_MARKER: &str = "error:";
+
+/// Follows the output of a stage, rendering ANSI colours.
+#[component]
+pub fn log_viewer(stage_id: i64) -> impl IntoView {
+    let (lines, set_lines) = create_signal(Vec::<String>::new());
+    let (finished, set_finished) = create_signal(false);
+    let (auto_scroll, set_auto_scroll) = create_signal(true);
+    let container = create_node_ref::<Div>();
+
+    create_effect(move |_| {
+        follow(stage_id, set_lines, set_finished);
+    });
+
+    create_effect(move |_| {
+        lines.track();
+        if auto_scroll.get_untracked() {
+            if let Some(container) = container.get_untracked() {
+                container.set_scroll_top(container.scroll_height());
+            }
+        }
+    });
+
+    // Lines are only searched once, as they arrive
+    let first_error = create_memo(move |previous: Option<&(usize, Option<usize>)>| {
+        let (searched, found) = previous.copied().unwrap_or_default();
+        if found.is_some() {
+            return (searched, found);
+        }
+        lines.with(|lines| {
+            let found = lines[searched..]
+                .iter()
+                .position(|line| strip_ansi(line).contains(ERROR_MARKER))
+                .map(|index| searched + index);
+            (lines.len(), found)
+        })
+    });
+    let first_error = move || first_error.get().1;
+    let jump_to_error = move |_| {
+        set_auto_scroll.set(false);
+        if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+            if let Some(element) = document.get_element_by_id("first-error") {
+                element.scroll_into_view();
+            }
+        }
+    };
+    // Lines never change once added, so only new ones are parsed and rendered
+    let rendered = view! {
+        <For
+            each=move || 0..lines.with(Vec::len)
+            key=|index| *index
+            children=move |index| {
+                let line = lines.with_untracked(|lines| render_ansi(&lines[index]));
+                let id = move || (first_error() == Some(index)).then_some("first-error");
+                view! { <div class="log-line" id=id>{line}</div> }
+            }
+        />
+    };
+
+    view! {
+        <div class="log-controls">
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=auto_scroll
+                    on:change=move |ev| set_auto_scroll.set(event_target_checked(&ev))
+                />
+                "Auto-scroll"
+            </label>
+            <button class="button secondary" on:click=jump_to_error disabled=move || first_error().is_none()>
+                "Jump to first error"
+            </button>
+            <span>{move || if finished.get() { "Finished" } else { "Following..." }}</span>
+        </div>
+        <div class="log-viewer" node_ref=container>{rendered}</div>
+    }
+}
+
+#[cfg(feature = "hydrate")]
+fn follow(stage_id: i64, set_lines: WriteSignal<Vec<String>>, set_finished: WriteSignal<bool>) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let source = match web_sys::EventSource::new(&format!("/logs/{stage_id}/stream")) {
+        Ok(source) => source,
+        Err(err) => {
+            log::error!("Failed to open log stream: {err:?}");
+            return;
+        }
+    };
+    let on_message = Closure::<dyn FnMut(_)>::new(move |event: web_sys::MessageEvent| {
+        if let Some(line) = event.data().as_string() {
+            set_lines.update(|lines| lines.push(line));
+        }
+    });
+    source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+    let end_source = source.clone();
+    let on_end = Closure::<dyn FnMut(_)>::new(move |_: web_sys::Event| {
+        end_source.close();
+        set_finished.set(true);
+    });
+    let _ = source.add_event_listener_with_callback("end", on_end.as_ref().unchecked_ref());
+    on_end.forget();
+    on_cleanup(move || source.close());
+}
+
+#[cfg(not(feature = "hydrate"))]
+fn follow(_stage_id: i64, _set_lines: WriteSignal<Vec<String>>, _set_finished: WriteSignal<bool>) {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Style {
+    foreground: Option<u8>,
+    bold: bool,
+}
+
+impl Style {
+    fn class(&self) -> String {
+        let mut classes = Vec::new();
+        if let Some(foreground) = self.foreground {
+            classes.push(format!("ansi-fg-{foreground}"));
+        }
+        if self.bold {
+            classes.push("ansi-bold".to_string());
+        }
+        classes.join(" ")
+    }
+
+    fn apply(&mut self, parameters: &str) {
+        let mut codes = parameters
+            .split(';')
+            .map(|code| code.parse::<u8>().unwrap_or(0));
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                code @ 30..=37 => self.foreground = Some(code - 30),
+                39 => self.foreground = None,
+                code @ 90..=97 => self.foreground = Some(code - 90 + 8),
+                // 256 and true colours are not shown, their parameters are skipped
+                38 | 48 => match codes.next() {
+                    Some(5) => {
+                        codes.next();
+                    }
+                    Some(2) => {
+                        codes.nth(2);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Splits a line into runs of text sharing the same SGR style.
+fn parse_ansi(line: &str) -> Vec<(Style, String)> {
+    let mut segments = Vec::new();
+    let mut style = Style::default();
+    let mut text = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() != Some(&'[') {
+            continue;
+        }
+        chars.next();
+        let mut parameters = String::new();
+        let mut command = None;
+        for c in chars.by_ref() {
+            if c.is_ascii_alphabetic() {
+                command = Some(c);
+                break;
+            }
+            parameters.push(c);
+        }
+        if command == Some('m') {
+            if !text.is_empty() {
+                segments.push((style, std::mem::take(&mut text)));
+            }
+            style.apply(&parameters);
+        }
+    }
+    if !text.is_empty() {
+        segments.push((style, text));
+    }
+    segments
+}
+
+fn strip_ansi(line: &str) -> String {
+    parse_ansi(line).into_iter().map(|(_, text)| text).collect()
+}
+
+fn render_ansi(line: &str) -> impl IntoView {
+    parse_ansi(line)
+        .into_iter()
+        .map(|(style, text)| view! { <span class=style.class()>{text}</span> })
+        .collect_view()
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn styled(foreground: Option<u8>, bold: bool, text: &str) -> (Style, String) {
+        (Style { foreground, bold }, text.to_string())
+    }
+
+    #[test]
+    fn plain_text_is_one_unstyled_segment() {
+        assert_eq!(
+            parse_ansi("cargo test"),
+            [styled(None, false, "cargo test")]
+        );
+        assert_eq!(parse_ansi(""), []);
+    }
+
+    #[test]
+    fn colours_and_bold_apply_until_reset() {
+        assert_eq!(
+            parse_ansi("\x1b[31merror\x1b[0m: failed"),
+            [
+                styled(Some(1), false, "error"),
+                styled(None, false, ": failed")
+            ]
+        );
+        assert_eq!(
+            parse_ansi("\x1b[1;32mok\x1b[22m done\x1b[39m."),
+            [
+                styled(Some(2), true, "ok"),
+                styled(Some(2), false, " done"),
+                styled(None, false, "."),
+            ]
+        );
+        assert_eq!(parse_ansi("\x1b[91mwarn"), [styled(Some(9), false, "warn")]);
+        assert_eq!(parse_ansi("\x1b[1m\x1b[mx"), [styled(None, false, "x")]);
+        assert_eq!(
+            Style {
+                foreground: Some(9),
+                bold: true
+            }
+            .class(),
+            "ansi-fg-9 ansi-bold"
+        );
+    }
+
+    #[test]
+    fn unknown_and_truncated_escapes_are_dropped() {
+        assert_eq!(
+            parse_ansi("\x1b[2Kcleared"),
+            [styled(None, false, "cleared")]
+        );
+        assert_eq!(parse_ansi("\x1b[38;5;200mx"), [styled(None, false, "x")]);
+        assert_eq!(parse_ansi("\x1b[38;5;31mx"), [styled(None, false, "x")]);
+        assert_eq!(parse_ansi("\x1b[38;5;1mx"), [styled(None, false, "x")]);
+        assert_eq!(
+            parse_ansi("\x1b[48;2;1;2;3;32mx"),
+            [styled(Some(2), false, "x")]
+        );
+        assert_eq!(
+            parse_ansi("\x1b[1;38;2;0;0;31mx"),
+            [styled(None, true, "x")]
+        );
+        assert_eq!(parse_ansi("a\x1bb"), [styled(None, false, "ab")]);
+        assert_eq!(parse_ansi("text\x1b[31"), [styled(None, false, "text")]);
+        assert_eq!(parse_ansi("text\x1b"), [styled(None, false, "text")]);
+        assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m\x1b[K"), "error");
+    }
+}
@@ -0,0 +1,67 @@
+use crate::error_template::{AppError, ErrorMessage};
+use crate::server::{load_mail_settings, SetMailSettings};
+use leptos::*;
+use leptos_router::*;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct MailSettings {
+    pub recipients: Vec<String>,
+    /// Whether the author of the executed commit is mailed as well.
+    pub commit_author: bool,
+    /// Whether every execution is mailed about, not only those breaking or
+    /// fixing the job.
+    pub every_run: bool,
+}
+
+#[component]
+pub fn mail_form(job_id: i64) -> impl IntoView {
+    let save = create_server_action::<SetMailSettings>();
+    let settings = create_resource(
+        move || save.version().get(),
+        move |_| async move { load_mail_settings(job_id).await },
+    );
+    let form = move |settings: MailSettings| {
+        view! {
+            <ActionForm class="mail-form" action=save>
+                <input type="text" value=job_id hidden=true name="job_id"/>
+                <label>
+                    "Recipients"
+                    <input
+                        type="text"
+                        name="recipients"
+                        value=settings.recipients.join(", ")
+                        placeholder="Addresses such as dev@example.com, ops@example.com"
+                    />
+                </label>
+                <label>
+                    "Commit author"
+                    <input
+                        type="checkbox"
+                        name="commit_author"
+                        value="on"
+                        checked=settings.commit_author
+                    />
+                </label>
+                <label>
+                    "Every run"
+                    <input type="checkbox" name="every_run" value="on" checked=settings.every_run/>
+                </label>
+                <input type="submit" value="Save"/>
+            </ActionForm>
+        }
+    };
+    let save_error = move || {
+        save.value()
+            .get()
+            .and_then(Result::err)
+            .map(|err| view! { <ErrorMessage error=AppError::from(err)/> })
+    };
+    view! {
+        <h2>"Email notifications"</h2>
+        <p>"Sent when an execution breaks or fixes the job, or after every run if asked."</p>
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            {move || settings.get().map(|settings| settings.map(form))}
+        </Transition>
+        {save_error}
+    }
+}
@@ -1,40 +1,229 @@
-use crate::server::{load_templates, AddStage};
+use crate::error_template::AppError;
+use crate::server::{load_template, load_templates, AddStage};
 use leptos::*;
-use leptos_router::*;
+use osprei_data::{EnvironmentVariable, StageDefinition, ValidationError};
+
+const DEFAULT_WORKING_DIR: &str = "/workspace/code";
 
 #[component]
 pub fn stage_form(
+    job_id: i64,
     dependency: Option<i64>,
     action: Action<AddStage, Result<(), ServerFnError>>,
 ) -> impl IntoView {
     match dependency {
         None => view! { <p>"Press a stage to add a new one depending on it"</p> }.into_view(),
-        Some(dependency) => view! { <DependencyStageForm dependency action/> }.into_view(),
+        Some(dependency) => view! { <StageEditor job_id dependency action/> }.into_view(),
     }
 }
 
+#[derive(Clone)]
+struct VariableRow {
+    key: usize,
+    name: RwSignal<String>,
+    value: RwSignal<String>,
+}
+
 #[component]
-fn dependency_stage_form(
+fn stage_editor(
+    job_id: i64,
     dependency: i64,
     action: Action<AddStage, Result<(), ServerFnError>>,
 ) -> impl IntoView {
     let templates = create_resource(|| (), |_| async { load_templates().await });
-    {
-        move || {
-            templates.get().map(|templates| {
-                templates.map(|templates| {
-                    let options = templates.into_iter().map(|template| view!{<option value={template.clone()}>{template}</option>}).collect_view();
-                    view! {
-                        <ActionForm class="add-stage-form" action>
-                            <input name="job_id" type="number" value=1 hidden=true/>
-                            <label>"Depends on" <input type="number" name="dependency" value={dependency} readonly/></label>
-                            <label>"Name" <input type="text" name="name"/></label>
-                            <label>"Template" <select name="template">{options}</select></label>
-                            <input type="submit" value="Add"/>
-                        </ActionForm>
+
+    let name = create_rw_signal(String::new());
+    let image = create_rw_signal(String::new());
+    let command = create_rw_signal(String::new());
+    let arguments = create_rw_signal(String::new());
+    let working_dir = create_rw_signal(DEFAULT_WORKING_DIR.to_string());
+    let artifacts = create_rw_signal(String::new());
+    let environment = create_rw_signal(Vec::<VariableRow>::new());
+    let next_key = store_value(0usize);
+    let errors = create_rw_signal(Vec::<ValidationError>::new());
+
+    let add_variable = move |variable: EnvironmentVariable| {
+        let key = next_key.get_value();
+        next_key.set_value(key + 1);
+        environment.update(|environment| {
+            environment.push(VariableRow {
+                key,
+                name: create_rw_signal(variable.name),
+                value: create_rw_signal(variable.value),
+            })
+        });
+    };
+    let remove_variable = move |key: usize| {
+        environment.update(|environment| environment.retain(|row| row.key != key));
+    };
+
+    let apply_template = move |template: String| {
+        if template.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            match load_template(template).await {
+                Ok(template) => {
+                    if name.get_untracked().is_empty() {
+                        name.set(template.name);
+                    }
+                    image.set(template.image);
+                    command.set(template.command.unwrap_or_default());
+                    arguments.set(template.arguments.join("\n"));
+                    artifacts.set(template.artifacts.join("\n"));
+                    environment.set(Vec::new());
+                    for variable in template.environment {
+                        add_variable(variable);
                     }
+                }
+                Err(err) => log::error!("Failed to load template: {err}"),
+            }
+        });
+    };
+
+    let submit = move |ev: ev::SubmitEvent| {
+        ev.prevent_default();
+        let command = command.get_untracked();
+        let definition = StageDefinition {
+            name: name.get_untracked().trim().to_string(),
+            image: image.get_untracked().trim().to_string(),
+            command: (!command.trim().is_empty()).then(|| command.trim().to_string()),
+            arguments: lines(&arguments.get_untracked()),
+            environment: environment
+                .get_untracked()
+                .into_iter()
+                .map(|row| EnvironmentVariable {
+                    name: row.name.get_untracked().trim().to_string(),
+                    value: row.value.get_untracked(),
                 })
-            })
+                .collect(),
+            working_dir: working_dir.get_untracked().trim().to_string(),
+            artifacts: lines(&artifacts.get_untracked()),
+        };
+        let found = definition.validate();
+        if found.is_empty() {
+            action.dispatch(AddStage {
+                job_id,
+                dependency,
+                definition,
+            });
         }
+        errors.set(found);
+    };
+
+    let server_error = move || {
+        action
+            .value()
+            .get()
+            .and_then(|result| result.err())
+            .map(|err| {
+                view! { <p class="field-error">{AppError::from(err).message}</p> }
+            })
+    };
+    let template_options = move || {
+        templates.get().map(|templates| {
+            templates.map(|templates| {
+                templates
+                    .into_iter()
+                    .map(|template| view! { <option value=template.clone()>{template}</option> })
+                    .collect_view()
+            })
+        })
+    };
+
+    view! {
+        <form class="add-stage-form" on:submit=submit>
+            <label>
+                "Depends on"
+                <input type="number" value=dependency readonly/>
+            </label>
+            <label>
+                "Template"
+                <select on:change=move |ev| apply_template(event_target_value(&ev))>
+                    <option value="">"None"</option>
+                    {template_options}
+                </select>
+            </label>
+            <label>
+                "Name"
+                <input type="text" prop:value=name on:input=move |ev| name.set(event_target_value(&ev))/>
+                <FieldError errors field="name"/>
+            </label>
+            <label>
+                "Image"
+                <input type="text" prop:value=image on:input=move |ev| image.set(event_target_value(&ev))/>
+                <FieldError errors field="image"/>
+            </label>
+            <label>
+                "Command"
+                <input type="text" prop:value=command on:input=move |ev| command.set(event_target_value(&ev))/>
+                <FieldError errors field="command"/>
+            </label>
+            <label>
+                "Arguments (one per line)"
+                <textarea prop:value=arguments on:input=move |ev| arguments.set(event_target_value(&ev))></textarea>
+            </label>
+            <label>
+                "Working directory"
+                <input type="text" prop:value=working_dir on:input=move |ev| working_dir.set(event_target_value(&ev))/>
+                <FieldError errors field="working_dir"/>
+            </label>
+            <fieldset>
+                <legend>"Environment"</legend>
+                <For
+                    each=move || environment.get().into_iter().enumerate()
+                    key=|(_, row)| row.key
+                    children=move |(index, row)| {
+                        let VariableRow { key, name, value } = row;
+                        let field = format!("environment.{index}");
+                        view! {
+                            <div class="environment-variable">
+                                <input type="text" placeholder="NAME" prop:value=name on:input=move |ev| name.set(event_target_value(&ev))/>
+                                "="
+                                <input type="text" placeholder="value" prop:value=value on:input=move |ev| value.set(event_target_value(&ev))/>
+                                <button type="button" class="button secondary" on:click=move |_| remove_variable(key)>"Remove"</button>
+                                <FieldError errors field/>
+                            </div>
+                        }
+                    }
+                />
+                <button
+                    type="button"
+                    class="button secondary"
+                    on:click=move |_| add_variable(EnvironmentVariable { name: String::new(), value: String::new() })
+                >
+                    "Add variable"
+                </button>
+            </fieldset>
+            <label>
+                "Artifacts (one glob per line, relative to /workspace)"
+                <textarea prop:value=artifacts on:input=move |ev| artifacts.set(event_target_value(&ev))></textarea>
+            </label>
+            {server_error}
+            <input class="button primary" type="submit" value="Add"/>
+        </form>
     }
 }
+
+#[component]
+fn field_error(
+    errors: RwSignal<Vec<ValidationError>>,
+    #[prop(into)] field: String,
+) -> impl IntoView {
+    move || {
+        errors.with(|errors| {
+            errors
+                .iter()
+                .find(|error| error.field == field)
+                .map(|error| view! { <span class="field-error">{error.message.clone()}</span> })
+        })
+    }
+}
+
+fn lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -1,73 +1,288 @@
 use leptos::*;
+use leptos_router::*;
+use osprei_data::ExecutionStatus;
+use std::collections::HashMap;
+
+const NODE_WIDTH: i64 = 160;
+const NODE_HEIGHT: i64 = 40;
+const COLUMN_GAP: i64 = 60;
+const ROW_GAP: i64 = 20;
+const MARGIN: i64 = 10;
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Stage {
     pub id: i64,
     pub dependency: Option<i64>,
     pub description: String,
+    /// Run of this stage in the latest execution of the job.
+    pub last_execution: Option<i64>,
+    pub status: Option<ExecutionStatus>,
 }
 
+/// Renders the stages as a graph laid out in columns by depth.
 #[component]
 pub fn stages(stages: Vec<Stage>, set_as_parent: WriteSignal<Option<i64>>) -> impl IntoView {
-    let root = StageWithChildren::new(&stages);
+    let layout = Layout::new(&stages);
+    let width = layout.width();
+    let height = layout.height();
+    let edges = layout
+        .edges()
+        .into_iter()
+        .map(|((x1, y1), (x2, y2))| {
+            let middle = (x1 + x2) / 2;
+            let d = format!("M {x1} {y1} C {middle} {y1}, {middle} {y2}, {x2} {y2}");
+            view! { <path class="stage-edge" d=d/> }
+        })
+        .collect_view();
+    let nodes = stages
+        .into_iter()
+        .filter_map(|stage| {
+            let (x, y) = layout.position(stage.id)?;
+            Some(view! { <Node stage x y set_as_parent/> })
+        })
+        .collect_view();
     view! {
-        <div style="text-align: left">
-            <ul>
-                <Node node=root set_as_parent/>
-            </ul>
+        <div class="stage-graph">
+            <svg width=width height=height viewBox=format!("0 0 {width} {height}")>
+                {edges}
+                {nodes}
+            </svg>
         </div>
     }
 }
 
 #[component]
-fn node(node: StageWithChildren, set_as_parent: WriteSignal<Option<i64>>) -> impl IntoView {
-    let StageWithChildren { id, name, children } = node;
-    let children = children
-        .into_iter()
-        .map(|node| {
-            view! { <Node node set_as_parent/> }
-        })
-        .collect_view();
-    let set = move |_| {
+fn node(stage: Stage, x: i64, y: i64, set_as_parent: WriteSignal<Option<i64>>) -> impl IntoView {
+    let Stage {
+        id,
+        description,
+        last_execution,
+        status,
+        ..
+    } = stage;
+    let class = format!(
+        "stage-node status-{}",
+        status.map(ExecutionStatus::key).unwrap_or("none")
+    );
+    let title = match &status {
+        Some(status) => format!("({id}) {description}: {status}"),
+        None => format!("({id}) {description}"),
+    };
+    let navigate = use_navigate();
+    let open_logs = move |_| {
+        if let Some(execution) = last_execution {
+            navigate(&format!("/stage/{execution}"), Default::default());
+        }
+    };
+    let add_child = move |ev: ev::MouseEvent| {
+        ev.stop_propagation();
         set_as_parent.set(Some(id));
     };
     view! {
-        <li class="stage">
-            <strong>"(" {id} ")"</strong>
-            <span>{name}</span>
-            <button class="add-stage-button" on:click=set>
-                "Add"
-            </button>
-            <ul>{children}</ul>
-        </li>
+        <g class=class transform=format!("translate({x} {y})") on:click=open_logs>
+            <title>{title}</title>
+            <rect width=NODE_WIDTH height=NODE_HEIGHT rx=6/>
+            <text x=10 y=NODE_HEIGHT / 2 dominant-baseline="middle">{description}</text>
+            <g class="add-stage-button" on:click=add_child>
+                <circle cx=NODE_WIDTH - 14 cy=NODE_HEIGHT / 2 r=9/>
+                <text x=NODE_WIDTH - 14 y=NODE_HEIGHT / 2 text-anchor="middle" dominant-baseline="central">
+                    "+"
+                </text>
+            </g>
+        </g>
     }
 }
 
-struct StageWithChildren {
-    id: i64,
-    name: String,
-    children: Vec<StageWithChildren>,
+/// Positions of the stages, by depth from the closest root.
+///
+/// Stages whose dependency is missing are treated as roots and cycles are
+/// broken, so any ordering of the stages can be laid out.
+struct Layout {
+    positions: HashMap<i64, (i64, i64)>,
+    edges: Vec<(i64, i64)>,
+    columns: i64,
+    rows: i64,
 }
 
-impl StageWithChildren {
-    fn new(stages: &[Stage]) -> StageWithChildren {
-        StageWithChildren::new_recursive(stages, 0)
+impl Layout {
+    fn new(stages: &[Stage]) -> Layout {
+        let parents: HashMap<i64, Option<i64>> = stages
+            .iter()
+            .map(|stage| (stage.id, stage.dependency))
+            .collect();
+        let mut depths: HashMap<i64, usize> = HashMap::new();
+        for stage in stages {
+            depth(stage.id, &parents, &mut depths, &mut Vec::new());
+        }
+        let mut layers: Vec<Vec<i64>> = Vec::new();
+        let mut ids: Vec<i64> = stages.iter().map(|stage| stage.id).collect();
+        ids.sort();
+        for id in ids {
+            let depth = depths[&id];
+            if layers.len() <= depth {
+                layers.resize(depth + 1, Vec::new());
+            }
+            layers[depth].push(id);
+        }
+        // Keep children next to their parents to limit edge crossings
+        for index in 1..layers.len() {
+            let (previous, current) = layers.split_at_mut(index);
+            let previous = &previous[index - 1];
+            current[0].sort_by_key(|id| {
+                parents[id]
+                    .and_then(|parent| previous.iter().position(|other| *other == parent))
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        let mut positions = HashMap::new();
+        for (column, layer) in layers.iter().enumerate() {
+            for (row, id) in layer.iter().enumerate() {
+                positions.insert(*id, (column as i64, row as i64));
+            }
+        }
+        let edges = stages
+            .iter()
+            .filter_map(|stage| {
+                let parent = stage.dependency?;
+                let (parent_column, _) = positions.get(&parent)?;
+                let (column, _) = positions.get(&stage.id)?;
+                (parent_column < column).then_some((parent, stage.id))
+            })
+            .collect();
+        Layout {
+            positions,
+            edges,
+            columns: layers.len() as i64,
+            rows: layers.iter().map(Vec::len).max().unwrap_or(0) as i64,
+        }
+    }
+
+    fn position(&self, id: i64) -> Option<(i64, i64)> {
+        self.positions.get(&id).map(|(column, row)| {
+            (
+                MARGIN + column * (NODE_WIDTH + COLUMN_GAP),
+                MARGIN + row * (NODE_HEIGHT + ROW_GAP),
+            )
+        })
     }
 
-    fn new_recursive(stages: &[Stage], idx: usize) -> StageWithChildren {
-        let Stage {
-            id, description, ..
-        } = stages.get(idx).expect("invalid reference");
-        let children = stages
+    /// Edge endpoints, from the right side of the parent to the left side of
+    /// the child.
+    fn edges(&self) -> Vec<((i64, i64), (i64, i64))> {
+        self.edges
             .iter()
-            .enumerate()
-            .filter(|(_, stage)| stage.dependency.map(|dep| dep == *id).unwrap_or(false))
-            .map(|(idx, _)| StageWithChildren::new_recursive(stages, idx))
-            .collect();
-        StageWithChildren {
-            id: *id,
-            name: description.to_string(),
-            children,
+            .filter_map(|(parent, child)| {
+                let (x1, y1) = self.position(*parent)?;
+                let (x2, y2) = self.position(*child)?;
+                Some((
+                    (x1 + NODE_WIDTH, y1 + NODE_HEIGHT / 2),
+                    (x2, y2 + NODE_HEIGHT / 2),
+                ))
+            })
+            .collect()
+    }
+
+    fn width(&self) -> i64 {
+        2 * MARGIN + self.columns * NODE_WIDTH + (self.columns - 1).max(0) * COLUMN_GAP
+    }
+
+    fn height(&self) -> i64 {
+        2 * MARGIN + self.rows * NODE_HEIGHT + (self.rows - 1).max(0) * ROW_GAP
+    }
+}
+
+fn depth(
+    id: i64,
+    parents: &HashMap<i64, Option<i64>>,
+    depths: &mut HashMap<i64, usize>,
+    visiting: &mut Vec<i64>,
+) -> usize {
+    if let Some(depth) = depths.get(&id) {
+        return *depth;
+    }
+    let parent = parents.get(&id).copied().flatten().filter(|parent| {
+        *parent != id && parents.contains_key(parent) && !visiting.contains(parent)
+    });
+    let depth = match parent {
+        Some(parent) => {
+            visiting.push(id);
+            let depth = depth(parent, parents, depths, visiting) + 1;
+            visiting.pop();
+            depth
+        }
+        None => 0,
+    };
+    depths.insert(id, depth);
+    depth
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn stage(id: i64, dependency: Option<i64>) -> Stage {
+        Stage {
+            id,
+            dependency,
+            description: format!("stage {id}"),
+            last_execution: None,
+            status: None,
         }
     }
+
+    fn grid(layout: &Layout, ids: &[i64]) -> Vec<(i64, i64)> {
+        ids.iter().map(|id| layout.positions[id]).collect()
+    }
+
+    #[test]
+    fn chains_are_laid_out_in_one_row() {
+        let layout = Layout::new(&[stage(3, Some(2)), stage(1, None), stage(2, Some(1))]);
+        assert_eq!(grid(&layout, &[1, 2, 3]), [(0, 0), (1, 0), (2, 0)]);
+        assert_eq!(layout.edges, [(2, 3), (1, 2)]);
+        assert_eq!((layout.columns, layout.rows), (3, 1));
+        assert_eq!(layout.width(), 2 * MARGIN + 3 * NODE_WIDTH + 2 * COLUMN_GAP);
+        assert_eq!(layout.height(), 2 * MARGIN + NODE_HEIGHT);
+    }
+
+    #[test]
+    fn fan_outs_stack_children_next_to_their_parent() {
+        let stages = [
+            stage(1, None),
+            stage(2, Some(1)),
+            stage(3, Some(1)),
+            stage(4, None),
+            stage(5, Some(4)),
+            stage(6, Some(3)),
+        ];
+        let layout = Layout::new(&stages);
+        assert_eq!(
+            grid(&layout, &[1, 4, 2, 3, 5, 6]),
+            [(0, 0), (0, 1), (1, 0), (1, 1), (1, 2), (2, 0)]
+        );
+        assert_eq!(layout.edges.len(), 4);
+        assert_eq!((layout.columns, layout.rows), (3, 3));
+    }
+
+    #[test]
+    fn dangling_dependencies_are_roots() {
+        let layout = Layout::new(&[stage(1, Some(99)), stage(2, Some(1))]);
+        assert_eq!(grid(&layout, &[1, 2]), [(0, 0), (1, 0)]);
+        assert_eq!(layout.edges, [(1, 2)]);
+        assert!(layout.position(99).is_none());
+    }
+
+    #[test]
+    fn cycles_are_broken() {
+        let layout = Layout::new(&[stage(1, Some(3)), stage(2, Some(1)), stage(3, Some(2))]);
+        assert_eq!(layout.positions.len(), 3);
+        assert_eq!(layout.columns, 3);
+        // The edge closing the cycle is dropped.
+        assert_eq!(layout.edges.len(), 2);
+
+        let layout = Layout::new(&[stage(1, Some(1))]);
+        assert_eq!(grid(&layout, &[1]), [(0, 0)]);
+        assert!(layout.edges.is_empty());
+        assert_eq!((layout.columns, layout.rows), (1, 1));
+        assert_eq!(Layout::new(&[]).width(), 2 * MARGIN);
+    }
 }
@@ -0,0 +1,12 @@
+use leptos::*;
+use osprei_data::ExecutionStatus;
+
+/// Coloured label for a status, `None` standing for something that never ran.
+#[component]
+pub fn status_badge(status: Option<ExecutionStatus>) -> impl IntoView {
+    let (key, label) = match status {
+        Some(status) => (status.key(), status.to_string()),
+        None => ("none", "Not executed".to_string()),
+    };
+    view! { <span class=format!("status-badge status-{key}")>{label}</span> }
+}
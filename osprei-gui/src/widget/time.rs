@@ -0,0 +1,41 @@
+use leptos::*;
+use time::{macros::format_description, OffsetDateTime};
+
+/// Current time in seconds since the unix epoch.
+pub fn now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+/// Formats seconds since the unix epoch as an absolute UTC time.
+pub fn format_time(timestamp: i64) -> String {
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second] UTC");
+    OffsetDateTime::from_unix_timestamp(timestamp)
+        .ok()
+        .and_then(|time| time.format(&format).ok())
+        .unwrap_or_default()
+}
+
+/// Describes how long ago the timestamp was, like "5 minutes ago".
+pub fn format_elapsed(timestamp: i64, now: i64) -> String {
+    let elapsed = (now - timestamp).max(0);
+    let (amount, unit) = match elapsed {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (elapsed / 60, "minute"),
+        3600..=86399 => (elapsed / 3600, "hour"),
+        _ => (elapsed / 86400, "day"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!("{amount} {unit}{plural} ago")
+}
+
+/// Absolute time, with how long ago it was on hover.
+#[component]
+pub fn timestamp(time: Option<i64>) -> impl IntoView {
+    match time {
+        Some(time) => view! {
+            <time title=format_elapsed(time, now())>{format_time(time)}</time>
+        }
+        .into_view(),
+        None => "-".into_view(),
+    }
+}
@@ -0,0 +1,96 @@
+use crate::server::RevokeToken;
+use crate::widget::Timestamp;
+use leptos::*;
+use leptos_router::*;
+use osprei_data::TokenScope;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    /// User who created the token.
+    pub owner: String,
+    pub service: bool,
+    pub scope: TokenScope,
+    /// Jobs the token is restricted to, every job when empty.
+    pub jobs: Vec<i64>,
+    pub created_at: i64,
+    pub last_used: Option<i64>,
+}
+
+type RevokeTokenAction = Action<RevokeToken, Result<(), ServerFnError>>;
+
+#[component]
+pub fn token_table(tokens: Vec<ApiToken>, action: RevokeTokenAction) -> impl IntoView {
+    if tokens.is_empty() {
+        return view! { <p>"No tokens yet."</p> }.into_view();
+    }
+    let rows = tokens
+        .into_iter()
+        .map(|token| view! { <Row token action/> })
+        .collect_view();
+    view! {
+        <table class="job-table token-table">
+            <Header/>
+            {rows}
+        </table>
+    }
+    .into_view()
+}
+
+#[component]
+fn header() -> impl IntoView {
+    view! {
+        <tr>
+            <th>"Name"</th>
+            <th>"Owner"</th>
+            <th>"Scope"</th>
+            <th>"Jobs"</th>
+            <th>"Created"</th>
+            <th>"Last used"</th>
+            <th>"Action"</th>
+        </tr>
+    }
+}
+
+#[component]
+fn row(token: ApiToken, action: RevokeTokenAction) -> impl IntoView {
+    let ApiToken {
+        id,
+        name,
+        owner,
+        service,
+        scope,
+        jobs,
+        created_at,
+        last_used,
+    } = token;
+    let owner = if service {
+        format!("Service, created by {owner}")
+    } else {
+        owner
+    };
+    let jobs = if jobs.is_empty() {
+        "All".into_view()
+    } else {
+        jobs.into_iter()
+            .map(|job| view! { <A href=format!("/job/{job}")>{job}</A>" " })
+            .collect_view()
+    };
+    view! {
+        <tr>
+            <td>{name}</td>
+            <td>{owner}</td>
+            <td>{scope.to_string()}</td>
+            <td>{jobs}</td>
+            <td><Timestamp time=Some(created_at)/></td>
+            <td><Timestamp time=last_used/></td>
+            <td>
+                <ActionForm action>
+                    <input type="text" value=id hidden=true name="id"/>
+                    <input class="run-button" type="submit" value="Revoke"/>
+                </ActionForm>
+            </td>
+        </tr>
+    }
+}
@@ -0,0 +1,57 @@
+use crate::server::{load_current_user, Logout};
+use leptos::*;
+use leptos_router::*;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct CurrentUser {
+    pub username: String,
+    pub admin: bool,
+    /// Whether the user can grant roles on some job or group.
+    pub manages_access: bool,
+    /// Whether the user maintains some job, and so can manage its webhooks.
+    pub manages_webhooks: bool,
+}
+
+/// Name of the logged in user, links to the pages they manage, and a button to
+/// log out.
+#[component]
+pub fn user_menu() -> impl IntoView {
+    let logout = create_server_action::<Logout>();
+    let location = use_location();
+    // Logging in navigates to another page, so the user is looked up again after
+    // each one.
+    let user = create_resource(
+        move || (logout.version().get(), location.pathname.get()),
+        |_| load_current_user(),
+    );
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                user
+                    .get()
+                    .and_then(Result::ok)
+                    .flatten()
+                    .map(|user| {
+                        let access = user
+                            .manages_access
+                            .then(|| view! { <A href="/access">"Access"</A> });
+                        let webhooks = user
+                            .manages_webhooks
+                            .then(|| view! { <A href="/webhooks">"Webhooks"</A> });
+                        view! {
+                            <div class="user-menu">
+                                <span>{user.username}</span>
+                                <A href="/tokens">"API tokens"</A>
+                                {access}
+                                {webhooks}
+                                <ActionForm action=logout>
+                                    <input type="submit" value="Log out"/>
+                                </ActionForm>
+                            </div>
+                        }
+                    })
+            }}
+        </Transition>
+    }
+}
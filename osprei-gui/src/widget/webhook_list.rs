@@ -0,0 +1,128 @@
+use crate::server::{DeleteWebhook, TestWebhook};
+use crate::widget::Timestamp;
+use leptos::*;
+use leptos_router::*;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Webhook {
+    pub id: i64,
+    /// Job notified about, every job when unset.
+    pub job: Option<i64>,
+    pub url: String,
+    /// Most recent deliveries first.
+    pub deliveries: Vec<Delivery>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Delivery {
+    pub id: i64,
+    /// Unset for test notifications.
+    pub execution: Option<i64>,
+    pub attempts: i64,
+    pub status_code: Option<i64>,
+    /// Why the delivery failed, unset when it succeeded.
+    pub error: Option<String>,
+    pub delivered_at: i64,
+}
+
+type TestWebhookAction = Action<TestWebhook, Result<Delivery, ServerFnError>>;
+type DeleteWebhookAction = Action<DeleteWebhook, Result<(), ServerFnError>>;
+
+#[component]
+pub fn webhook_list(
+    webhooks: Vec<Webhook>,
+    test: TestWebhookAction,
+    delete: DeleteWebhookAction,
+) -> impl IntoView {
+    if webhooks.is_empty() {
+        return view! { <p>"No webhooks yet."</p> }.into_view();
+    }
+    webhooks
+        .into_iter()
+        .map(|webhook| view! { <WebhookEntry webhook test delete/> })
+        .collect_view()
+}
+
+#[component]
+fn webhook_entry(
+    webhook: Webhook,
+    test: TestWebhookAction,
+    delete: DeleteWebhookAction,
+) -> impl IntoView {
+    let Webhook {
+        id,
+        job,
+        url,
+        deliveries,
+    } = webhook;
+    let target = match job {
+        Some(job) => view! { <A href=format!("/job/{job}")>"Job " {job}</A> }.into_view(),
+        None => "Every job".into_view(),
+    };
+    let deliveries = if deliveries.is_empty() {
+        view! { <p>"Nothing delivered yet."</p> }.into_view()
+    } else {
+        let rows = deliveries
+            .into_iter()
+            .map(|delivery| view! { <DeliveryRow delivery/> })
+            .collect_view();
+        view! {
+            <table class="job-table">
+                <tr>
+                    <th>"Sent"</th>
+                    <th>"Execution"</th>
+                    <th>"Attempts"</th>
+                    <th>"Response"</th>
+                    <th>"Outcome"</th>
+                </tr>
+                {rows}
+            </table>
+        }
+        .into_view()
+    };
+    view! {
+        <div class="webhook">
+            <h3>{url}</h3>
+            <p>{target}</p>
+            <div class="webhook-actions">
+                <ActionForm action=test>
+                    <input type="text" value=id hidden=true name="id"/>
+                    <input class="run-button" type="submit" value="Send test notification"/>
+                </ActionForm>
+                <ActionForm action=delete>
+                    <input type="text" value=id hidden=true name="id"/>
+                    <input class="run-button" type="submit" value="Delete"/>
+                </ActionForm>
+            </div>
+            {deliveries}
+        </div>
+    }
+}
+
+#[component]
+fn delivery_row(delivery: Delivery) -> impl IntoView {
+    let Delivery {
+        execution,
+        attempts,
+        status_code,
+        error,
+        delivered_at,
+        ..
+    } = delivery;
+    let execution = match execution {
+        Some(execution) => {
+            view! { <A href=format!("/execution/{execution}")>{execution}</A> }.into_view()
+        }
+        None => "Test".into_view(),
+    };
+    let outcome = error.unwrap_or_else(|| "Delivered".to_string());
+    view! {
+        <tr>
+            <td><Timestamp time=Some(delivered_at)/></td>
+            <td>{execution}</td>
+            <td>{attempts}</td>
+            <td>{status_code}</td>
+            <td>{outcome}</td>
+        </tr>
+    }
+}
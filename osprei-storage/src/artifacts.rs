@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+
+use crate::{data_path, Error, Storage};
+
+#[derive(Clone, sqlx::FromRow)]
+pub struct Artifact {
+    pub id: i64,
+    pub execution: i64,
+    pub path: String,
+    pub size: i64,
+}
+
+/// Directory where the artifacts of an execution are copied to.
+pub fn directory(execution_id: i64) -> PathBuf {
+    data_path().join("artifacts").join(execution_id.to_string())
+}
+
+/// Location on disk of a recorded artifact.
+pub fn location(artifact: &Artifact) -> PathBuf {
+    directory(artifact.execution).join(&artifact.path)
+}
+
+/// Records every file found in the artifact directory of the execution.
+pub async fn record(storage: &dyn Storage, execution_id: i64) -> Result<usize, Error> {
+    let root = directory(execution_id);
+    let mut files = Vec::new();
+    if root.is_dir() {
+        walk(&root, &root, &mut files)?;
+    }
+    let count = files.len();
+    storage.create_artifacts(execution_id, files).await?;
+    Ok(count)
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut Vec<(String, i64)>) -> Result<(), Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            walk(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push((
+                relative.to_string_lossy().to_string(),
+                metadata.len() as i64,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the artifacts of all but the `keep` most recent executions of the
+/// job.
+pub async fn prune(storage: &dyn Storage, job_id: i64, keep: i64) -> Result<(), Error> {
+    log::info!("Prune artifacts for job ({job_id}) keeping {keep}");
+    for id in storage.expired_executions(job_id, keep).await? {
+        let dir = directory(id);
+        if dir.is_dir() {
+            std::fs::remove_dir_all(&dir)?;
+            log::info!("Deleted artifacts: {}", dir.display());
+        }
+        storage.delete_artifacts(id).await?;
+    }
+    Ok(())
+}
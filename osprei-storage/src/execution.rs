@@ -1,113 +1,86 @@
-use log::info;
+use crate::{status_code, status_from_code, Error, ExecutionStatus};
 
-use crate::{db, Error, ExecutionStatus};
-
-pub async fn create(job_id: i64) -> Result<i64, Error> {
-    let mut conn = db().await?;
-    log::info!("Insert execution with job ({job_id})");
-    let execution_id = sqlx::query!(
-        "
-            INSERT INTO executions
-            (job, start_time)
-            VALUES ($1, datetime('now'))
-            ",
-        job_id
-    )
-    .execute(&mut conn)
-    .await?
-    .last_insert_rowid();
-    info!("Inserted");
-    Ok(execution_id)
+pub struct Execution {
+    pub id: i64,
+    pub job: i64,
+    pub source: String,
+    pub triggered_by: String,
+    pub git_ref: Option<String>,
+    pub commit_hash: Option<String>,
+    /// Seconds since the unix epoch, in UTC.
+    pub start_time: Option<i64>,
+    /// Seconds since the unix epoch, in UTC.
+    pub end_time: Option<i64>,
+    pub status: ExecutionStatus,
+    pub duration: Option<i64>,
 }
 
-pub async fn ids() -> Result<Vec<i64>, Error> {
-    let mut conn = db().await?;
-    log::info!("Get ids");
-    struct Query {
-        id: i64,
-    }
-    let ids = sqlx::query_as!(
-        Query,
-        "
-            SELECT id
-            FROM executions
-            ORDER BY id DESC
-            "
-    )
-    .fetch_all(&mut conn)
-    .await?
-    .into_iter()
-    .map(|query| query.id)
-    .collect();
-    Ok(ids)
+/// Criteria for listing executions, unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub job: Option<i64>,
+    pub status: Option<ExecutionStatus>,
+    /// Matches the requested ref or a prefix of the commit.
+    pub git_ref: Option<String>,
+    /// First day the execution may have started on, as `YYYY-MM-DD`.
+    pub since: Option<String>,
+    /// Last day the execution may have started on, as `YYYY-MM-DD`.
+    pub until: Option<String>,
+    /// Jobs the execution may be of, such as the jobs a user can see.
+    pub jobs: Option<Vec<i64>>,
 }
 
-pub async fn status(id: i64) -> Result<ExecutionStatus, Error> {
-    let mut conn = db().await?;
-    log::info!("Get execution ({id}) status");
-    struct Query {
-        status: Option<i64>,
-    }
-    let status: ExecutionStatus = sqlx::query_as!(
-        Query,
-        "
-            SELECT status
-            FROM executions
-            WHERE id = $1
-            ",
-        id
-    )
-    .fetch_one(&mut conn)
-    .await?
-    .status
-    .into();
-    Ok(status)
+#[derive(sqlx::FromRow)]
+pub(crate) struct Query {
+    pub(crate) id: i64,
+    pub(crate) job: i64,
+    pub(crate) source: String,
+    pub(crate) triggered_by: String,
+    pub(crate) git_ref: Option<String>,
+    pub(crate) commit_hash: Option<String>,
+    pub(crate) start_time: Option<i64>,
+    pub(crate) end_time: Option<i64>,
+    pub(crate) status: Option<i64>,
+    pub(crate) duration: Option<i64>,
 }
 
-pub async fn success(id: i64) -> Result<(), Error> {
-    set_status(id, 0).await
-}
+impl TryFrom<Query> for Execution {
+    type Error = Error;
 
-pub async fn failure(id: i64) -> Result<(), Error> {
-    set_status(id, 1).await
-}
-
-async fn set_status(id: i64, status: i64) -> Result<(), Error> {
-    let mut conn = db().await?;
-    log::info!("Set execution ({id}) status ({status})");
-    sqlx::query!(
-        "
-            UPDATE executions
-            SET
-                status = $2,
-                end_time = datetime('now')
-            WHERE id = $1
-            ",
-        id,
-        status
-    )
-    .execute(&mut conn)
-    .await?;
-    Ok(())
+    fn try_from(query: Query) -> Result<Execution, Error> {
+        let Query {
+            id,
+            job,
+            source,
+            triggered_by,
+            git_ref,
+            commit_hash,
+            start_time,
+            end_time,
+            status,
+            duration,
+        } = query;
+        Ok(Execution {
+            id,
+            job,
+            source,
+            triggered_by,
+            git_ref,
+            commit_hash,
+            start_time,
+            end_time,
+            status: status_from_code(status)?,
+            duration,
+        })
+    }
 }
 
-pub async fn duration(id: i64) -> Result<Option<i64>, Error> {
-    let mut conn = db().await?;
-    log::info!("Get execution ({id}) duration");
-    struct Query {
-        duration: Option<i64>,
+/// Status filter as query parameters: the status code and whether only running
+/// executions match, as those have no code yet.
+pub(crate) fn status_parameters(status: Option<ExecutionStatus>) -> (Option<i64>, bool) {
+    match status.map(status_code) {
+        None => (None, false),
+        Some(None) => (None, true),
+        Some(code) => (code, false),
     }
-    let duration = sqlx::query_as!(
-        Query,
-        "
-            SELECT unixepoch(end_time) - unixepoch(start_time) AS duration
-            FROM executions
-            WHERE id = $1
-            ",
-        id
-    )
-    .fetch_one(&mut conn)
-    .await?
-    .duration;
-    Ok(duration)
 }
@@ -0,0 +1,52 @@
+use osprei_data::Role;
+
+/// Named set of jobs sharing the roles granted on it.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct Group {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Role of a user on a job or on every job of a group, exactly one of which is
+/// set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grant {
+    pub id: i64,
+    pub user_id: i64,
+    pub username: String,
+    pub job: Option<i64>,
+    pub group: Option<i64>,
+    pub role: Role,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct Query {
+    pub(crate) id: i64,
+    pub(crate) user_id: i64,
+    pub(crate) username: String,
+    pub(crate) job: Option<i64>,
+    pub(crate) group: Option<i64>,
+    pub(crate) role: String,
+}
+
+impl From<Query> for Grant {
+    fn from(query: Query) -> Grant {
+        let Query {
+            id,
+            user_id,
+            username,
+            job,
+            group,
+            role,
+        } = query;
+        Grant {
+            id,
+            user_id,
+            username,
+            job,
+            group,
+            // Unknown roles grant the least.
+            role: Role::from_key(&role).unwrap_or(Role::Viewer),
+        }
+    }
+}
@@ -1,51 +1,334 @@
-use sqlx::Connection;
+use std::sync::Arc;
 
-pub mod job;
+use async_trait::async_trait;
+use osprei_data::{StageDefinition, Template};
+
+mod memory;
+pub use memory::MemoryStorage;
+
+mod postgres;
+pub use postgres::PostgresStorage;
+
+mod sqlite;
+pub use sqlite::SqliteStorage;
 
 pub mod execution;
+pub use execution::{Execution, Filter};
+
+pub mod stage_execution;
+pub use stage_execution::StageExecution;
 
 pub mod stages;
 pub use stages::Stage;
 
-pub mod templates;
+pub mod artifacts;
+pub use artifacts::Artifact;
+
+pub mod users;
+pub use users::User;
+
+pub mod tokens;
+pub use tokens::ApiToken;
+
+pub mod grants;
+pub use grants::{Grant, Group};
+
+pub mod webhooks;
+pub use webhooks::{Delivery, Webhook};
+
+pub mod mail;
+pub use mail::MailSettings;
+
+pub mod metered;
+pub use metered::Metered;
+
+pub use osprei_data::{ExecutionStatus, Role, TokenScope};
+
+/// Code stored for a status, executions still running have none.
+pub(crate) fn status_code(status: ExecutionStatus) -> Option<i64> {
+    match status {
+        ExecutionStatus::Running => None,
+        ExecutionStatus::Success => Some(0),
+        ExecutionStatus::Failure => Some(1),
+        ExecutionStatus::Cancelled => Some(2),
+        ExecutionStatus::TimedOut => Some(3),
+        ExecutionStatus::Interrupted => Some(4),
+        ExecutionStatus::Queued => Some(5),
+    }
+}
 
-pub enum ExecutionStatus {
-    Running,
-    Success,
-    Failure,
-    Unknown,
+/// Status stored under the code, failing on codes this version does not know.
+pub(crate) fn status_from_code(code: Option<i64>) -> Result<ExecutionStatus, Error> {
+    ExecutionStatus::ALL
+        .into_iter()
+        .find(|status| status_code(*status) == code)
+        .ok_or(Error::UnknownStatus(code.unwrap_or_default()))
 }
 
-impl From<Option<i64>> for ExecutionStatus {
-    fn from(value: Option<i64>) -> ExecutionStatus {
-        match value {
-            None => Self::Running,
-            Some(0) => Self::Success,
-            Some(1) => Self::Failure,
-            _ => Self::Unknown,
-        }
+/// Operations every storage backend provides.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn job_ids(&self) -> Result<Vec<i64>, Error>;
+    async fn job_source(&self, id: i64) -> Result<String, Error>;
+    /// Status of the most recent execution of the job, if it ever ran.
+    async fn job_status(&self, id: i64) -> Result<Option<ExecutionStatus>, Error>;
+    async fn job_last_execution(&self, id: i64) -> Result<Option<i64>, Error>;
+    /// Inserts the job along with the stage checking out its source.
+    async fn create_job(&self, source: String) -> Result<i64, Error>;
+
+    async fn stages_for_job(&self, job_id: i64) -> Result<Vec<Stage>, Error>;
+    async fn create_stage(
+        &self,
+        job_id: i64,
+        dependency: Option<i64>,
+        definition: &StageDefinition,
+    ) -> Result<i64, Error>;
+
+    async fn template_names(&self) -> Result<Vec<String>, Error>;
+    async fn template(&self, name: &str) -> Result<Template, Error>;
+
+    /// Records a queued execution, of `git_ref` when set and of the default
+    /// branch otherwise.
+    async fn create_execution(
+        &self,
+        job_id: i64,
+        triggered_by: &str,
+        git_ref: Option<&str>,
+    ) -> Result<i64, Error>;
+    async fn execution(&self, id: i64) -> Result<Execution, Error>;
+    async fn execution_status(&self, id: i64) -> Result<ExecutionStatus, Error>;
+    /// Executions matching the filter, most recent first.
+    async fn list_executions(
+        &self,
+        filter: &Filter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Execution>, Error>;
+    async fn count_executions(&self, filter: &Filter) -> Result<i64, Error>;
+    async fn set_execution_commit(&self, id: i64, commit_hash: &str) -> Result<(), Error>;
+    /// Marks the queued execution as running, from now on.
+    async fn set_execution_running(&self, id: i64) -> Result<(), Error>;
+    async fn finish_execution(&self, id: i64, status: ExecutionStatus) -> Result<(), Error>;
+    /// Marks the executions left queued or running, and their running stages,
+    /// as interrupted, returning how many executions there were.
+    async fn interrupt_executions(&self) -> Result<u64, Error>;
+
+    /// Inserts a pending entry for each of the stages, in execution order.
+    async fn create_stage_executions(
+        &self,
+        execution_id: i64,
+        stages: Vec<(i64, String)>,
+    ) -> Result<(), Error>;
+    async fn start_stage_execution(&self, execution_id: i64, position: i64) -> Result<(), Error>;
+    async fn finish_stage_execution(
+        &self,
+        execution_id: i64,
+        position: i64,
+        status: ExecutionStatus,
+    ) -> Result<(), Error>;
+    async fn stage_executions(&self, execution_id: i64) -> Result<Vec<StageExecution>, Error>;
+    async fn stage_execution(&self, id: i64) -> Result<StageExecution, Error>;
+    /// Stages of the most recent execution of the job.
+    async fn latest_stage_executions(&self, job_id: i64) -> Result<Vec<StageExecution>, Error>;
+
+    /// Inserts the files, given as relative path and size, as artifacts of the
+    /// execution.
+    async fn create_artifacts(
+        &self,
+        execution_id: i64,
+        files: Vec<(String, i64)>,
+    ) -> Result<(), Error>;
+    async fn artifacts(&self, execution_id: i64) -> Result<Vec<Artifact>, Error>;
+    async fn artifact(&self, id: i64) -> Result<Artifact, Error>;
+    /// Executions of the job older than the `keep` most recent ones.
+    async fn expired_executions(&self, job_id: i64, keep: i64) -> Result<Vec<i64>, Error>;
+    async fn delete_artifacts(&self, execution_id: i64) -> Result<(), Error>;
+
+    /// Inserts the user, failing with `AlreadyExists` if the name is taken.
+    async fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        admin: bool,
+    ) -> Result<i64, Error>;
+    async fn user(&self, id: i64) -> Result<User, Error>;
+    async fn user_by_name(&self, username: &str) -> Result<User, Error>;
+    /// Records a session of the user by the hash of its token, valid for
+    /// `lifetime` seconds.
+    async fn create_session(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        lifetime: i64,
+    ) -> Result<(), Error>;
+    /// User of the session, `NotFound` once it expired.
+    async fn session_user(&self, token_hash: &str) -> Result<User, Error>;
+    async fn delete_session(&self, token_hash: &str) -> Result<(), Error>;
+
+    /// Records an API token of the user by the hash of its secret.
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        name: &str,
+        service: bool,
+        scope: TokenScope,
+        jobs: &[i64],
+    ) -> Result<i64, Error>;
+    /// Every API token, most recent first.
+    async fn tokens(&self) -> Result<Vec<ApiToken>, Error>;
+    async fn token(&self, id: i64) -> Result<ApiToken, Error>;
+    /// Token with the hash, recording that it was used now.
+    async fn use_token(&self, token_hash: &str) -> Result<ApiToken, Error>;
+    async fn delete_token(&self, id: i64) -> Result<(), Error>;
+
+    /// Inserts the group, failing with `AlreadyExists` if the name is taken.
+    async fn create_group(&self, name: &str) -> Result<i64, Error>;
+    async fn groups(&self) -> Result<Vec<Group>, Error>;
+    /// Group the job belongs to, if any.
+    async fn job_group(&self, job_id: i64) -> Result<Option<i64>, Error>;
+    /// Every job with the group it belongs to, by id.
+    async fn job_groups(&self) -> Result<Vec<(i64, Option<i64>)>, Error>;
+    async fn set_job_group(&self, job_id: i64, group_id: Option<i64>) -> Result<(), Error>;
+    /// Grants the role on the job or on the group, replacing the role the user
+    /// had there.
+    async fn grant_role(
+        &self,
+        user_id: i64,
+        job_id: Option<i64>,
+        group_id: Option<i64>,
+        role: Role,
+    ) -> Result<i64, Error>;
+    async fn grants(&self) -> Result<Vec<Grant>, Error>;
+    async fn user_grants(&self, user_id: i64) -> Result<Vec<Grant>, Error>;
+    async fn grant(&self, id: i64) -> Result<Grant, Error>;
+    async fn delete_grant(&self, id: i64) -> Result<(), Error>;
+
+    /// Inserts a webhook of the job, or of every job when `job_id` is unset.
+    async fn create_webhook(&self, job_id: Option<i64>, url: &str) -> Result<i64, Error>;
+    async fn webhooks(&self) -> Result<Vec<Webhook>, Error>;
+    async fn webhook(&self, id: i64) -> Result<Webhook, Error>;
+    /// Webhooks notified about executions of the job, its own and those of
+    /// every job.
+    async fn job_webhooks(&self, job_id: i64) -> Result<Vec<Webhook>, Error>;
+    /// Deletes the webhook along with its deliveries.
+    async fn delete_webhook(&self, id: i64) -> Result<(), Error>;
+    /// Records the outcome of notifying the webhook, about the execution when
+    /// set.
+    async fn record_delivery(
+        &self,
+        webhook_id: i64,
+        execution_id: Option<i64>,
+        attempts: i64,
+        status_code: Option<i64>,
+        error: Option<&str>,
+    ) -> Result<i64, Error>;
+    /// The `limit` most recent deliveries of the webhook, most recent first.
+    async fn deliveries(&self, webhook_id: i64, limit: i64) -> Result<Vec<Delivery>, Error>;
+
+    async fn mail_settings(&self, job_id: i64) -> Result<MailSettings, Error>;
+    async fn set_mail_settings(&self, job_id: i64, settings: &MailSettings) -> Result<(), Error>;
+
+    /// Checks that the database answers and that every migration is applied.
+    async fn check_migrations(&self) -> Result<(), Error>;
+}
+
+/// Fails with the versions of the migrations missing from `applied`.
+pub(crate) fn pending_migrations(
+    migrator: &sqlx::migrate::Migrator,
+    applied: &[i64],
+) -> Result<(), Error> {
+    let pending: Vec<i64> = migrator
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect();
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::PendingMigrations(pending))
     }
 }
 
-async fn db() -> Result<sqlx::SqliteConnection, Error> {
-    let url = std::env::var("DATABASE_URL").unwrap();
-    log::info!("Connecting to database: {}", url);
-    let connection = sqlx::SqliteConnection::connect(&url).await?;
-    log::info!("Connected to database");
-    Ok(connection)
+/// Handle to the storage backend, shared by every request.
+pub type SharedStorage = Arc<dyn Storage>;
+
+/// Connects to the database in `DATABASE_URL`.
+pub async fn from_env() -> Result<SharedStorage, Error> {
+    let url =
+        std::env::var("DATABASE_URL").map_err(|_| Error::MissingConfiguration("DATABASE_URL"))?;
+    connect(&url).await
+}
+
+/// Connects to the backend matching the scheme of the url and applies pending
+/// migrations.
+///
+/// Operations are timed under `metered::DURATION_METRIC`.
+pub async fn connect(url: &str) -> Result<SharedStorage, Error> {
+    let scheme = url.split(':').next().unwrap_or_default();
+    let storage: SharedStorage = match scheme {
+        "sqlite" => Arc::new(Metered::new(SqliteStorage::connect(url).await?)),
+        "postgres" | "postgresql" => Arc::new(Metered::new(PostgresStorage::connect(url).await?)),
+        _ => return Err(Error::UnsupportedDatabase(scheme.to_string())),
+    };
+    Ok(storage)
+}
+
+static DATA_PATH: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+
+/// Sets the directory returned by `data_path`, once at startup.
+pub fn set_data_path(path: std::path::PathBuf) {
+    if DATA_PATH.set(path).is_err() {
+        log::warn!("Data path already set");
+    }
+}
+
+/// Directory holding the files produced by executions, `OSPREI_DATA_PATH` (or
+/// the older `DATA_PATH`) unless set at startup.
+pub fn data_path() -> std::path::PathBuf {
+    match DATA_PATH.get() {
+        Some(path) => path.clone(),
+        None => std::env::var("OSPREI_DATA_PATH")
+            .or_else(|_| std::env::var("DATA_PATH"))
+            .unwrap_or_else(|_| "data".to_string())
+            .into(),
+    }
 }
 
 #[derive(Debug)]
 pub enum Error {
+    /// The requested row does not exist.
+    NotFound,
+    /// A row with the same unique key exists.
+    AlreadyExists,
     Sqlx(sqlx::Error),
     Serde(serde_json::Error),
+    Io(std::io::Error),
+    Migration(sqlx::migrate::MigrateError),
+    /// Versions of the migrations the database lacks.
+    PendingMigrations(Vec<i64>),
+    /// A status code written by another version of osprei, or by hand.
+    UnknownStatus(i64),
+    MissingConfiguration(&'static str),
+    UnsupportedDatabase(String),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            Error::NotFound => write!(f, "not found"),
+            Error::AlreadyExists => write!(f, "already exists"),
             Error::Sqlx(err) => write!(f, "sqlx: {}", err),
             Error::Serde(err) => write!(f, "serde: {}", err),
+            Error::Io(err) => write!(f, "io: {}", err),
+            Error::Migration(err) => write!(f, "migration: {}", err),
+            Error::PendingMigrations(versions) => {
+                write!(f, "migrations not applied: {:?}", versions)
+            }
+            Error::UnknownStatus(code) => write!(f, "unknown status code: {}", code),
+            Error::MissingConfiguration(name) => write!(f, "missing configuration: {}", name),
+            Error::UnsupportedDatabase(scheme) => write!(f, "unsupported database: {}", scheme),
         }
     }
 }
@@ -54,7 +337,11 @@ impl std::error::Error for Error {}
 
 impl From<sqlx::Error> for Error {
     fn from(value: sqlx::Error) -> Error {
-        Error::Sqlx(value)
+        match value {
+            sqlx::Error::RowNotFound => Error::NotFound,
+            sqlx::Error::Database(err) if err.is_unique_violation() => Error::AlreadyExists,
+            value => Error::Sqlx(value),
+        }
     }
 }
 
@@ -63,3 +350,15 @@ impl From<serde_json::Error> for Error {
         Error::Serde(value)
     }
 }
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Error::Io(value)
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for Error {
+    fn from(value: sqlx::migrate::MigrateError) -> Self {
+        Error::Migration(value)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::Error;
+
+/// Who is mailed about the executions of a job, nobody by default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MailSettings {
+    /// Addresses mailed about the job.
+    pub recipients: Vec<String>,
+    /// Whether the author of the executed commit is mailed as well.
+    pub commit_author: bool,
+    /// Whether every execution is mailed about, not only those breaking or
+    /// fixing the job.
+    pub every_run: bool,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct Query {
+    pub(crate) mail_recipients: String,
+    pub(crate) mail_commit_author: bool,
+    pub(crate) mail_every_run: bool,
+}
+
+impl TryFrom<Query> for MailSettings {
+    type Error = Error;
+
+    fn try_from(query: Query) -> Result<MailSettings, Error> {
+        Ok(MailSettings {
+            recipients: serde_json::from_str(&query.mail_recipients)?,
+            commit_author: query.mail_commit_author,
+            every_run: query.mail_every_run,
+        })
+    }
+}
@@ -0,0 +1,924 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use osprei_data::{StageDefinition, Template};
+use time::{macros::format_description, OffsetDateTime};
+
+use crate::{
+    execution, stages, status_code, status_from_code, ApiToken, Artifact, Delivery, Error,
+    Execution, ExecutionStatus, Filter, Grant, Group, MailSettings, Role, Stage, StageExecution,
+    Storage, TokenScope, User, Webhook,
+};
+
+/// Storage kept in memory and lost on drop, meant for tests.
+#[derive(Default)]
+pub struct MemoryStorage {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    jobs: Vec<String>,
+    stages: Vec<StageRow>,
+    templates: Vec<(String, Template)>,
+    executions: Vec<ExecutionRow>,
+    stage_executions: Vec<StageExecutionRow>,
+    artifacts: Vec<Artifact>,
+    next_artifact: i64,
+    users: Vec<User>,
+    sessions: Vec<SessionRow>,
+    tokens: Vec<TokenRow>,
+    next_token: i64,
+    groups: Vec<Group>,
+    /// Group of each job that has one.
+    job_groups: HashMap<i64, i64>,
+    /// Mail settings of each job that has some.
+    mail_settings: HashMap<i64, MailSettings>,
+    grants: Vec<GrantRow>,
+    next_grant: i64,
+    webhooks: Vec<Webhook>,
+    next_webhook: i64,
+    deliveries: Vec<Delivery>,
+}
+
+struct StageRow {
+    job: i64,
+    dependency: Option<i64>,
+    definition: StageDefinition,
+}
+
+struct SessionRow {
+    token_hash: String,
+    user: i64,
+    expires_at: i64,
+}
+
+struct TokenRow {
+    id: i64,
+    token_hash: String,
+    user: i64,
+    name: String,
+    service: bool,
+    scope: TokenScope,
+    jobs: Vec<i64>,
+    created_at: i64,
+    last_used: Option<i64>,
+}
+
+struct GrantRow {
+    id: i64,
+    user: i64,
+    job: Option<i64>,
+    group: Option<i64>,
+    role: Role,
+}
+
+struct ExecutionRow {
+    job: i64,
+    triggered_by: String,
+    git_ref: Option<String>,
+    commit_hash: Option<String>,
+    start_time: i64,
+    end_time: Option<i64>,
+    status: Option<i64>,
+}
+
+struct StageExecutionRow {
+    execution: i64,
+    stage: i64,
+    position: i64,
+    name: String,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    status: Option<i64>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+
+    /// Makes the template available under the name.
+    pub fn add_template(&self, name: &str, template: Template) {
+        self.state().templates.push((name.to_string(), template));
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl State {
+    fn grant(&self, grant: &GrantRow) -> Result<Grant, Error> {
+        Ok(Grant {
+            id: grant.id,
+            user_id: grant.user,
+            username: row(&self.users, grant.user)?.username.clone(),
+            job: grant.job,
+            group: grant.group,
+            role: grant.role,
+        })
+    }
+
+    fn api_token(&self, token: &TokenRow) -> Result<ApiToken, Error> {
+        Ok(ApiToken {
+            id: token.id,
+            name: token.name.clone(),
+            user_id: token.user,
+            username: row(&self.users, token.user)?.username.clone(),
+            service: token.service,
+            scope: token.scope,
+            jobs: token.jobs.clone(),
+            created_at: token.created_at,
+            last_used: token.last_used,
+        })
+    }
+}
+
+impl State {
+    fn execution(&self, id: i64) -> Result<&ExecutionRow, Error> {
+        row(&self.executions, id)
+    }
+
+    fn executions_of(&self, job: i64) -> impl DoubleEndedIterator<Item = (i64, &ExecutionRow)> {
+        rows(&self.executions).filter(move |(_, row)| row.job == job)
+    }
+
+    fn stage_execution(&self, id: i64, row: &StageExecutionRow) -> Result<StageExecution, Error> {
+        let status = row
+            .start_time
+            .map(|_| status_from_code(row.status))
+            .transpose()?;
+        Ok(StageExecution {
+            id,
+            execution: row.execution,
+            stage: Some(row.stage),
+            position: row.position,
+            name: row.name.clone(),
+            status,
+            duration: duration(row.start_time, row.end_time),
+        })
+    }
+
+    fn stage_executions_of(&self, execution: i64) -> Result<Vec<StageExecution>, Error> {
+        let mut stages = rows(&self.stage_executions)
+            .filter(|(_, row)| row.execution == execution)
+            .map(|(id, row)| self.stage_execution(id, row))
+            .collect::<Result<Vec<_>, _>>()?;
+        stages.sort_by_key(|stage| stage.position);
+        Ok(stages)
+    }
+
+    fn update_stage_execution(
+        &mut self,
+        execution: i64,
+        position: i64,
+    ) -> Option<&mut StageExecutionRow> {
+        self.stage_executions
+            .iter_mut()
+            .find(|row| row.execution == execution && row.position == position)
+    }
+
+    fn to_execution(&self, id: i64, row: &ExecutionRow) -> Result<Execution, Error> {
+        Ok(Execution {
+            id,
+            job: row.job,
+            source: self.jobs[(row.job - 1) as usize].clone(),
+            triggered_by: row.triggered_by.clone(),
+            git_ref: row.git_ref.clone(),
+            commit_hash: row.commit_hash.clone(),
+            start_time: Some(row.start_time),
+            end_time: row.end_time,
+            status: status_from_code(row.status)?,
+            duration: duration(Some(row.start_time), row.end_time),
+        })
+    }
+
+    fn matching<'a>(
+        &'a self,
+        filter: &'a Filter,
+    ) -> impl Iterator<Item = (i64, &'a ExecutionRow)> + 'a {
+        let (status, running) = execution::status_parameters(filter.status);
+        rows(&self.executions).filter(move |(_, row)| {
+            let date = format_date(row.start_time);
+            filter.job.is_none_or(|job| row.job == job)
+                && status.is_none_or(|status| row.status == Some(status))
+                && (!running || row.status.is_none())
+                && filter.git_ref.as_ref().is_none_or(|git_ref| {
+                    row.git_ref.as_ref() == Some(git_ref)
+                        || row
+                            .commit_hash
+                            .as_ref()
+                            .is_some_and(|commit| commit.starts_with(git_ref.as_str()))
+                })
+                && filter
+                    .since
+                    .as_ref()
+                    .is_none_or(|since| date.as_str() >= since.as_str())
+                && filter
+                    .until
+                    .as_ref()
+                    .is_none_or(|until| date.as_str() <= until.as_str())
+                && filter
+                    .jobs
+                    .as_ref()
+                    .is_none_or(|jobs| jobs.contains(&row.job))
+        })
+    }
+}
+
+/// Rows along with their ids, which start at one like database keys.
+fn rows<T>(rows: &[T]) -> impl DoubleEndedIterator<Item = (i64, &T)> {
+    rows.iter()
+        .enumerate()
+        .map(|(index, row)| (index as i64 + 1, row))
+}
+
+fn row<T>(rows: &[T], id: i64) -> Result<&T, Error> {
+    usize::try_from(id - 1)
+        .ok()
+        .and_then(|index| rows.get(index))
+        .ok_or(Error::NotFound)
+}
+
+fn row_mut<T>(rows: &mut [T], id: i64) -> Result<&mut T, Error> {
+    usize::try_from(id - 1)
+        .ok()
+        .and_then(|index| rows.get_mut(index))
+        .ok_or(Error::NotFound)
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn duration(start_time: Option<i64>, end_time: Option<i64>) -> Option<i64> {
+    Some(end_time? - start_time?)
+}
+
+/// Day of the timestamp as `YYYY-MM-DD`, as the filters expect it.
+fn format_date(timestamp: i64) -> String {
+    let format = format_description!("[year]-[month]-[day]");
+    OffsetDateTime::from_unix_timestamp(timestamp)
+        .ok()
+        .and_then(|time| time.format(&format).ok())
+        .unwrap_or_default()
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn job_ids(&self) -> Result<Vec<i64>, Error> {
+        Ok(rows(&self.state().jobs).map(|(id, _)| id).collect())
+    }
+
+    async fn job_source(&self, id: i64) -> Result<String, Error> {
+        row(&self.state().jobs, id).cloned()
+    }
+
+    async fn job_status(&self, id: i64) -> Result<Option<ExecutionStatus>, Error> {
+        let state = self.state();
+        let status = state
+            .executions_of(id)
+            .last()
+            .map(|(_, row)| status_from_code(row.status))
+            .transpose()?;
+        Ok(status)
+    }
+
+    async fn job_last_execution(&self, id: i64) -> Result<Option<i64>, Error> {
+        let state = self.state();
+        let execution = state.executions_of(id).last().map(|(id, _)| id);
+        Ok(execution)
+    }
+
+    async fn create_job(&self, source: String) -> Result<i64, Error> {
+        let mut state = self.state();
+        state.jobs.push(source.clone());
+        let id = state.jobs.len() as i64;
+        state.stages.push(StageRow {
+            job: id,
+            dependency: None,
+            definition: stages::checkout(source),
+        });
+        Ok(id)
+    }
+
+    async fn stages_for_job(&self, job_id: i64) -> Result<Vec<Stage>, Error> {
+        let state = self.state();
+        let stages = rows(&state.stages)
+            .filter(|(_, row)| row.job == job_id)
+            .map(|(id, row)| Stage {
+                id,
+                dependency: row.dependency,
+                definition: row.definition.clone(),
+            })
+            .collect();
+        Ok(stages)
+    }
+
+    async fn create_stage(
+        &self,
+        job_id: i64,
+        dependency: Option<i64>,
+        definition: &StageDefinition,
+    ) -> Result<i64, Error> {
+        let mut state = self.state();
+        row(&state.jobs, job_id)?;
+        if let Some(dependency) = dependency {
+            row(&state.stages, dependency)?;
+        }
+        state.stages.push(StageRow {
+            job: job_id,
+            dependency,
+            definition: definition.clone(),
+        });
+        Ok(state.stages.len() as i64)
+    }
+
+    async fn template_names(&self) -> Result<Vec<String>, Error> {
+        let state = self.state();
+        Ok(state
+            .templates
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect())
+    }
+
+    async fn template(&self, name: &str) -> Result<Template, Error> {
+        self.state()
+            .templates
+            .iter()
+            .find(|(template, _)| template == name)
+            .map(|(_, template)| template.clone())
+            .ok_or(Error::NotFound)
+    }
+
+    async fn create_execution(
+        &self,
+        job_id: i64,
+        triggered_by: &str,
+        git_ref: Option<&str>,
+    ) -> Result<i64, Error> {
+        let mut state = self.state();
+        row(&state.jobs, job_id)?;
+        state.executions.push(ExecutionRow {
+            job: job_id,
+            triggered_by: triggered_by.to_string(),
+            git_ref: git_ref.map(str::to_string),
+            commit_hash: None,
+            start_time: now(),
+            end_time: None,
+            status: status_code(ExecutionStatus::Queued),
+        });
+        Ok(state.executions.len() as i64)
+    }
+
+    async fn execution(&self, id: i64) -> Result<Execution, Error> {
+        let state = self.state();
+        let row = state.execution(id)?;
+        state.to_execution(id, row)
+    }
+
+    async fn execution_status(&self, id: i64) -> Result<ExecutionStatus, Error> {
+        status_from_code(self.state().execution(id)?.status)
+    }
+
+    async fn list_executions(
+        &self,
+        filter: &Filter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Execution>, Error> {
+        let state = self.state();
+        let executions: Vec<_> = state.matching(filter).collect();
+        let executions = executions
+            .into_iter()
+            .rev()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .map(|(id, row)| state.to_execution(id, row))
+            .collect::<Result<_, _>>()?;
+        Ok(executions)
+    }
+
+    async fn count_executions(&self, filter: &Filter) -> Result<i64, Error> {
+        Ok(self.state().matching(filter).count() as i64)
+    }
+
+    async fn set_execution_commit(&self, id: i64, commit_hash: &str) -> Result<(), Error> {
+        row_mut(&mut self.state().executions, id)?.commit_hash = Some(commit_hash.to_string());
+        Ok(())
+    }
+
+    async fn set_execution_running(&self, id: i64) -> Result<(), Error> {
+        let mut state = self.state();
+        let execution = row_mut(&mut state.executions, id)?;
+        execution.status = status_code(ExecutionStatus::Running);
+        execution.start_time = now();
+        Ok(())
+    }
+
+    async fn finish_execution(&self, id: i64, status: ExecutionStatus) -> Result<(), Error> {
+        let mut state = self.state();
+        let execution = row_mut(&mut state.executions, id)?;
+        execution.status = status_code(status);
+        execution.end_time = Some(now());
+        Ok(())
+    }
+
+    async fn interrupt_executions(&self) -> Result<u64, Error> {
+        let mut state = self.state();
+        let interrupted = status_code(ExecutionStatus::Interrupted);
+        let mut count = 0;
+        for execution in state.executions.iter_mut() {
+            if !status_from_code(execution.status)?.is_finished() {
+                execution.status = interrupted;
+                execution.end_time = Some(now());
+                count += 1;
+            }
+        }
+        for stage in state.stage_executions.iter_mut() {
+            if stage.start_time.is_some() && stage.status.is_none() {
+                stage.status = interrupted;
+                stage.end_time = Some(now());
+            }
+        }
+        Ok(count)
+    }
+
+    async fn create_stage_executions(
+        &self,
+        execution_id: i64,
+        stages: Vec<(i64, String)>,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        state.execution(execution_id)?;
+        for (position, (stage, name)) in stages.into_iter().enumerate() {
+            state.stage_executions.push(StageExecutionRow {
+                execution: execution_id,
+                stage,
+                position: position as i64,
+                name,
+                start_time: None,
+                end_time: None,
+                status: None,
+            });
+        }
+        Ok(())
+    }
+
+    async fn start_stage_execution(&self, execution_id: i64, position: i64) -> Result<(), Error> {
+        if let Some(stage) = self.state().update_stage_execution(execution_id, position) {
+            stage.start_time = Some(now());
+        }
+        Ok(())
+    }
+
+    async fn finish_stage_execution(
+        &self,
+        execution_id: i64,
+        position: i64,
+        status: ExecutionStatus,
+    ) -> Result<(), Error> {
+        if let Some(stage) = self.state().update_stage_execution(execution_id, position) {
+            stage.status = status_code(status);
+            stage.end_time = Some(now());
+        }
+        Ok(())
+    }
+
+    async fn stage_executions(&self, execution_id: i64) -> Result<Vec<StageExecution>, Error> {
+        self.state().stage_executions_of(execution_id)
+    }
+
+    async fn stage_execution(&self, id: i64) -> Result<StageExecution, Error> {
+        let state = self.state();
+        let row = row(&state.stage_executions, id)?;
+        state.stage_execution(id, row)
+    }
+
+    async fn latest_stage_executions(&self, job_id: i64) -> Result<Vec<StageExecution>, Error> {
+        let state = self.state();
+        let stages = match state.executions_of(job_id).last() {
+            Some((execution, _)) => state.stage_executions_of(execution)?,
+            None => Vec::new(),
+        };
+        Ok(stages)
+    }
+
+    async fn create_artifacts(
+        &self,
+        execution_id: i64,
+        files: Vec<(String, i64)>,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        state.execution(execution_id)?;
+        for (path, size) in files {
+            state.next_artifact += 1;
+            let id = state.next_artifact;
+            state.artifacts.push(Artifact {
+                id,
+                execution: execution_id,
+                path,
+                size,
+            });
+        }
+        Ok(())
+    }
+
+    async fn artifacts(&self, execution_id: i64) -> Result<Vec<Artifact>, Error> {
+        let mut artifacts: Vec<_> = self
+            .state()
+            .artifacts
+            .iter()
+            .filter(|artifact| artifact.execution == execution_id)
+            .cloned()
+            .collect();
+        artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(artifacts)
+    }
+
+    async fn artifact(&self, id: i64) -> Result<Artifact, Error> {
+        self.state()
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.id == id)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn expired_executions(&self, job_id: i64, keep: i64) -> Result<Vec<i64>, Error> {
+        let state = self.state();
+        let expired = state
+            .executions_of(job_id)
+            .rev()
+            .skip(keep.max(0) as usize)
+            .map(|(id, _)| id)
+            .collect();
+        Ok(expired)
+    }
+
+    async fn delete_artifacts(&self, execution_id: i64) -> Result<(), Error> {
+        self.state()
+            .artifacts
+            .retain(|artifact| artifact.execution != execution_id);
+        Ok(())
+    }
+
+    async fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        admin: bool,
+    ) -> Result<i64, Error> {
+        let mut state = self.state();
+        if state.users.iter().any(|user| user.username == username) {
+            return Err(Error::AlreadyExists);
+        }
+        let id = state.users.len() as i64 + 1;
+        state.users.push(User {
+            id,
+            username: username.to_string(),
+            password_hash: password_hash.to_string(),
+            admin,
+        });
+        Ok(id)
+    }
+
+    async fn user(&self, id: i64) -> Result<User, Error> {
+        row(&self.state().users, id).cloned()
+    }
+
+    async fn user_by_name(&self, username: &str) -> Result<User, Error> {
+        self.state()
+            .users
+            .iter()
+            .find(|user| user.username == username)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn create_session(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        lifetime: i64,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        row(&state.users, user_id)?;
+        state.sessions.push(SessionRow {
+            token_hash: token_hash.to_string(),
+            user: user_id,
+            expires_at: now() + lifetime,
+        });
+        Ok(())
+    }
+
+    async fn session_user(&self, token_hash: &str) -> Result<User, Error> {
+        let state = self.state();
+        let session = state
+            .sessions
+            .iter()
+            .find(|session| session.token_hash == token_hash && session.expires_at > now())
+            .ok_or(Error::NotFound)?;
+        row(&state.users, session.user).cloned()
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> Result<(), Error> {
+        let now = now();
+        self.state()
+            .sessions
+            .retain(|session| session.token_hash != token_hash && session.expires_at > now);
+        Ok(())
+    }
+
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        name: &str,
+        service: bool,
+        scope: TokenScope,
+        jobs: &[i64],
+    ) -> Result<i64, Error> {
+        let mut state = self.state();
+        row(&state.users, user_id)?;
+        if state
+            .tokens
+            .iter()
+            .any(|token| token.token_hash == token_hash)
+        {
+            return Err(Error::AlreadyExists);
+        }
+        state.next_token += 1;
+        let id = state.next_token;
+        state.tokens.push(TokenRow {
+            id,
+            token_hash: token_hash.to_string(),
+            user: user_id,
+            name: name.to_string(),
+            service,
+            scope,
+            jobs: jobs.to_vec(),
+            created_at: now(),
+            last_used: None,
+        });
+        Ok(id)
+    }
+
+    async fn tokens(&self) -> Result<Vec<ApiToken>, Error> {
+        let state = self.state();
+        state
+            .tokens
+            .iter()
+            .rev()
+            .map(|token| state.api_token(token))
+            .collect()
+    }
+
+    async fn token(&self, id: i64) -> Result<ApiToken, Error> {
+        let state = self.state();
+        let token = state
+            .tokens
+            .iter()
+            .find(|token| token.id == id)
+            .ok_or(Error::NotFound)?;
+        state.api_token(token)
+    }
+
+    async fn use_token(&self, token_hash: &str) -> Result<ApiToken, Error> {
+        let mut state = self.state();
+        let index = state
+            .tokens
+            .iter()
+            .position(|token| token.token_hash == token_hash)
+            .ok_or(Error::NotFound)?;
+        state.tokens[index].last_used = Some(now());
+        state.api_token(&state.tokens[index])
+    }
+
+    async fn delete_token(&self, id: i64) -> Result<(), Error> {
+        self.state().tokens.retain(|token| token.id != id);
+        Ok(())
+    }
+
+    async fn create_group(&self, name: &str) -> Result<i64, Error> {
+        let mut state = self.state();
+        if state.groups.iter().any(|group| group.name == name) {
+            return Err(Error::AlreadyExists);
+        }
+        let id = state.groups.len() as i64 + 1;
+        state.groups.push(Group {
+            id,
+            name: name.to_string(),
+        });
+        Ok(id)
+    }
+
+    async fn groups(&self) -> Result<Vec<Group>, Error> {
+        Ok(self.state().groups.clone())
+    }
+
+    async fn job_group(&self, job_id: i64) -> Result<Option<i64>, Error> {
+        let state = self.state();
+        row(&state.jobs, job_id)?;
+        Ok(state.job_groups.get(&job_id).copied())
+    }
+
+    async fn job_groups(&self) -> Result<Vec<(i64, Option<i64>)>, Error> {
+        let state = self.state();
+        let groups = rows(&state.jobs)
+            .map(|(id, _)| (id, state.job_groups.get(&id).copied()))
+            .collect();
+        Ok(groups)
+    }
+
+    async fn set_job_group(&self, job_id: i64, group_id: Option<i64>) -> Result<(), Error> {
+        let mut state = self.state();
+        row(&state.jobs, job_id)?;
+        match group_id {
+            Some(group_id) => {
+                row(&state.groups, group_id)?;
+                state.job_groups.insert(job_id, group_id);
+            }
+            None => {
+                state.job_groups.remove(&job_id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn grant_role(
+        &self,
+        user_id: i64,
+        job_id: Option<i64>,
+        group_id: Option<i64>,
+        role: Role,
+    ) -> Result<i64, Error> {
+        let mut state = self.state();
+        row(&state.users, user_id)?;
+        state
+            .grants
+            .retain(|grant| (grant.user, grant.job, grant.group) != (user_id, job_id, group_id));
+        state.next_grant += 1;
+        let id = state.next_grant;
+        state.grants.push(GrantRow {
+            id,
+            user: user_id,
+            job: job_id,
+            group: group_id,
+            role,
+        });
+        Ok(id)
+    }
+
+    async fn grants(&self) -> Result<Vec<Grant>, Error> {
+        let state = self.state();
+        state
+            .grants
+            .iter()
+            .map(|grant| state.grant(grant))
+            .collect()
+    }
+
+    async fn user_grants(&self, user_id: i64) -> Result<Vec<Grant>, Error> {
+        let state = self.state();
+        state
+            .grants
+            .iter()
+            .filter(|grant| grant.user == user_id)
+            .map(|grant| state.grant(grant))
+            .collect()
+    }
+
+    async fn grant(&self, id: i64) -> Result<Grant, Error> {
+        let state = self.state();
+        let grant = state
+            .grants
+            .iter()
+            .find(|grant| grant.id == id)
+            .ok_or(Error::NotFound)?;
+        state.grant(grant)
+    }
+
+    async fn delete_grant(&self, id: i64) -> Result<(), Error> {
+        self.state().grants.retain(|grant| grant.id != id);
+        Ok(())
+    }
+
+    async fn create_webhook(&self, job_id: Option<i64>, url: &str) -> Result<i64, Error> {
+        let mut state = self.state();
+        if let Some(job_id) = job_id {
+            row(&state.jobs, job_id)?;
+        }
+        state.next_webhook += 1;
+        let id = state.next_webhook;
+        state.webhooks.push(Webhook {
+            id,
+            job: job_id,
+            url: url.to_string(),
+            created_at: now(),
+        });
+        Ok(id)
+    }
+
+    async fn webhooks(&self) -> Result<Vec<Webhook>, Error> {
+        Ok(self.state().webhooks.clone())
+    }
+
+    async fn webhook(&self, id: i64) -> Result<Webhook, Error> {
+        self.state()
+            .webhooks
+            .iter()
+            .find(|webhook| webhook.id == id)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn job_webhooks(&self, job_id: i64) -> Result<Vec<Webhook>, Error> {
+        Ok(self
+            .state()
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.job.is_none_or(|job| job == job_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_webhook(&self, id: i64) -> Result<(), Error> {
+        let mut state = self.state();
+        state.deliveries.retain(|delivery| delivery.webhook != id);
+        state.webhooks.retain(|webhook| webhook.id != id);
+        Ok(())
+    }
+
+    async fn record_delivery(
+        &self,
+        webhook_id: i64,
+        execution_id: Option<i64>,
+        attempts: i64,
+        status_code: Option<i64>,
+        error: Option<&str>,
+    ) -> Result<i64, Error> {
+        let mut state = self.state();
+        if !state
+            .webhooks
+            .iter()
+            .any(|webhook| webhook.id == webhook_id)
+        {
+            return Err(Error::NotFound);
+        }
+        let id = state.deliveries.len() as i64 + 1;
+        state.deliveries.push(Delivery {
+            id,
+            webhook: webhook_id,
+            execution: execution_id,
+            attempts,
+            status_code,
+            error: error.map(str::to_string),
+            delivered_at: now(),
+        });
+        Ok(id)
+    }
+
+    async fn deliveries(&self, webhook_id: i64, limit: i64) -> Result<Vec<Delivery>, Error> {
+        Ok(self
+            .state()
+            .deliveries
+            .iter()
+            .rev()
+            .filter(|delivery| delivery.webhook == webhook_id)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn mail_settings(&self, job_id: i64) -> Result<MailSettings, Error> {
+        let state = self.state();
+        row(&state.jobs, job_id)?;
+        Ok(state
+            .mail_settings
+            .get(&job_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn set_mail_settings(&self, job_id: i64, settings: &MailSettings) -> Result<(), Error> {
+        let mut state = self.state();
+        row(&state.jobs, job_id)?;
+        state.mail_settings.insert(job_id, settings.clone());
+        Ok(())
+    }
+
+    /// Memory needs no migrations.
+    async fn check_migrations(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
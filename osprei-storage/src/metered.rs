@@ -0,0 +1,152 @@
+use std::time::Instant;
+
+use async_trait::async_trait;
+use osprei_data::{StageDefinition, Template};
+
+use crate::{
+    ApiToken, Artifact, Delivery, Error, Execution, ExecutionStatus, Filter, Grant, Group,
+    MailSettings, Role, Stage, StageExecution, Storage, TokenScope, User, Webhook,
+};
+
+/// Histogram of the time taken by each storage operation, labelled by
+/// `operation`.
+pub const DURATION_METRIC: &str = "osprei_storage_duration_seconds";
+
+/// Backend recording how long each of its operations takes.
+pub struct Metered<S> {
+    inner: S,
+}
+
+impl<S: Storage> Metered<S> {
+    pub fn new(inner: S) -> Metered<S> {
+        Metered { inner }
+    }
+}
+
+/// Implements `Storage` by timing the same operation of the inner backend.
+macro_rules! metered {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $output:ty;)*) => {
+        #[async_trait]
+        impl<S: Storage> Storage for Metered<S> {
+            $(
+                async fn $name(&self $(, $arg: $ty)*) -> Result<$output, Error> {
+                    let start = Instant::now();
+                    let result = self.inner.$name($($arg),*).await;
+                    let elapsed = start.elapsed().as_secs_f64();
+                    metrics::histogram!(DURATION_METRIC, elapsed, "operation" => stringify!($name));
+                    result
+                }
+            )*
+        }
+    };
+}
+
+metered! {
+    fn job_ids(&self) -> Vec<i64>;
+    fn job_source(&self, id: i64) -> String;
+    fn job_status(&self, id: i64) -> Option<ExecutionStatus>;
+    fn job_last_execution(&self, id: i64) -> Option<i64>;
+    fn create_job(&self, source: String) -> i64;
+
+    fn stages_for_job(&self, job_id: i64) -> Vec<Stage>;
+    fn create_stage(
+        &self,
+        job_id: i64,
+        dependency: Option<i64>,
+        definition: &StageDefinition
+    ) -> i64;
+
+    fn template_names(&self) -> Vec<String>;
+    fn template(&self, name: &str) -> Template;
+
+    fn create_execution(
+        &self,
+        job_id: i64,
+        triggered_by: &str,
+        git_ref: Option<&str>
+    ) -> i64;
+    fn execution(&self, id: i64) -> Execution;
+    fn execution_status(&self, id: i64) -> ExecutionStatus;
+    fn list_executions(&self, filter: &Filter, limit: i64, offset: i64) -> Vec<Execution>;
+    fn count_executions(&self, filter: &Filter) -> i64;
+    fn set_execution_commit(&self, id: i64, commit_hash: &str) -> ();
+    fn set_execution_running(&self, id: i64) -> ();
+    fn finish_execution(&self, id: i64, status: ExecutionStatus) -> ();
+    fn interrupt_executions(&self) -> u64;
+
+    fn create_stage_executions(&self, execution_id: i64, stages: Vec<(i64, String)>) -> ();
+    fn start_stage_execution(&self, execution_id: i64, position: i64) -> ();
+    fn finish_stage_execution(
+        &self,
+        execution_id: i64,
+        position: i64,
+        status: ExecutionStatus
+    ) -> ();
+    fn stage_executions(&self, execution_id: i64) -> Vec<StageExecution>;
+    fn stage_execution(&self, id: i64) -> StageExecution;
+    fn latest_stage_executions(&self, job_id: i64) -> Vec<StageExecution>;
+
+    fn create_artifacts(&self, execution_id: i64, files: Vec<(String, i64)>) -> ();
+    fn artifacts(&self, execution_id: i64) -> Vec<Artifact>;
+    fn artifact(&self, id: i64) -> Artifact;
+    fn expired_executions(&self, job_id: i64, keep: i64) -> Vec<i64>;
+    fn delete_artifacts(&self, execution_id: i64) -> ();
+
+    fn create_user(&self, username: &str, password_hash: &str, admin: bool) -> i64;
+    fn user(&self, id: i64) -> User;
+    fn user_by_name(&self, username: &str) -> User;
+    fn create_session(&self, token_hash: &str, user_id: i64, lifetime: i64) -> ();
+    fn session_user(&self, token_hash: &str) -> User;
+    fn delete_session(&self, token_hash: &str) -> ();
+
+    fn create_token(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        name: &str,
+        service: bool,
+        scope: TokenScope,
+        jobs: &[i64]
+    ) -> i64;
+    fn tokens(&self) -> Vec<ApiToken>;
+    fn token(&self, id: i64) -> ApiToken;
+    fn use_token(&self, token_hash: &str) -> ApiToken;
+    fn delete_token(&self, id: i64) -> ();
+
+    fn create_group(&self, name: &str) -> i64;
+    fn groups(&self) -> Vec<Group>;
+    fn job_group(&self, job_id: i64) -> Option<i64>;
+    fn job_groups(&self) -> Vec<(i64, Option<i64>)>;
+    fn set_job_group(&self, job_id: i64, group_id: Option<i64>) -> ();
+    fn grant_role(
+        &self,
+        user_id: i64,
+        job_id: Option<i64>,
+        group_id: Option<i64>,
+        role: Role
+    ) -> i64;
+    fn grants(&self) -> Vec<Grant>;
+    fn user_grants(&self, user_id: i64) -> Vec<Grant>;
+    fn grant(&self, id: i64) -> Grant;
+    fn delete_grant(&self, id: i64) -> ();
+
+    fn create_webhook(&self, job_id: Option<i64>, url: &str) -> i64;
+    fn webhooks(&self) -> Vec<Webhook>;
+    fn webhook(&self, id: i64) -> Webhook;
+    fn job_webhooks(&self, job_id: i64) -> Vec<Webhook>;
+    fn delete_webhook(&self, id: i64) -> ();
+    fn record_delivery(
+        &self,
+        webhook_id: i64,
+        execution_id: Option<i64>,
+        attempts: i64,
+        status_code: Option<i64>,
+        error: Option<&str>
+    ) -> i64;
+    fn deliveries(&self, webhook_id: i64, limit: i64) -> Vec<Delivery>;
+
+    fn mail_settings(&self, job_id: i64) -> MailSettings;
+    fn set_mail_settings(&self, job_id: i64, settings: &MailSettings) -> ();
+
+    fn check_migrations(&self) -> ();
+}
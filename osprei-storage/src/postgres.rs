@@ -0,0 +1,1272 @@
+use async_trait::async_trait;
+use osprei_data::{StageDefinition, Template};
+use sqlx::migrate::Migrator;
+use sqlx::postgres::PgPool;
+
+use crate::{
+    execution, grants, mail, pending_migrations, stage_execution, stages, status_code,
+    status_from_code, tokens, ApiToken, Artifact, Delivery, Error, Execution, ExecutionStatus,
+    Filter, Grant, Group, MailSettings, Role, Stage, StageExecution, Storage, TokenScope, User,
+    Webhook,
+};
+
+const EXECUTION_COLUMNS: &str = "
+    executions.id,
+    jobs.id AS job,
+    jobs.source,
+    executions.triggered_by,
+    executions.git_ref,
+    executions.commit_hash,
+    EXTRACT(EPOCH FROM executions.start_time)::BIGINT AS start_time,
+    EXTRACT(EPOCH FROM executions.end_time)::BIGINT AS end_time,
+    executions.status,
+    EXTRACT(EPOCH FROM executions.end_time - executions.start_time)::BIGINT AS duration
+";
+
+const EXECUTION_FILTER: &str = "
+    ($1::BIGINT IS NULL OR executions.job = $1)
+    AND ($2::BIGINT IS NULL OR executions.status = $2)
+    AND (NOT $3 OR executions.status IS NULL)
+    AND ($4::TEXT IS NULL OR executions.git_ref = $4
+        OR substr(executions.commit_hash, 1, length($4)) = $4)
+    AND ($5::TEXT IS NULL OR (executions.start_time AT TIME ZONE 'UTC')::DATE >= $5::DATE)
+    AND ($6::TEXT IS NULL OR (executions.start_time AT TIME ZONE 'UTC')::DATE <= $6::DATE)
+    AND ($7::BIGINT[] IS NULL OR executions.job = ANY($7))
+";
+
+const USER_COLUMNS: &str = "users.id, username, password_hash, admin";
+
+const TOKEN_COLUMNS: &str = "
+    api_tokens.id,
+    name,
+    user_id,
+    username,
+    service,
+    scope,
+    jobs,
+    EXTRACT(EPOCH FROM created_at)::BIGINT AS created_at,
+    EXTRACT(EPOCH FROM last_used)::BIGINT AS last_used
+";
+
+const GRANT_COLUMNS: &str = r#"
+    grants.id,
+    user_id,
+    username,
+    job_id AS job,
+    group_id AS "group",
+    role
+"#;
+
+const WEBHOOK_COLUMNS: &str = "
+    id,
+    job_id AS job,
+    url,
+    EXTRACT(EPOCH FROM created_at)::BIGINT AS created_at
+";
+
+const DELIVERY_COLUMNS: &str = "
+    id,
+    webhook_id AS webhook,
+    execution_id AS execution,
+    attempts,
+    status_code,
+    error,
+    EXTRACT(EPOCH FROM delivered_at)::BIGINT AS delivered_at
+";
+
+const STAGE_EXECUTION_COLUMNS: &str = "
+    id,
+    execution,
+    stage,
+    position,
+    name,
+    start_time IS NOT NULL AS started,
+    status,
+    EXTRACT(EPOCH FROM end_time - start_time)::BIGINT AS duration
+";
+
+static MIGRATOR: Migrator = sqlx::migrate!("../migrations/postgres");
+
+/// Storage in a PostgreSQL database.
+#[derive(Clone)]
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    /// Opens a pool to the database and applies pending migrations.
+    pub async fn connect(url: &str) -> Result<PostgresStorage, Error> {
+        log::info!("Connecting to database: {}", url);
+        let pool = PgPool::connect(url).await?;
+        log::info!("Connected to database");
+        MIGRATOR.run(&pool).await?;
+        log::info!("Applied migrations");
+        Ok(PostgresStorage { pool })
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn job_ids(&self) -> Result<Vec<i64>, Error> {
+        log::info!("Get ids");
+        let jobs = sqlx::query_scalar(
+            "
+            SELECT id
+            FROM jobs
+            ORDER BY id
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(jobs)
+    }
+
+    async fn job_source(&self, id: i64) -> Result<String, Error> {
+        log::info!("Get ({id}) source");
+        let source = sqlx::query_scalar(
+            "
+            SELECT source
+            FROM jobs
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(source)
+    }
+
+    async fn job_status(&self, id: i64) -> Result<Option<ExecutionStatus>, Error> {
+        log::info!("Get ({id}) status");
+        let status = sqlx::query_scalar::<_, Option<i64>>(
+            "
+            SELECT status
+            FROM executions
+            WHERE job = $1
+            ORDER BY id DESC
+            LIMIT 1
+            ",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(status_from_code)
+        .transpose()?;
+        Ok(status)
+    }
+
+    async fn job_last_execution(&self, id: i64) -> Result<Option<i64>, Error> {
+        log::info!("Get ({id}) last execution");
+        let execution = sqlx::query_scalar(
+            "
+            SELECT id
+            FROM executions
+            WHERE job = $1
+            ORDER BY id DESC
+            LIMIT 1
+            ",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(execution)
+    }
+
+    async fn create_job(&self, source: String) -> Result<i64, Error> {
+        log::info!("Insert ({source})");
+        let mut transaction = self.pool.begin().await?;
+        let id: i64 = sqlx::query_scalar(
+            "
+            INSERT INTO jobs
+            (source)
+            VALUES ($1)
+            RETURNING id
+            ",
+        )
+        .bind(&source)
+        .fetch_one(&mut *transaction)
+        .await?;
+        let checkout = serde_json::to_string(&stages::checkout(source))?;
+        sqlx::query(
+            "
+            INSERT INTO stages
+            (job, dependency, definition)
+            VALUES ($1, NULL, $2)
+            ",
+        )
+        .bind(id)
+        .bind(checkout)
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        log::info!("Inserted");
+        Ok(id)
+    }
+
+    async fn stages_for_job(&self, job_id: i64) -> Result<Vec<Stage>, Error> {
+        log::info!("Get stages for job ({job_id})");
+        let query: Vec<(i64, Option<i64>, String)> = sqlx::query_as(
+            "
+            SELECT id, dependency, definition
+            FROM stages
+            WHERE job = $1 ORDER BY id
+            ",
+        )
+        .bind(job_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut stages = Vec::with_capacity(query.len());
+        for (id, dependency, definition) in query {
+            let definition: StageDefinition = serde_json::from_str(&definition)?;
+            stages.push(Stage {
+                id,
+                dependency,
+                definition,
+            });
+        }
+        Ok(stages)
+    }
+
+    async fn create_stage(
+        &self,
+        job_id: i64,
+        dependency: Option<i64>,
+        definition: &StageDefinition,
+    ) -> Result<i64, Error> {
+        log::info!("Insert for job ({job_id})");
+        let definition = serde_json::to_string(definition)?;
+        let id = sqlx::query_scalar(
+            "
+            INSERT INTO stages
+            (job, dependency, definition)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            ",
+        )
+        .bind(job_id)
+        .bind(dependency)
+        .bind(definition)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn template_names(&self) -> Result<Vec<String>, Error> {
+        log::info!("Get names");
+        let names = sqlx::query_scalar(
+            "
+            SELECT name
+            FROM templates
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(names)
+    }
+
+    async fn template(&self, name: &str) -> Result<Template, Error> {
+        log::info!("Get for {name}");
+        let definition: String = sqlx::query_scalar(
+            "
+            SELECT definition
+            FROM templates
+            WHERE name = $1
+            ",
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await?;
+        let definition = serde_json::from_str(&definition)?;
+        Ok(definition)
+    }
+
+    async fn create_execution(
+        &self,
+        job_id: i64,
+        triggered_by: &str,
+        git_ref: Option<&str>,
+    ) -> Result<i64, Error> {
+        log::info!("Insert execution with job ({job_id}) triggered by {triggered_by}");
+        let execution_id = sqlx::query_scalar(
+            "
+            INSERT INTO executions
+            (job, start_time, triggered_by, git_ref, status)
+            VALUES ($1, now(), $2, $3, $4)
+            RETURNING id
+            ",
+        )
+        .bind(job_id)
+        .bind(triggered_by)
+        .bind(git_ref)
+        .bind(status_code(ExecutionStatus::Queued))
+        .fetch_one(&self.pool)
+        .await?;
+        log::info!("Inserted");
+        Ok(execution_id)
+    }
+
+    async fn execution(&self, id: i64) -> Result<Execution, Error> {
+        log::info!("Get execution ({id})");
+        let sql = format!(
+            "
+            SELECT {EXECUTION_COLUMNS}
+            FROM executions
+            INNER JOIN jobs ON executions.job = jobs.id
+            WHERE executions.id = $1
+            "
+        );
+        let execution = sqlx::query_as::<_, execution::Query>(&sql)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?
+            .try_into()?;
+        Ok(execution)
+    }
+
+    async fn execution_status(&self, id: i64) -> Result<ExecutionStatus, Error> {
+        log::info!("Get execution ({id}) status");
+        let status = sqlx::query_scalar::<_, Option<i64>>(
+            "
+            SELECT status
+            FROM executions
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        status_from_code(status)
+    }
+
+    async fn list_executions(
+        &self,
+        filter: &Filter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Execution>, Error> {
+        log::info!("List executions ({filter:?}) limit {limit} offset {offset}");
+        let (status, running) = execution::status_parameters(filter.status);
+        let sql = format!(
+            "
+            SELECT {EXECUTION_COLUMNS}
+            FROM executions
+            INNER JOIN jobs ON executions.job = jobs.id
+            WHERE {EXECUTION_FILTER}
+            ORDER BY executions.id DESC
+            LIMIT $8 OFFSET $9
+            "
+        );
+        let executions = sqlx::query_as::<_, execution::Query>(&sql)
+            .bind(filter.job)
+            .bind(status)
+            .bind(running)
+            .bind(&filter.git_ref)
+            .bind(&filter.since)
+            .bind(&filter.until)
+            .bind(&filter.jobs)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(Execution::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(executions)
+    }
+
+    async fn count_executions(&self, filter: &Filter) -> Result<i64, Error> {
+        log::info!("Count executions ({filter:?})");
+        let (status, running) = execution::status_parameters(filter.status);
+        let sql = format!(
+            "
+            SELECT COUNT(*)
+            FROM executions
+            WHERE {EXECUTION_FILTER}
+            "
+        );
+        let count = sqlx::query_scalar(&sql)
+            .bind(filter.job)
+            .bind(status)
+            .bind(running)
+            .bind(&filter.git_ref)
+            .bind(&filter.since)
+            .bind(&filter.until)
+            .bind(&filter.jobs)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    async fn set_execution_commit(&self, id: i64, commit_hash: &str) -> Result<(), Error> {
+        log::info!("Set execution ({id}) commit ({commit_hash})");
+        sqlx::query(
+            "
+            UPDATE executions
+            SET commit_hash = $2
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(commit_hash)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_execution_running(&self, id: i64) -> Result<(), Error> {
+        log::info!("Run execution ({id})");
+        sqlx::query(
+            "
+            UPDATE executions
+            SET
+                status = NULL,
+                start_time = now()
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn interrupt_executions(&self) -> Result<u64, Error> {
+        log::info!("Interrupt unfinished executions");
+        let interrupted = status_code(ExecutionStatus::Interrupted);
+        let mut transaction = self.pool.begin().await?;
+        let count = sqlx::query(
+            "
+            UPDATE executions
+            SET
+                status = $1,
+                end_time = now()
+            WHERE status IS NULL OR status = $2
+            ",
+        )
+        .bind(interrupted)
+        .bind(status_code(ExecutionStatus::Queued))
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+        sqlx::query(
+            "
+            UPDATE stage_executions
+            SET
+                status = $1,
+                end_time = now()
+            WHERE start_time IS NOT NULL AND status IS NULL
+            ",
+        )
+        .bind(interrupted)
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(count)
+    }
+
+    async fn finish_execution(&self, id: i64, status: ExecutionStatus) -> Result<(), Error> {
+        log::info!("Finish execution ({id}) with status ({status:?})");
+        let status = status_code(status);
+        sqlx::query(
+            "
+            UPDATE executions
+            SET
+                status = $2,
+                end_time = now()
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(status)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_stage_executions(
+        &self,
+        execution_id: i64,
+        stages: Vec<(i64, String)>,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Insert {} stages for execution ({execution_id})",
+            stages.len()
+        );
+        let mut transaction = self.pool.begin().await?;
+        for (position, (stage, name)) in stages.into_iter().enumerate() {
+            sqlx::query(
+                "
+                INSERT INTO stage_executions
+                (execution, stage, position, name)
+                VALUES ($1, $2, $3, $4)
+                ",
+            )
+            .bind(execution_id)
+            .bind(stage)
+            .bind(position as i64)
+            .bind(name)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn start_stage_execution(&self, execution_id: i64, position: i64) -> Result<(), Error> {
+        log::info!("Start stage ({position}) of execution ({execution_id})");
+        sqlx::query(
+            "
+            UPDATE stage_executions
+            SET start_time = now()
+            WHERE execution = $1 AND position = $2
+            ",
+        )
+        .bind(execution_id)
+        .bind(position)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn finish_stage_execution(
+        &self,
+        execution_id: i64,
+        position: i64,
+        status: ExecutionStatus,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Finish stage ({position}) of execution ({execution_id}) with status ({status:?})"
+        );
+        let status = status_code(status);
+        sqlx::query(
+            "
+            UPDATE stage_executions
+            SET
+                status = $3,
+                end_time = now()
+            WHERE execution = $1 AND position = $2
+            ",
+        )
+        .bind(execution_id)
+        .bind(position)
+        .bind(status)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn stage_executions(&self, execution_id: i64) -> Result<Vec<StageExecution>, Error> {
+        log::info!("Get stages of execution ({execution_id})");
+        let sql = format!(
+            "
+            SELECT {STAGE_EXECUTION_COLUMNS}
+            FROM stage_executions
+            WHERE execution = $1
+            ORDER BY position
+            "
+        );
+        let stages = sqlx::query_as::<_, stage_execution::Query>(&sql)
+            .bind(execution_id)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(StageExecution::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(stages)
+    }
+
+    async fn stage_execution(&self, id: i64) -> Result<StageExecution, Error> {
+        log::info!("Get stage execution ({id})");
+        let sql = format!(
+            "
+            SELECT {STAGE_EXECUTION_COLUMNS}
+            FROM stage_executions
+            WHERE id = $1
+            "
+        );
+        let stage = sqlx::query_as::<_, stage_execution::Query>(&sql)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?
+            .try_into()?;
+        Ok(stage)
+    }
+
+    async fn latest_stage_executions(&self, job_id: i64) -> Result<Vec<StageExecution>, Error> {
+        log::info!("Get stages of latest execution of job ({job_id})");
+        let sql = format!(
+            "
+            SELECT {STAGE_EXECUTION_COLUMNS}
+            FROM stage_executions
+            WHERE execution = (
+                SELECT MAX(id)
+                FROM executions
+                WHERE job = $1
+            )
+            ORDER BY position
+            "
+        );
+        let stages = sqlx::query_as::<_, stage_execution::Query>(&sql)
+            .bind(job_id)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(StageExecution::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(stages)
+    }
+
+    async fn create_artifacts(
+        &self,
+        execution_id: i64,
+        files: Vec<(String, i64)>,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Insert {} artifacts for execution ({execution_id})",
+            files.len()
+        );
+        let mut transaction = self.pool.begin().await?;
+        for (path, size) in files {
+            sqlx::query(
+                "
+                INSERT INTO artifacts
+                (execution, path, size)
+                VALUES ($1, $2, $3)
+                ",
+            )
+            .bind(execution_id)
+            .bind(path)
+            .bind(size)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn artifacts(&self, execution_id: i64) -> Result<Vec<Artifact>, Error> {
+        log::info!("Get artifacts for execution ({execution_id})");
+        let artifacts = sqlx::query_as(
+            "
+            SELECT id, execution, path, size
+            FROM artifacts
+            WHERE execution = $1
+            ORDER BY path
+            ",
+        )
+        .bind(execution_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(artifacts)
+    }
+
+    async fn artifact(&self, id: i64) -> Result<Artifact, Error> {
+        log::info!("Get artifact ({id})");
+        let artifact = sqlx::query_as(
+            "
+            SELECT id, execution, path, size
+            FROM artifacts
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(artifact)
+    }
+
+    async fn expired_executions(&self, job_id: i64, keep: i64) -> Result<Vec<i64>, Error> {
+        log::info!("Get executions of job ({job_id}) past the {keep} most recent");
+        let expired = sqlx::query_scalar(
+            "
+            SELECT id
+            FROM executions
+            WHERE job = $1
+            ORDER BY id DESC
+            OFFSET $2
+            ",
+        )
+        .bind(job_id)
+        .bind(keep)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(expired)
+    }
+
+    async fn delete_artifacts(&self, execution_id: i64) -> Result<(), Error> {
+        log::info!("Delete artifacts of execution ({execution_id})");
+        sqlx::query(
+            "
+            DELETE FROM artifacts
+            WHERE execution = $1
+            ",
+        )
+        .bind(execution_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        admin: bool,
+    ) -> Result<i64, Error> {
+        log::info!("Create user ({username})");
+        let id = sqlx::query_scalar(
+            "
+            INSERT INTO users (username, password_hash, admin)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            ",
+        )
+        .bind(username)
+        .bind(password_hash)
+        .bind(admin)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn user(&self, id: i64) -> Result<User, Error> {
+        log::info!("Get user ({id})");
+        let sql = format!(
+            "
+            SELECT {USER_COLUMNS}
+            FROM users
+            WHERE id = $1
+            "
+        );
+        let user = sqlx::query_as::<_, User>(&sql)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(user)
+    }
+
+    async fn user_by_name(&self, username: &str) -> Result<User, Error> {
+        log::info!("Get user ({username})");
+        let sql = format!(
+            "
+            SELECT {USER_COLUMNS}
+            FROM users
+            WHERE username = $1
+            "
+        );
+        let user = sqlx::query_as::<_, User>(&sql)
+            .bind(username)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(user)
+    }
+
+    async fn create_session(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        lifetime: i64,
+    ) -> Result<(), Error> {
+        log::info!("Create session of user ({user_id})");
+        sqlx::query(
+            "
+            INSERT INTO sessions (token_hash, user_id, expires_at)
+            VALUES ($1, $2, now() + make_interval(secs => $3))
+            ",
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .bind(lifetime as f64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn session_user(&self, token_hash: &str) -> Result<User, Error> {
+        let sql = format!(
+            "
+            SELECT {USER_COLUMNS}
+            FROM sessions
+            INNER JOIN users ON sessions.user_id = users.id
+            WHERE token_hash = $1 AND expires_at > now()
+            "
+        );
+        let user = sqlx::query_as::<_, User>(&sql)
+            .bind(token_hash)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(user)
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> Result<(), Error> {
+        log::info!("Delete session");
+        sqlx::query(
+            "
+            DELETE FROM sessions
+            WHERE token_hash = $1 OR expires_at <= now()
+            ",
+        )
+        .bind(token_hash)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        name: &str,
+        service: bool,
+        scope: TokenScope,
+        jobs: &[i64],
+    ) -> Result<i64, Error> {
+        log::info!("Create token ({name}) of user ({user_id})");
+        let id = sqlx::query_scalar(
+            "
+            INSERT INTO api_tokens (token_hash, user_id, name, service, scope, jobs, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, now())
+            RETURNING id
+            ",
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .bind(name)
+        .bind(service)
+        .bind(scope.key())
+        .bind(serde_json::to_string(jobs)?)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn tokens(&self) -> Result<Vec<ApiToken>, Error> {
+        log::info!("Get tokens");
+        let sql = format!(
+            "
+            SELECT {TOKEN_COLUMNS}
+            FROM api_tokens
+            INNER JOIN users ON api_tokens.user_id = users.id
+            ORDER BY api_tokens.id DESC
+            "
+        );
+        sqlx::query_as::<_, tokens::Query>(&sql)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(ApiToken::try_from)
+            .collect()
+    }
+
+    async fn token(&self, id: i64) -> Result<ApiToken, Error> {
+        log::info!("Get token ({id})");
+        let sql = format!(
+            "
+            SELECT {TOKEN_COLUMNS}
+            FROM api_tokens
+            INNER JOIN users ON api_tokens.user_id = users.id
+            WHERE api_tokens.id = $1
+            "
+        );
+        sqlx::query_as::<_, tokens::Query>(&sql)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?
+            .try_into()
+    }
+
+    async fn use_token(&self, token_hash: &str) -> Result<ApiToken, Error> {
+        let sql = format!(
+            "
+            WITH used AS (
+                UPDATE api_tokens
+                SET last_used = now()
+                WHERE token_hash = $1
+                RETURNING *
+            )
+            SELECT {TOKEN_COLUMNS}
+            FROM used AS api_tokens
+            INNER JOIN users ON api_tokens.user_id = users.id
+            "
+        );
+        sqlx::query_as::<_, tokens::Query>(&sql)
+            .bind(token_hash)
+            .fetch_one(&self.pool)
+            .await?
+            .try_into()
+    }
+
+    async fn delete_token(&self, id: i64) -> Result<(), Error> {
+        log::info!("Delete token ({id})");
+        sqlx::query(
+            "
+            DELETE FROM api_tokens
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_group(&self, name: &str) -> Result<i64, Error> {
+        log::info!("Create group ({name})");
+        let id = sqlx::query_scalar(
+            "
+            INSERT INTO project_groups (name)
+            VALUES ($1)
+            RETURNING id
+            ",
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn groups(&self) -> Result<Vec<Group>, Error> {
+        log::info!("Get groups");
+        let groups = sqlx::query_as::<_, Group>(
+            "
+            SELECT id, name
+            FROM project_groups
+            ORDER BY name
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(groups)
+    }
+
+    async fn job_group(&self, job_id: i64) -> Result<Option<i64>, Error> {
+        let group = sqlx::query_scalar(
+            "
+            SELECT group_id
+            FROM jobs
+            WHERE id = $1
+            ",
+        )
+        .bind(job_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(group)
+    }
+
+    async fn job_groups(&self) -> Result<Vec<(i64, Option<i64>)>, Error> {
+        let groups = sqlx::query_as(
+            "
+            SELECT id, group_id
+            FROM jobs
+            ORDER BY id
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(groups)
+    }
+
+    async fn set_job_group(&self, job_id: i64, group_id: Option<i64>) -> Result<(), Error> {
+        log::info!("Set job ({job_id}) group ({group_id:?})");
+        let updated = sqlx::query(
+            "
+            UPDATE jobs
+            SET group_id = $2
+            WHERE id = $1
+            ",
+        )
+        .bind(job_id)
+        .bind(group_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn grant_role(
+        &self,
+        user_id: i64,
+        job_id: Option<i64>,
+        group_id: Option<i64>,
+        role: Role,
+    ) -> Result<i64, Error> {
+        log::info!("Grant {role:?} to user ({user_id}) on job ({job_id:?}) group ({group_id:?})");
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query(
+            "
+            DELETE FROM grants
+            WHERE user_id = $1
+                AND job_id IS NOT DISTINCT FROM $2
+                AND group_id IS NOT DISTINCT FROM $3
+            ",
+        )
+        .bind(user_id)
+        .bind(job_id)
+        .bind(group_id)
+        .execute(&mut *transaction)
+        .await?;
+        let id = sqlx::query_scalar(
+            "
+            INSERT INTO grants (user_id, job_id, group_id, role)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            ",
+        )
+        .bind(user_id)
+        .bind(job_id)
+        .bind(group_id)
+        .bind(role.key())
+        .fetch_one(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(id)
+    }
+
+    async fn grants(&self) -> Result<Vec<Grant>, Error> {
+        log::info!("Get grants");
+        let sql = format!(
+            "
+            SELECT {GRANT_COLUMNS}
+            FROM grants
+            INNER JOIN users ON grants.user_id = users.id
+            ORDER BY grants.id
+            "
+        );
+        let grants = sqlx::query_as::<_, grants::Query>(&sql)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(Grant::from)
+            .collect();
+        Ok(grants)
+    }
+
+    async fn user_grants(&self, user_id: i64) -> Result<Vec<Grant>, Error> {
+        let sql = format!(
+            "
+            SELECT {GRANT_COLUMNS}
+            FROM grants
+            INNER JOIN users ON grants.user_id = users.id
+            WHERE user_id = $1
+            ORDER BY grants.id
+            "
+        );
+        let grants = sqlx::query_as::<_, grants::Query>(&sql)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(Grant::from)
+            .collect();
+        Ok(grants)
+    }
+
+    async fn grant(&self, id: i64) -> Result<Grant, Error> {
+        let sql = format!(
+            "
+            SELECT {GRANT_COLUMNS}
+            FROM grants
+            INNER JOIN users ON grants.user_id = users.id
+            WHERE grants.id = $1
+            "
+        );
+        let grant = sqlx::query_as::<_, grants::Query>(&sql)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(grant.into())
+    }
+
+    async fn delete_grant(&self, id: i64) -> Result<(), Error> {
+        log::info!("Delete grant ({id})");
+        sqlx::query(
+            "
+            DELETE FROM grants
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_webhook(&self, job_id: Option<i64>, url: &str) -> Result<i64, Error> {
+        log::info!("Create webhook of job ({job_id:?})");
+        let id = sqlx::query_scalar(
+            "
+            INSERT INTO webhooks (job_id, url, created_at)
+            VALUES ($1, $2, now())
+            RETURNING id
+            ",
+        )
+        .bind(job_id)
+        .bind(url)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn webhooks(&self) -> Result<Vec<Webhook>, Error> {
+        log::info!("Get webhooks");
+        let sql = format!(
+            "
+            SELECT {WEBHOOK_COLUMNS}
+            FROM webhooks
+            ORDER BY id
+            "
+        );
+        let webhooks = sqlx::query_as::<_, Webhook>(&sql)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(webhooks)
+    }
+
+    async fn webhook(&self, id: i64) -> Result<Webhook, Error> {
+        let sql = format!(
+            "
+            SELECT {WEBHOOK_COLUMNS}
+            FROM webhooks
+            WHERE id = $1
+            "
+        );
+        let webhook = sqlx::query_as::<_, Webhook>(&sql)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(webhook)
+    }
+
+    async fn job_webhooks(&self, job_id: i64) -> Result<Vec<Webhook>, Error> {
+        let sql = format!(
+            "
+            SELECT {WEBHOOK_COLUMNS}
+            FROM webhooks
+            WHERE job_id IS NULL OR job_id = $1
+            ORDER BY id
+            "
+        );
+        let webhooks = sqlx::query_as::<_, Webhook>(&sql)
+            .bind(job_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(webhooks)
+    }
+
+    async fn delete_webhook(&self, id: i64) -> Result<(), Error> {
+        log::info!("Delete webhook ({id})");
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query(
+            "
+            DELETE FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ",
+        )
+        .bind(id)
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query(
+            "
+            DELETE FROM webhooks
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn record_delivery(
+        &self,
+        webhook_id: i64,
+        execution_id: Option<i64>,
+        attempts: i64,
+        status_code: Option<i64>,
+        error: Option<&str>,
+    ) -> Result<i64, Error> {
+        let id = sqlx::query_scalar(
+            "
+            INSERT INTO webhook_deliveries
+                (webhook_id, execution_id, attempts, status_code, error, delivered_at)
+            VALUES ($1, $2, $3, $4, $5, now())
+            RETURNING id
+            ",
+        )
+        .bind(webhook_id)
+        .bind(execution_id)
+        .bind(attempts)
+        .bind(status_code)
+        .bind(error)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn deliveries(&self, webhook_id: i64, limit: i64) -> Result<Vec<Delivery>, Error> {
+        let sql = format!(
+            "
+            SELECT {DELIVERY_COLUMNS}
+            FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY id DESC
+            LIMIT $2
+            "
+        );
+        let deliveries = sqlx::query_as::<_, Delivery>(&sql)
+            .bind(webhook_id)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(deliveries)
+    }
+
+    async fn mail_settings(&self, job_id: i64) -> Result<MailSettings, Error> {
+        sqlx::query_as::<_, mail::Query>(
+            "
+            SELECT mail_recipients, mail_commit_author, mail_every_run
+            FROM jobs
+            WHERE id = $1
+            ",
+        )
+        .bind(job_id)
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()
+    }
+
+    async fn set_mail_settings(&self, job_id: i64, settings: &MailSettings) -> Result<(), Error> {
+        log::info!("Set job ({job_id}) mail settings");
+        let recipients = serde_json::to_string(&settings.recipients)?;
+        let updated = sqlx::query(
+            "
+            UPDATE jobs
+            SET mail_recipients = $2, mail_commit_author = $3, mail_every_run = $4
+            WHERE id = $1
+            ",
+        )
+        .bind(job_id)
+        .bind(recipients)
+        .bind(settings.commit_author)
+        .bind(settings.every_run)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn check_migrations(&self) -> Result<(), Error> {
+        let applied: Vec<i64> =
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await?;
+        pending_migrations(&MIGRATOR, &applied)
+    }
+}
@@ -0,0 +1,1421 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use osprei_data::{StageDefinition, Template};
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool};
+
+use crate::{
+    execution, grants, mail, pending_migrations, stage_execution, stages, status_code,
+    status_from_code, tokens, ApiToken, Artifact, Delivery, Error, Execution, ExecutionStatus,
+    Filter, Grant, Group, MailSettings, Role, Stage, StageExecution, Storage, TokenScope, User,
+    Webhook,
+};
+
+static MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+
+/// Storage in a SQLite database file.
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    /// Opens a pool to the database, creating it if needed, and applies pending
+    /// migrations.
+    pub async fn connect(url: &str) -> Result<SqliteStorage, Error> {
+        log::info!("Connecting to database: {}", url);
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true);
+        let pool = SqlitePool::connect_with(options).await?;
+        log::info!("Connected to database");
+        MIGRATOR.run(&pool).await?;
+        log::info!("Applied migrations");
+        Ok(SqliteStorage { pool })
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn job_ids(&self) -> Result<Vec<i64>, Error> {
+        log::info!("Get ids");
+        struct Query {
+            id: i64,
+        }
+        let jobs = sqlx::query_as!(
+            Query,
+            "
+            SELECT id
+            FROM jobs
+            "
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|job| job.id)
+        .collect();
+        Ok(jobs)
+    }
+
+    async fn job_source(&self, id: i64) -> Result<String, Error> {
+        log::info!("Get ({id}) source");
+        struct Query {
+            source: String,
+        }
+        let source = sqlx::query_as!(
+            Query,
+            "
+            SELECT source
+            FROM jobs
+            WHERE id = $1
+            ",
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .source;
+        Ok(source)
+    }
+
+    async fn job_status(&self, id: i64) -> Result<Option<ExecutionStatus>, Error> {
+        log::info!("Get ({id}) status");
+        struct Query {
+            status: Option<i64>,
+        }
+        let status = sqlx::query_as!(
+            Query,
+            "
+            SELECT status
+            FROM (
+                jobs
+                INNER JOIN
+                    executions
+                        ON executions.job = jobs.id
+            )
+            WHERE jobs.id = $1
+            ORDER BY executions.id DESC
+            LIMIT 1
+            ",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|query| status_from_code(query.status))
+        .transpose()?;
+        Ok(status)
+    }
+
+    async fn job_last_execution(&self, id: i64) -> Result<Option<i64>, Error> {
+        log::info!("Get ({id}) last execution");
+        struct Query {
+            id: i64,
+        }
+        let execution = sqlx::query_as!(
+            Query,
+            r#"
+            SELECT id AS "id!: i64"
+            FROM executions
+            WHERE job = $1
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|query| query.id);
+        Ok(execution)
+    }
+
+    async fn create_job(&self, source: String) -> Result<i64, Error> {
+        log::info!("Insert ({source})");
+        let mut transaction = self.pool.begin().await?;
+        let id = sqlx::query!(
+            "
+            INSERT INTO jobs
+            (source)
+            VALUES ($1)
+            ",
+            source
+        )
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_rowid();
+        let checkout = serde_json::to_string(&stages::checkout(source))?;
+        sqlx::query!(
+            "
+            INSERT INTO stages
+            (job, dependency, definition)
+            VALUES ($1, NULL, $2)
+            ",
+            id,
+            checkout
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        log::info!("Inserted");
+        Ok(id)
+    }
+
+    async fn stages_for_job(&self, job_id: i64) -> Result<Vec<Stage>, Error> {
+        log::info!("Get stages for job ({job_id})");
+        struct Query {
+            id: i64,
+            dependency: Option<i64>,
+            definition: String,
+        }
+        let query = sqlx::query_as!(
+            Query,
+            "
+            SELECT id, dependency, definition
+            FROM stages
+            WHERE job = $1 ORDER BY id
+            ",
+            job_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut stages = Vec::with_capacity(query.len());
+        for Query {
+            id,
+            dependency,
+            definition,
+        } in query
+        {
+            let definition: StageDefinition = serde_json::from_str(&definition)?;
+            let stage = Stage {
+                id,
+                dependency,
+                definition,
+            };
+            stages.push(stage);
+        }
+        Ok(stages)
+    }
+
+    async fn create_stage(
+        &self,
+        job_id: i64,
+        dependency: Option<i64>,
+        definition: &StageDefinition,
+    ) -> Result<i64, Error> {
+        log::info!("Insert for job ({job_id})");
+        let definition = serde_json::to_string(definition)?;
+        let id = sqlx::query!(
+            "
+            INSERT INTO stages
+            (job, dependency, definition)
+            VALUES ($1, $2, $3)
+            ",
+            job_id,
+            dependency,
+            definition
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn template_names(&self) -> Result<Vec<String>, Error> {
+        log::info!("Get names");
+        struct Query {
+            name: String,
+        }
+        let names = sqlx::query_as!(
+            Query,
+            "
+            SELECT name
+            FROM templates
+            "
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|query| query.name)
+        .collect();
+        Ok(names)
+    }
+
+    async fn template(&self, name: &str) -> Result<Template, Error> {
+        log::info!("Get for {name}");
+        struct Query {
+            definition: String,
+        }
+        let definition = sqlx::query_as!(
+            Query,
+            "
+            SELECT definition
+            FROM templates
+            WHERE name = $1
+            ",
+            name
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .definition;
+        let definition = serde_json::from_str(&definition)?;
+        Ok(definition)
+    }
+
+    async fn create_execution(
+        &self,
+        job_id: i64,
+        triggered_by: &str,
+        git_ref: Option<&str>,
+    ) -> Result<i64, Error> {
+        log::info!("Insert execution with job ({job_id}) triggered by {triggered_by}");
+        let queued = status_code(ExecutionStatus::Queued);
+        let execution_id = sqlx::query!(
+            "
+            INSERT INTO executions
+            (job, start_time, triggered_by, git_ref, status)
+            VALUES ($1, unixepoch(), $2, $3, $4)
+            ",
+            job_id,
+            triggered_by,
+            git_ref,
+            queued
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        log::info!("Inserted");
+        Ok(execution_id)
+    }
+
+    async fn execution(&self, id: i64) -> Result<Execution, Error> {
+        log::info!("Get execution ({id})");
+        let execution = sqlx::query_as!(
+            execution::Query,
+            r#"
+            SELECT
+                executions.id AS "id!: i64",
+                jobs.id AS "job!: i64",
+                jobs.source AS "source!: String",
+                executions.triggered_by AS "triggered_by!: String",
+                executions.git_ref AS "git_ref?: String",
+                executions.commit_hash AS "commit_hash?: String",
+                executions.start_time AS "start_time?: i64",
+                executions.end_time AS "end_time?: i64",
+                executions.status AS "status?: i64",
+                executions.end_time - executions.start_time AS "duration?: i64"
+            FROM executions
+            INNER JOIN jobs ON executions.job = jobs.id
+            WHERE executions.id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()?;
+        Ok(execution)
+    }
+
+    async fn execution_status(&self, id: i64) -> Result<ExecutionStatus, Error> {
+        log::info!("Get execution ({id}) status");
+        struct Query {
+            status: Option<i64>,
+        }
+        let status = sqlx::query_as!(
+            Query,
+            "
+            SELECT status
+            FROM executions
+            WHERE id = $1
+            ",
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .status;
+        status_from_code(status)
+    }
+
+    async fn list_executions(
+        &self,
+        filter: &Filter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Execution>, Error> {
+        log::info!("List executions ({filter:?}) limit {limit} offset {offset}");
+        let (status, running) = execution::status_parameters(filter.status);
+        let jobs = filter
+            .jobs
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let executions = sqlx::query_as!(
+            execution::Query,
+            r#"
+            SELECT
+                executions.id AS "id!: i64",
+                jobs.id AS "job!: i64",
+                jobs.source AS "source!: String",
+                executions.triggered_by AS "triggered_by!: String",
+                executions.git_ref AS "git_ref?: String",
+                executions.commit_hash AS "commit_hash?: String",
+                executions.start_time AS "start_time?: i64",
+                executions.end_time AS "end_time?: i64",
+                executions.status AS "status?: i64",
+                executions.end_time - executions.start_time AS "duration?: i64"
+            FROM executions
+            INNER JOIN jobs ON executions.job = jobs.id
+            WHERE ($1 IS NULL OR executions.job = $1)
+                AND ($2 IS NULL OR executions.status = $2)
+                AND (NOT $3 OR executions.status IS NULL)
+                AND ($4 IS NULL OR executions.git_ref = $4
+                    OR substr(executions.commit_hash, 1, length($4)) = $4)
+                AND ($5 IS NULL OR date(executions.start_time, 'unixepoch') >= date($5))
+                AND ($6 IS NULL OR date(executions.start_time, 'unixepoch') <= date($6))
+                AND ($7 IS NULL OR executions.job IN (SELECT value FROM json_each($7)))
+            ORDER BY executions.id DESC
+            LIMIT $8 OFFSET $9
+            "#,
+            filter.job,
+            status,
+            running,
+            filter.git_ref,
+            filter.since,
+            filter.until,
+            jobs,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(Execution::try_from)
+        .collect::<Result<_, _>>()?;
+        Ok(executions)
+    }
+
+    async fn count_executions(&self, filter: &Filter) -> Result<i64, Error> {
+        log::info!("Count executions ({filter:?})");
+        let (status, running) = execution::status_parameters(filter.status);
+        let jobs = filter
+            .jobs
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        struct Query {
+            count: i64,
+        }
+        let count = sqlx::query_as!(
+            Query,
+            r#"
+            SELECT COUNT(*) AS "count!: i64"
+            FROM executions
+            WHERE ($1 IS NULL OR job = $1)
+                AND ($2 IS NULL OR status = $2)
+                AND (NOT $3 OR status IS NULL)
+                AND ($4 IS NULL OR git_ref = $4 OR substr(commit_hash, 1, length($4)) = $4)
+                AND ($5 IS NULL OR date(start_time, 'unixepoch') >= date($5))
+                AND ($6 IS NULL OR date(start_time, 'unixepoch') <= date($6))
+                AND ($7 IS NULL OR job IN (SELECT value FROM json_each($7)))
+            "#,
+            filter.job,
+            status,
+            running,
+            filter.git_ref,
+            filter.since,
+            filter.until,
+            jobs
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .count;
+        Ok(count)
+    }
+
+    async fn set_execution_commit(&self, id: i64, commit_hash: &str) -> Result<(), Error> {
+        log::info!("Set execution ({id}) commit ({commit_hash})");
+        sqlx::query!(
+            "
+            UPDATE executions
+            SET commit_hash = $2
+            WHERE id = $1
+            ",
+            id,
+            commit_hash
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_execution_running(&self, id: i64) -> Result<(), Error> {
+        log::info!("Run execution ({id})");
+        sqlx::query!(
+            "
+            UPDATE executions
+            SET
+                status = NULL,
+                start_time = unixepoch()
+            WHERE id = $1
+            ",
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn interrupt_executions(&self) -> Result<u64, Error> {
+        log::info!("Interrupt unfinished executions");
+        let interrupted = status_code(ExecutionStatus::Interrupted);
+        let queued = status_code(ExecutionStatus::Queued);
+        let mut transaction = self.pool.begin().await?;
+        let count = sqlx::query!(
+            "
+            UPDATE executions
+            SET
+                status = $1,
+                end_time = unixepoch()
+            WHERE status IS NULL OR status = $2
+            ",
+            interrupted,
+            queued
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+        sqlx::query!(
+            "
+            UPDATE stage_executions
+            SET
+                status = $1,
+                end_time = unixepoch()
+            WHERE start_time IS NOT NULL AND status IS NULL
+            ",
+            interrupted
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(count)
+    }
+
+    async fn finish_execution(&self, id: i64, status: ExecutionStatus) -> Result<(), Error> {
+        log::info!("Finish execution ({id}) with status ({status:?})");
+        let status = status_code(status);
+        sqlx::query!(
+            "
+            UPDATE executions
+            SET
+                status = $2,
+                end_time = unixepoch()
+            WHERE id = $1
+            ",
+            id,
+            status
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_stage_executions(
+        &self,
+        execution_id: i64,
+        stages: Vec<(i64, String)>,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Insert {} stages for execution ({execution_id})",
+            stages.len()
+        );
+        let mut transaction = self.pool.begin().await?;
+        for (position, (stage, name)) in stages.into_iter().enumerate() {
+            let position = position as i64;
+            sqlx::query!(
+                "
+                INSERT INTO stage_executions
+                (execution, stage, position, name)
+                VALUES ($1, $2, $3, $4)
+                ",
+                execution_id,
+                stage,
+                position,
+                name
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn start_stage_execution(&self, execution_id: i64, position: i64) -> Result<(), Error> {
+        log::info!("Start stage ({position}) of execution ({execution_id})");
+        sqlx::query!(
+            "
+            UPDATE stage_executions
+            SET start_time = unixepoch()
+            WHERE execution = $1 AND position = $2
+            ",
+            execution_id,
+            position
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn finish_stage_execution(
+        &self,
+        execution_id: i64,
+        position: i64,
+        status: ExecutionStatus,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Finish stage ({position}) of execution ({execution_id}) with status ({status:?})"
+        );
+        let status = status_code(status);
+        sqlx::query!(
+            "
+            UPDATE stage_executions
+            SET
+                status = $3,
+                end_time = unixepoch()
+            WHERE execution = $1 AND position = $2
+            ",
+            execution_id,
+            position,
+            status
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn stage_executions(&self, execution_id: i64) -> Result<Vec<StageExecution>, Error> {
+        log::info!("Get stages of execution ({execution_id})");
+        let stages = sqlx::query_as!(
+            stage_execution::Query,
+            r#"
+            SELECT
+                id AS "id!: i64",
+                execution AS "execution!: i64",
+                stage AS "stage?: i64",
+                position AS "position!: i64",
+                name AS "name!: String",
+                start_time IS NOT NULL AS "started!: bool",
+                status AS "status?: i64",
+                end_time - start_time AS "duration?: i64"
+            FROM stage_executions
+            WHERE execution = $1
+            ORDER BY position
+            "#,
+            execution_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(StageExecution::try_from)
+        .collect::<Result<_, _>>()?;
+        Ok(stages)
+    }
+
+    async fn stage_execution(&self, id: i64) -> Result<StageExecution, Error> {
+        log::info!("Get stage execution ({id})");
+        let stage = sqlx::query_as!(
+            stage_execution::Query,
+            r#"
+            SELECT
+                id AS "id!: i64",
+                execution AS "execution!: i64",
+                stage AS "stage?: i64",
+                position AS "position!: i64",
+                name AS "name!: String",
+                start_time IS NOT NULL AS "started!: bool",
+                status AS "status?: i64",
+                end_time - start_time AS "duration?: i64"
+            FROM stage_executions
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()?;
+        Ok(stage)
+    }
+
+    async fn latest_stage_executions(&self, job_id: i64) -> Result<Vec<StageExecution>, Error> {
+        log::info!("Get stages of latest execution of job ({job_id})");
+        let stages = sqlx::query_as!(
+            stage_execution::Query,
+            r#"
+            SELECT
+                id AS "id!: i64",
+                execution AS "execution!: i64",
+                stage AS "stage?: i64",
+                position AS "position!: i64",
+                name AS "name!: String",
+                start_time IS NOT NULL AS "started!: bool",
+                status AS "status?: i64",
+                end_time - start_time AS "duration?: i64"
+            FROM stage_executions
+            WHERE execution = (
+                SELECT MAX(id)
+                FROM executions
+                WHERE job = $1
+            )
+            ORDER BY position
+            "#,
+            job_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(StageExecution::try_from)
+        .collect::<Result<_, _>>()?;
+        Ok(stages)
+    }
+
+    async fn create_artifacts(
+        &self,
+        execution_id: i64,
+        files: Vec<(String, i64)>,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Insert {} artifacts for execution ({execution_id})",
+            files.len()
+        );
+        let mut transaction = self.pool.begin().await?;
+        for (path, size) in files.iter() {
+            sqlx::query!(
+                "
+                INSERT INTO artifacts
+                (execution, path, size)
+                VALUES ($1, $2, $3)
+                ",
+                execution_id,
+                path,
+                size
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn artifacts(&self, execution_id: i64) -> Result<Vec<Artifact>, Error> {
+        log::info!("Get artifacts for execution ({execution_id})");
+        let artifacts = sqlx::query_as!(
+            Artifact,
+            "
+            SELECT id, execution, path, size
+            FROM artifacts
+            WHERE execution = $1
+            ORDER BY path
+            ",
+            execution_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(artifacts)
+    }
+
+    async fn artifact(&self, id: i64) -> Result<Artifact, Error> {
+        log::info!("Get artifact ({id})");
+        let artifact = sqlx::query_as!(
+            Artifact,
+            "
+            SELECT id, execution, path, size
+            FROM artifacts
+            WHERE id = $1
+            ",
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(artifact)
+    }
+
+    async fn expired_executions(&self, job_id: i64, keep: i64) -> Result<Vec<i64>, Error> {
+        log::info!("Get executions of job ({job_id}) past the {keep} most recent");
+        struct Query {
+            id: i64,
+        }
+        let expired = sqlx::query_as!(
+            Query,
+            r#"
+            SELECT id AS "id!: i64"
+            FROM executions
+            WHERE job = $1
+            ORDER BY id DESC
+            LIMIT -1 OFFSET $2
+            "#,
+            job_id,
+            keep
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|query| query.id)
+        .collect();
+        Ok(expired)
+    }
+
+    async fn delete_artifacts(&self, execution_id: i64) -> Result<(), Error> {
+        log::info!("Delete artifacts of execution ({execution_id})");
+        sqlx::query!(
+            "
+            DELETE FROM artifacts
+            WHERE execution = $1
+            ",
+            execution_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        admin: bool,
+    ) -> Result<i64, Error> {
+        log::info!("Create user ({username})");
+        let id = sqlx::query!(
+            "
+            INSERT INTO users (username, password_hash, admin)
+            VALUES ($1, $2, $3)
+            ",
+            username,
+            password_hash,
+            admin
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn user(&self, id: i64) -> Result<User, Error> {
+        log::info!("Get user ({id})");
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT id AS "id!: i64", username, password_hash, admin AS "admin: bool"
+            FROM users
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(user)
+    }
+
+    async fn user_by_name(&self, username: &str) -> Result<User, Error> {
+        log::info!("Get user ({username})");
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT id AS "id!: i64", username, password_hash, admin AS "admin: bool"
+            FROM users
+            WHERE username = $1
+            "#,
+            username
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(user)
+    }
+
+    async fn create_session(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        lifetime: i64,
+    ) -> Result<(), Error> {
+        log::info!("Create session of user ({user_id})");
+        sqlx::query!(
+            "
+            INSERT INTO sessions (token_hash, user_id, expires_at)
+            VALUES ($1, $2, unixepoch() + $3)
+            ",
+            token_hash,
+            user_id,
+            lifetime
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn session_user(&self, token_hash: &str) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT users.id AS "id!: i64", username, password_hash, admin AS "admin: bool"
+            FROM sessions
+            INNER JOIN users ON sessions.user_id = users.id
+            WHERE token_hash = $1 AND expires_at > unixepoch()
+            "#,
+            token_hash
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(user)
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> Result<(), Error> {
+        log::info!("Delete session");
+        sqlx::query!(
+            "
+            DELETE FROM sessions
+            WHERE token_hash = $1 OR expires_at <= unixepoch()
+            ",
+            token_hash
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        name: &str,
+        service: bool,
+        scope: TokenScope,
+        jobs: &[i64],
+    ) -> Result<i64, Error> {
+        log::info!("Create token ({name}) of user ({user_id})");
+        let scope = scope.key();
+        let jobs = serde_json::to_string(jobs)?;
+        let id = sqlx::query!(
+            "
+            INSERT INTO api_tokens (token_hash, user_id, name, service, scope, jobs, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, unixepoch())
+            ",
+            token_hash,
+            user_id,
+            name,
+            service,
+            scope,
+            jobs
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn tokens(&self) -> Result<Vec<ApiToken>, Error> {
+        log::info!("Get tokens");
+        sqlx::query_as!(
+            tokens::Query,
+            r#"
+            SELECT
+                api_tokens.id AS "id!: i64",
+                name,
+                user_id,
+                username,
+                service AS "service: bool",
+                scope,
+                jobs,
+                created_at,
+                last_used
+            FROM api_tokens
+            INNER JOIN users ON api_tokens.user_id = users.id
+            ORDER BY api_tokens.id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(ApiToken::try_from)
+        .collect()
+    }
+
+    async fn token(&self, id: i64) -> Result<ApiToken, Error> {
+        log::info!("Get token ({id})");
+        sqlx::query_as!(
+            tokens::Query,
+            r#"
+            SELECT
+                api_tokens.id AS "id!: i64",
+                name,
+                user_id,
+                username,
+                service AS "service: bool",
+                scope,
+                jobs,
+                created_at,
+                last_used
+            FROM api_tokens
+            INNER JOIN users ON api_tokens.user_id = users.id
+            WHERE api_tokens.id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()
+    }
+
+    async fn use_token(&self, token_hash: &str) -> Result<ApiToken, Error> {
+        let updated = sqlx::query!(
+            "
+            UPDATE api_tokens
+            SET last_used = unixepoch()
+            WHERE token_hash = $1
+            ",
+            token_hash
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(Error::NotFound);
+        }
+        sqlx::query_as!(
+            tokens::Query,
+            r#"
+            SELECT
+                api_tokens.id AS "id!: i64",
+                name,
+                user_id,
+                username,
+                service AS "service: bool",
+                scope,
+                jobs,
+                created_at,
+                last_used
+            FROM api_tokens
+            INNER JOIN users ON api_tokens.user_id = users.id
+            WHERE token_hash = $1
+            "#,
+            token_hash
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()
+    }
+
+    async fn delete_token(&self, id: i64) -> Result<(), Error> {
+        log::info!("Delete token ({id})");
+        sqlx::query!(
+            "
+            DELETE FROM api_tokens
+            WHERE id = $1
+            ",
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_group(&self, name: &str) -> Result<i64, Error> {
+        log::info!("Create group ({name})");
+        let id = sqlx::query!(
+            "
+            INSERT INTO project_groups (name)
+            VALUES ($1)
+            ",
+            name
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn groups(&self) -> Result<Vec<Group>, Error> {
+        log::info!("Get groups");
+        let groups = sqlx::query_as!(
+            Group,
+            r#"
+            SELECT id AS "id!: i64", name
+            FROM project_groups
+            ORDER BY name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(groups)
+    }
+
+    async fn job_group(&self, job_id: i64) -> Result<Option<i64>, Error> {
+        struct Query {
+            group_id: Option<i64>,
+        }
+        let group = sqlx::query_as!(
+            Query,
+            "
+            SELECT group_id
+            FROM jobs
+            WHERE id = $1
+            ",
+            job_id
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .group_id;
+        Ok(group)
+    }
+
+    async fn job_groups(&self) -> Result<Vec<(i64, Option<i64>)>, Error> {
+        struct Query {
+            id: i64,
+            group_id: Option<i64>,
+        }
+        let groups = sqlx::query_as!(
+            Query,
+            "
+            SELECT id, group_id
+            FROM jobs
+            ORDER BY id
+            "
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|job| (job.id, job.group_id))
+        .collect();
+        Ok(groups)
+    }
+
+    async fn set_job_group(&self, job_id: i64, group_id: Option<i64>) -> Result<(), Error> {
+        log::info!("Set job ({job_id}) group ({group_id:?})");
+        let updated = sqlx::query!(
+            "
+            UPDATE jobs
+            SET group_id = $2
+            WHERE id = $1
+            ",
+            job_id,
+            group_id
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn grant_role(
+        &self,
+        user_id: i64,
+        job_id: Option<i64>,
+        group_id: Option<i64>,
+        role: Role,
+    ) -> Result<i64, Error> {
+        log::info!("Grant {role:?} to user ({user_id}) on job ({job_id:?}) group ({group_id:?})");
+        let role = role.key();
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!(
+            "
+            DELETE FROM grants
+            WHERE user_id = $1 AND job_id IS $2 AND group_id IS $3
+            ",
+            user_id,
+            job_id,
+            group_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        let id = sqlx::query!(
+            "
+            INSERT INTO grants (user_id, job_id, group_id, role)
+            VALUES ($1, $2, $3, $4)
+            ",
+            user_id,
+            job_id,
+            group_id,
+            role
+        )
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_rowid();
+        transaction.commit().await?;
+        Ok(id)
+    }
+
+    async fn grants(&self) -> Result<Vec<Grant>, Error> {
+        log::info!("Get grants");
+        let grants = sqlx::query_as!(
+            grants::Query,
+            r#"
+            SELECT
+                grants.id AS "id!: i64",
+                user_id,
+                username,
+                job_id AS "job?: i64",
+                group_id AS "group?: i64",
+                role
+            FROM grants
+            INNER JOIN users ON grants.user_id = users.id
+            ORDER BY grants.id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(Grant::from)
+        .collect();
+        Ok(grants)
+    }
+
+    async fn user_grants(&self, user_id: i64) -> Result<Vec<Grant>, Error> {
+        let grants = sqlx::query_as!(
+            grants::Query,
+            r#"
+            SELECT
+                grants.id AS "id!: i64",
+                user_id,
+                username,
+                job_id AS "job?: i64",
+                group_id AS "group?: i64",
+                role
+            FROM grants
+            INNER JOIN users ON grants.user_id = users.id
+            WHERE user_id = $1
+            ORDER BY grants.id
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(Grant::from)
+        .collect();
+        Ok(grants)
+    }
+
+    async fn grant(&self, id: i64) -> Result<Grant, Error> {
+        let grant = sqlx::query_as!(
+            grants::Query,
+            r#"
+            SELECT
+                grants.id AS "id!: i64",
+                user_id,
+                username,
+                job_id AS "job?: i64",
+                group_id AS "group?: i64",
+                role
+            FROM grants
+            INNER JOIN users ON grants.user_id = users.id
+            WHERE grants.id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(grant.into())
+    }
+
+    async fn delete_grant(&self, id: i64) -> Result<(), Error> {
+        log::info!("Delete grant ({id})");
+        sqlx::query!(
+            "
+            DELETE FROM grants
+            WHERE id = $1
+            ",
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_webhook(&self, job_id: Option<i64>, url: &str) -> Result<i64, Error> {
+        log::info!("Create webhook of job ({job_id:?})");
+        let id = sqlx::query!(
+            "
+            INSERT INTO webhooks (job_id, url, created_at)
+            VALUES ($1, $2, unixepoch())
+            ",
+            job_id,
+            url
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn webhooks(&self) -> Result<Vec<Webhook>, Error> {
+        log::info!("Get webhooks");
+        let webhooks = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id AS "id!: i64", job_id AS "job?: i64", url, created_at
+            FROM webhooks
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(webhooks)
+    }
+
+    async fn webhook(&self, id: i64) -> Result<Webhook, Error> {
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id AS "id!: i64", job_id AS "job?: i64", url, created_at
+            FROM webhooks
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(webhook)
+    }
+
+    async fn job_webhooks(&self, job_id: i64) -> Result<Vec<Webhook>, Error> {
+        let webhooks = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id AS "id!: i64", job_id AS "job?: i64", url, created_at
+            FROM webhooks
+            WHERE job_id IS NULL OR job_id = $1
+            ORDER BY id
+            "#,
+            job_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(webhooks)
+    }
+
+    async fn delete_webhook(&self, id: i64) -> Result<(), Error> {
+        log::info!("Delete webhook ({id})");
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!(
+            "
+            DELETE FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ",
+            id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query!(
+            "
+            DELETE FROM webhooks
+            WHERE id = $1
+            ",
+            id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn record_delivery(
+        &self,
+        webhook_id: i64,
+        execution_id: Option<i64>,
+        attempts: i64,
+        status_code: Option<i64>,
+        error: Option<&str>,
+    ) -> Result<i64, Error> {
+        let id = sqlx::query!(
+            "
+            INSERT INTO webhook_deliveries
+                (webhook_id, execution_id, attempts, status_code, error, delivered_at)
+            VALUES ($1, $2, $3, $4, $5, unixepoch())
+            ",
+            webhook_id,
+            execution_id,
+            attempts,
+            status_code,
+            error
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn deliveries(&self, webhook_id: i64, limit: i64) -> Result<Vec<Delivery>, Error> {
+        let deliveries = sqlx::query_as!(
+            Delivery,
+            r#"
+            SELECT
+                id AS "id!: i64",
+                webhook_id AS webhook,
+                execution_id AS "execution?: i64",
+                attempts,
+                status_code,
+                error,
+                delivered_at
+            FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY id DESC
+            LIMIT $2
+            "#,
+            webhook_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(deliveries)
+    }
+
+    async fn mail_settings(&self, job_id: i64) -> Result<MailSettings, Error> {
+        sqlx::query_as!(
+            mail::Query,
+            r#"
+            SELECT
+                mail_recipients,
+                mail_commit_author AS "mail_commit_author: bool",
+                mail_every_run AS "mail_every_run: bool"
+            FROM jobs
+            WHERE id = $1
+            "#,
+            job_id
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()
+    }
+
+    async fn set_mail_settings(&self, job_id: i64, settings: &MailSettings) -> Result<(), Error> {
+        log::info!("Set job ({job_id}) mail settings");
+        let recipients = serde_json::to_string(&settings.recipients)?;
+        let updated = sqlx::query!(
+            "
+            UPDATE jobs
+            SET mail_recipients = $2, mail_commit_author = $3, mail_every_run = $4
+            WHERE id = $1
+            ",
+            job_id,
+            recipients,
+            settings.commit_author,
+            settings.every_run
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn check_migrations(&self) -> Result<(), Error> {
+        let applied: Vec<i64> =
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await?;
+        pending_migrations(&MIGRATOR, &applied)
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use crate::{data_path, status_from_code, Error, ExecutionStatus};
+
+pub struct StageExecution {
+    pub id: i64,
+    pub execution: i64,
+    /// Stage definition that was run, if it still exists.
+    pub stage: Option<i64>,
+    pub position: i64,
+    pub name: String,
+    /// `None` until the stage starts.
+    pub status: Option<ExecutionStatus>,
+    pub duration: Option<i64>,
+}
+
+/// Directory where the stage logs of an execution are written.
+pub fn log_directory(execution_id: i64) -> PathBuf {
+    data_path().join("logs").join(execution_id.to_string())
+}
+
+pub fn log_path(stage: &StageExecution) -> PathBuf {
+    log_directory(stage.execution).join(format!("{}.log", stage.position))
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct Query {
+    pub(crate) id: i64,
+    pub(crate) execution: i64,
+    pub(crate) stage: Option<i64>,
+    pub(crate) position: i64,
+    pub(crate) name: String,
+    pub(crate) started: bool,
+    pub(crate) status: Option<i64>,
+    pub(crate) duration: Option<i64>,
+}
+
+impl TryFrom<Query> for StageExecution {
+    type Error = Error;
+
+    fn try_from(query: Query) -> Result<StageExecution, Error> {
+        let Query {
+            id,
+            execution,
+            stage,
+            position,
+            name,
+            started,
+            status,
+            duration,
+        } = query;
+        let status = if started {
+            Some(status_from_code(status)?)
+        } else {
+            None
+        };
+        Ok(StageExecution {
+            id,
+            execution,
+            stage,
+            position,
+            name,
+            status,
+            duration,
+        })
+    }
+}
@@ -0,0 +1,65 @@
+use osprei_data::TokenScope;
+
+use crate::Error;
+
+/// Token for scripts to call the API without a session, only its hash is
+/// stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    /// User who created the token, the bearer of a personal token acts as them.
+    pub user_id: i64,
+    pub username: String,
+    /// Whether the token belongs to a service rather than to the user who
+    /// created it.
+    pub service: bool,
+    pub scope: TokenScope,
+    /// Jobs the token is restricted to, every job when empty.
+    pub jobs: Vec<i64>,
+    pub created_at: i64,
+    pub last_used: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct Query {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) user_id: i64,
+    pub(crate) username: String,
+    pub(crate) service: bool,
+    pub(crate) scope: String,
+    pub(crate) jobs: String,
+    pub(crate) created_at: i64,
+    pub(crate) last_used: Option<i64>,
+}
+
+impl TryFrom<Query> for ApiToken {
+    type Error = Error;
+
+    fn try_from(query: Query) -> Result<ApiToken, Error> {
+        let Query {
+            id,
+            name,
+            user_id,
+            username,
+            service,
+            scope,
+            jobs,
+            created_at,
+            last_used,
+        } = query;
+        Ok(ApiToken {
+            id,
+            name,
+            user_id,
+            username,
+            service,
+            // Unknown scopes grant the least.
+            scope: TokenScope::from_key(&scope).unwrap_or(TokenScope::Read),
+            jobs: serde_json::from_str(&jobs)?,
+            created_at,
+            last_used,
+        })
+    }
+}
@@ -0,0 +1,24 @@
+/// Url notified when an execution of the job finishes, or of any job when `job`
+/// is unset.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct Webhook {
+    pub id: i64,
+    pub job: Option<i64>,
+    pub url: String,
+    pub created_at: i64,
+}
+
+/// Outcome of notifying a webhook, after retrying failed attempts.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook: i64,
+    /// Execution notified about, unset for test notifications.
+    pub execution: Option<i64>,
+    pub attempts: i64,
+    /// Status of the last response, unset when no response came.
+    pub status_code: Option<i64>,
+    /// Why the last attempt failed, unset when the delivery succeeded.
+    pub error: Option<String>,
+    pub delivered_at: i64,
+}
@@ -0,0 +1,672 @@
+//! Behaviour every storage backend must share.
+//!
+//! Runs against the in-memory and SQLite backends, and against PostgreSQL when
+//! `TEST_POSTGRES_URL` points to a database (see `make db-postgres`).
+
+use osprei_data::StageDefinition;
+use osprei_storage::{
+    Error, ExecutionStatus, Filter, MailSettings, MemoryStorage, Role, SharedStorage, TokenScope,
+};
+
+async fn backends(name: &str) -> Vec<SharedStorage> {
+    let path = std::env::temp_dir().join(format!("osprei-{}-{name}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut backends: Vec<SharedStorage> = vec![
+        std::sync::Arc::new(MemoryStorage::new()),
+        osprei_storage::connect(&format!("sqlite:{}", path.display()))
+            .await
+            .unwrap(),
+    ];
+    if let Ok(url) = std::env::var("TEST_POSTGRES_URL") {
+        backends.push(osprei_storage::connect(&url).await.unwrap());
+    }
+    backends
+}
+
+fn stage(name: &str) -> StageDefinition {
+    StageDefinition {
+        name: name.to_string(),
+        image: "rust:latest".to_string(),
+        command: Some("cargo".to_string()),
+        arguments: vec!["test".to_string()],
+        environment: Vec::new(),
+        working_dir: "/workspace/code".to_string(),
+        artifacts: Vec::new(),
+    }
+}
+
+fn for_job(job: i64) -> Filter {
+    Filter {
+        job: Some(job),
+        ..Filter::default()
+    }
+}
+
+#[tokio::test]
+async fn jobs_start_with_a_checkout_stage() {
+    for storage in backends("jobs").await {
+        let job = storage
+            .create_job("https://example.com/repo.git".to_string())
+            .await
+            .unwrap();
+        assert!(storage.job_ids().await.unwrap().contains(&job));
+        assert_eq!(
+            storage.job_source(job).await.unwrap(),
+            "https://example.com/repo.git"
+        );
+        assert_eq!(storage.job_status(job).await.unwrap(), None);
+        assert_eq!(storage.job_last_execution(job).await.unwrap(), None);
+
+        let stages = storage.stages_for_job(job).await.unwrap();
+        assert_eq!(stages.len(), 1);
+        assert_eq!(stages[0].definition.name, "checkout");
+        assert_eq!(stages[0].dependency, None);
+
+        let test = storage
+            .create_stage(job, Some(stages[0].id), &stage("test"))
+            .await
+            .unwrap();
+        let stages = storage.stages_for_job(job).await.unwrap();
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages[1].id, test);
+        assert_eq!(stages[1].dependency, Some(stages[0].id));
+        assert_eq!(stages[1].definition.command.as_deref(), Some("cargo"));
+    }
+}
+
+#[tokio::test]
+async fn executions_track_stage_progress() {
+    for storage in backends("progress").await {
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let checkout = storage.stages_for_job(job).await.unwrap()[0].id;
+        let test = storage
+            .create_stage(job, Some(checkout), &stage("test"))
+            .await
+            .unwrap();
+        let execution = storage.create_execution(job, "manual", None).await.unwrap();
+        storage
+            .create_stage_executions(
+                execution,
+                vec![
+                    (checkout, "checkout".to_string()),
+                    (test, "test".to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.execution_status(execution).await.unwrap(),
+            ExecutionStatus::Queued
+        );
+        storage.set_execution_running(execution).await.unwrap();
+        assert_eq!(
+            storage.execution_status(execution).await.unwrap(),
+            ExecutionStatus::Running
+        );
+
+        let stages = storage.stage_executions(execution).await.unwrap();
+        assert_eq!(stages.len(), 2);
+        assert!(stages.iter().all(|stage| stage.status.is_none()));
+
+        storage.start_stage_execution(execution, 0).await.unwrap();
+        let first = storage.stage_execution(stages[0].id).await.unwrap();
+        assert_eq!(first.status, Some(ExecutionStatus::Running));
+        assert_eq!(first.stage, Some(checkout));
+
+        storage
+            .finish_stage_execution(execution, 0, ExecutionStatus::Success)
+            .await
+            .unwrap();
+        storage.start_stage_execution(execution, 1).await.unwrap();
+        storage
+            .finish_stage_execution(execution, 1, ExecutionStatus::Failure)
+            .await
+            .unwrap();
+        storage
+            .set_execution_commit(execution, "0123abcd")
+            .await
+            .unwrap();
+        storage
+            .finish_execution(execution, ExecutionStatus::Failure)
+            .await
+            .unwrap();
+
+        let stages = storage.latest_stage_executions(job).await.unwrap();
+        assert_eq!(stages[0].status, Some(ExecutionStatus::Success));
+        assert_eq!(stages[1].status, Some(ExecutionStatus::Failure));
+        assert_eq!(stages[1].name, "test");
+        assert!(stages[1].duration.is_some());
+
+        let details = storage.execution(execution).await.unwrap();
+        assert_eq!(details.status, ExecutionStatus::Failure);
+        assert_eq!(details.commit_hash.as_deref(), Some("0123abcd"));
+        assert_eq!(details.triggered_by, "manual");
+        assert_eq!(details.source, "source");
+        let start_time = details.start_time.unwrap();
+        assert!(start_time > 1_700_000_000);
+        assert!(details.end_time.unwrap() >= start_time);
+        assert_eq!(
+            storage.job_status(job).await.unwrap(),
+            Some(ExecutionStatus::Failure)
+        );
+        assert_eq!(
+            storage.job_last_execution(job).await.unwrap(),
+            Some(execution)
+        );
+    }
+}
+
+#[tokio::test]
+async fn executions_are_filtered_and_paged() {
+    for storage in backends("history").await {
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let other = storage.create_job("other".to_string()).await.unwrap();
+        let succeeded = storage.create_execution(job, "manual", None).await.unwrap();
+        storage
+            .set_execution_commit(succeeded, "aaaa1111")
+            .await
+            .unwrap();
+        storage
+            .finish_execution(succeeded, ExecutionStatus::Success)
+            .await
+            .unwrap();
+        let failed = storage.create_execution(job, "manual", None).await.unwrap();
+        storage
+            .finish_execution(failed, ExecutionStatus::Failure)
+            .await
+            .unwrap();
+        let running = storage
+            .create_execution(job, "manual", Some("release"))
+            .await
+            .unwrap();
+        storage.set_execution_running(running).await.unwrap();
+        storage
+            .create_execution(other, "manual", None)
+            .await
+            .unwrap();
+
+        let ids = |executions: Vec<osprei_storage::Execution>| {
+            executions
+                .into_iter()
+                .map(|execution| execution.id)
+                .collect::<Vec<_>>()
+        };
+        let all = storage.list_executions(&for_job(job), 10, 0).await.unwrap();
+        assert_eq!(ids(all), vec![running, failed, succeeded]);
+        assert_eq!(storage.count_executions(&for_job(job)).await.unwrap(), 3);
+        let page = storage.list_executions(&for_job(job), 2, 2).await.unwrap();
+        assert_eq!(ids(page), vec![succeeded]);
+
+        for (status, expected) in [
+            (ExecutionStatus::Success, succeeded),
+            (ExecutionStatus::Failure, failed),
+            (ExecutionStatus::Running, running),
+        ] {
+            let filter = Filter {
+                status: Some(status),
+                ..for_job(job)
+            };
+            let executions = storage.list_executions(&filter, 10, 0).await.unwrap();
+            assert_eq!(ids(executions), vec![expected]);
+        }
+
+        let by_commit = Filter {
+            git_ref: Some("aaaa".to_string()),
+            ..for_job(job)
+        };
+        assert_eq!(storage.count_executions(&by_commit).await.unwrap(), 1);
+        for wildcard in ["%", "_", "aa_a"] {
+            let by_wildcard = Filter {
+                git_ref: Some(wildcard.to_string()),
+                ..for_job(job)
+            };
+            assert_eq!(storage.count_executions(&by_wildcard).await.unwrap(), 0);
+            let executions = storage.list_executions(&by_wildcard, 10, 0).await;
+            assert!(executions.unwrap().is_empty());
+        }
+        let by_ref = Filter {
+            git_ref: Some("release".to_string()),
+            ..for_job(job)
+        };
+        let executions = storage.list_executions(&by_ref, 10, 0).await.unwrap();
+        assert_eq!(ids(executions), vec![running]);
+        let future = Filter {
+            since: Some("2999-01-01".to_string()),
+            ..for_job(job)
+        };
+        assert_eq!(storage.count_executions(&future).await.unwrap(), 0);
+        let past = Filter {
+            until: Some("2000-01-01".to_string()),
+            ..for_job(job)
+        };
+        assert_eq!(storage.count_executions(&past).await.unwrap(), 0);
+        let visible = Filter {
+            jobs: Some(vec![other]),
+            ..Filter::default()
+        };
+        assert_eq!(storage.count_executions(&visible).await.unwrap(), 1);
+        let none = Filter {
+            jobs: Some(Vec::new()),
+            ..Filter::default()
+        };
+        assert!(storage
+            .list_executions(&none, 10, 0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn every_status_is_stored() {
+    for storage in backends("statuses").await {
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        for status in ExecutionStatus::ALL {
+            let execution = storage.create_execution(job, "manual", None).await.unwrap();
+            storage.finish_execution(execution, status).await.unwrap();
+            assert_eq!(storage.execution_status(execution).await.unwrap(), status);
+            let filter = Filter {
+                status: Some(status),
+                ..for_job(job)
+            };
+            let executions = storage.list_executions(&filter, 10, 0).await.unwrap();
+            assert_eq!(executions.len(), 1);
+            assert_eq!(executions[0].id, execution);
+        }
+    }
+}
+
+#[tokio::test]
+async fn unfinished_executions_are_interrupted() {
+    // Every test shares the PostgreSQL database, where this would interrupt their
+    // executions.
+    for storage in backends("interrupted").await.into_iter().take(2) {
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let checkout = storage.stages_for_job(job).await.unwrap()[0].id;
+        let queued = storage.create_execution(job, "manual", None).await.unwrap();
+        let running = storage.create_execution(job, "manual", None).await.unwrap();
+        storage
+            .create_stage_executions(running, vec![(checkout, "checkout".to_string())])
+            .await
+            .unwrap();
+        storage.set_execution_running(running).await.unwrap();
+        storage.start_stage_execution(running, 0).await.unwrap();
+        let finished = storage.create_execution(job, "manual", None).await.unwrap();
+        storage
+            .finish_execution(finished, ExecutionStatus::Success)
+            .await
+            .unwrap();
+
+        assert_eq!(storage.interrupt_executions().await.unwrap(), 2);
+        for execution in [queued, running] {
+            assert_eq!(
+                storage.execution_status(execution).await.unwrap(),
+                ExecutionStatus::Interrupted
+            );
+        }
+        assert_eq!(
+            storage.execution_status(finished).await.unwrap(),
+            ExecutionStatus::Success
+        );
+        let stages = storage.stage_executions(running).await.unwrap();
+        assert_eq!(stages[0].status, Some(ExecutionStatus::Interrupted));
+        assert_eq!(storage.interrupt_executions().await.unwrap(), 0);
+    }
+}
+
+#[tokio::test]
+async fn artifacts_expire_with_old_executions() {
+    for storage in backends("artifacts").await {
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let old = storage.create_execution(job, "manual", None).await.unwrap();
+        let new = storage.create_execution(job, "manual", None).await.unwrap();
+        storage
+            .create_artifacts(
+                old,
+                vec![("target/b.txt".to_string(), 2), ("a.txt".to_string(), 1)],
+            )
+            .await
+            .unwrap();
+
+        let artifacts = storage.artifacts(old).await.unwrap();
+        let paths: Vec<_> = artifacts
+            .iter()
+            .map(|artifact| artifact.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["a.txt", "target/b.txt"]);
+        let artifact = storage.artifact(artifacts[1].id).await.unwrap();
+        assert_eq!((artifact.execution, artifact.size), (old, 2));
+
+        assert_eq!(storage.expired_executions(job, 1).await.unwrap(), vec![old]);
+        assert_eq!(
+            storage.expired_executions(job, 0).await.unwrap(),
+            vec![new, old]
+        );
+        storage.delete_artifacts(old).await.unwrap();
+        assert!(storage.artifacts(old).await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn missing_rows_are_errors() {
+    for storage in backends("missing").await {
+        let not_found = |result: Result<_, Error>| matches!(result, Err(Error::NotFound));
+        assert!(not_found(storage.job_source(i64::MAX).await.map(|_| ())));
+        assert!(not_found(storage.execution(i64::MAX).await.map(|_| ())));
+        assert!(not_found(
+            storage.stage_execution(i64::MAX).await.map(|_| ())
+        ));
+        assert!(not_found(storage.artifact(i64::MAX).await.map(|_| ())));
+        assert!(not_found(storage.template("missing").await.map(|_| ())));
+    }
+}
+
+#[tokio::test]
+async fn sessions_resolve_to_their_user_until_they_expire() {
+    for storage in backends("sessions").await {
+        // Databases may be shared between runs, so the names must be new.
+        let name = format!("user-{}", osprei_storage::users::new_token());
+        let hash = osprei_storage::users::hash_password("hunter2");
+        let id = storage.create_user(&name, &hash, true).await.unwrap();
+        assert!(matches!(
+            storage.create_user(&name, &hash, false).await,
+            Err(Error::AlreadyExists)
+        ));
+        let user = storage.user_by_name(&name).await.unwrap();
+        assert_eq!(user, storage.user(id).await.unwrap());
+        assert!(user.admin);
+        assert!(osprei_storage::users::verify_password(
+            "hunter2",
+            &user.password_hash
+        ));
+        assert!(!osprei_storage::users::verify_password(
+            "hunter3",
+            &user.password_hash
+        ));
+
+        let token = osprei_storage::users::hash_token("token");
+        let expired = osprei_storage::users::hash_token(&osprei_storage::users::new_token());
+        storage.create_session(&token, id, 3600).await.unwrap();
+        storage.create_session(&expired, id, -1).await.unwrap();
+        assert_eq!(storage.session_user(&token).await.unwrap().id, id);
+        assert!(matches!(
+            storage.session_user(&expired).await,
+            Err(Error::NotFound)
+        ));
+        storage.delete_session(&token).await.unwrap();
+        assert!(matches!(
+            storage.session_user(&token).await,
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(
+            storage.user_by_name("missing").await,
+            Err(Error::NotFound)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn api_tokens_are_found_by_hash_and_revoked() {
+    for storage in backends("tokens").await {
+        let name = format!("user-{}", osprei_storage::users::new_token());
+        let user = storage.create_user(&name, "", false).await.unwrap();
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let secret = osprei_storage::users::new_token();
+        let hash = osprei_storage::users::hash_token(&secret);
+        let id = storage
+            .create_token(&hash, user, "deploy", true, TokenScope::Trigger, &[job])
+            .await
+            .unwrap();
+        let personal = osprei_storage::users::hash_token(&osprei_storage::users::new_token());
+        let other = storage
+            .create_token(&personal, user, "laptop", false, TokenScope::Read, &[])
+            .await
+            .unwrap();
+
+        let token = storage.token(id).await.unwrap();
+        assert_eq!(token.name, "deploy");
+        assert_eq!(token.username, name);
+        assert!(token.service);
+        assert_eq!(token.scope, TokenScope::Trigger);
+        assert_eq!(token.jobs, vec![job]);
+        assert_eq!(token.last_used, None);
+        let used = storage.use_token(&hash).await.unwrap();
+        assert_eq!(used.id, id);
+        assert!(used.last_used.is_some());
+        assert!(matches!(
+            storage
+                .use_token(&osprei_storage::users::hash_token("bogus"))
+                .await,
+            Err(Error::NotFound)
+        ));
+
+        let listed: Vec<_> = storage
+            .tokens()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|token| token.user_id == user)
+            .map(|token| token.id)
+            .collect();
+        assert_eq!(listed, vec![other, id]);
+        storage.delete_token(id).await.unwrap();
+        assert!(matches!(
+            storage.use_token(&hash).await,
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(storage.token(id).await, Err(Error::NotFound)));
+    }
+}
+
+#[tokio::test]
+async fn roles_are_granted_on_jobs_and_groups() {
+    for storage in backends("grants").await {
+        let name = format!("user-{}", osprei_storage::users::new_token());
+        let user = storage.create_user(&name, "", false).await.unwrap();
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let group_name = format!("group-{}", osprei_storage::users::new_token());
+        let group = storage.create_group(&group_name).await.unwrap();
+        assert!(matches!(
+            storage.create_group(&group_name).await,
+            Err(Error::AlreadyExists)
+        ));
+        assert!(storage
+            .groups()
+            .await
+            .unwrap()
+            .iter()
+            .any(|found| found.id == group && found.name == group_name));
+
+        assert_eq!(storage.job_group(job).await.unwrap(), None);
+        storage.set_job_group(job, Some(group)).await.unwrap();
+        assert_eq!(storage.job_group(job).await.unwrap(), Some(group));
+        assert!(storage
+            .job_groups()
+            .await
+            .unwrap()
+            .contains(&(job, Some(group))));
+        assert!(matches!(
+            storage.set_job_group(i64::MAX, None).await,
+            Err(Error::NotFound)
+        ));
+
+        storage
+            .grant_role(user, Some(job), None, Role::Viewer)
+            .await
+            .unwrap();
+        // Granting again on the same job replaces the role.
+        let on_job = storage
+            .grant_role(user, Some(job), None, Role::Runner)
+            .await
+            .unwrap();
+        let on_group = storage
+            .grant_role(user, None, Some(group), Role::Maintainer)
+            .await
+            .unwrap();
+        let grants = storage.user_grants(user).await.unwrap();
+        assert_eq!(grants.len(), 2);
+        assert_eq!(grants[0].id, on_job);
+        assert_eq!(grants[0].job, Some(job));
+        assert_eq!(grants[0].role, Role::Runner);
+        assert_eq!(grants[1].group, Some(group));
+        assert_eq!(grants[1].username, name);
+        assert_eq!(storage.grant(on_group).await.unwrap(), grants[1]);
+        assert!(storage
+            .grants()
+            .await
+            .unwrap()
+            .iter()
+            .any(|grant| grant.id == on_group));
+
+        storage.delete_grant(on_job).await.unwrap();
+        assert_eq!(storage.user_grants(user).await.unwrap().len(), 1);
+        assert!(matches!(storage.grant(on_job).await, Err(Error::NotFound)));
+    }
+}
+
+#[tokio::test]
+async fn webhooks_log_their_deliveries() {
+    for storage in backends("webhooks").await {
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let other = storage.create_job("other".to_string()).await.unwrap();
+        let global = storage
+            .create_webhook(None, "http://example.com/all")
+            .await
+            .unwrap();
+        let own = storage
+            .create_webhook(Some(job), "http://example.com/job")
+            .await
+            .unwrap();
+        storage
+            .create_webhook(Some(other), "http://example.com/other")
+            .await
+            .unwrap();
+
+        let webhook = storage.webhook(own).await.unwrap();
+        assert_eq!(webhook.job, Some(job));
+        assert_eq!(webhook.url, "http://example.com/job");
+        let notified: Vec<_> = storage
+            .job_webhooks(job)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|webhook| webhook.id)
+            .filter(|id| *id == global || *id >= own)
+            .collect();
+        assert_eq!(notified, vec![global, own]);
+
+        let execution = storage.create_execution(job, "manual", None).await.unwrap();
+        storage
+            .record_delivery(own, Some(execution), 3, Some(500), Some("server error"))
+            .await
+            .unwrap();
+        let delivered = storage
+            .record_delivery(own, None, 1, Some(200), None)
+            .await
+            .unwrap();
+        let deliveries = storage.deliveries(own, 1).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].id, delivered);
+        assert_eq!(deliveries[0].execution, None);
+        assert_eq!(deliveries[0].error, None);
+        let failed = &storage.deliveries(own, 10).await.unwrap()[1];
+        assert_eq!(failed.execution, Some(execution));
+        assert_eq!((failed.attempts, failed.status_code), (3, Some(500)));
+        assert_eq!(failed.error.as_deref(), Some("server error"));
+
+        storage.delete_webhook(own).await.unwrap();
+        storage.delete_webhook(global).await.unwrap();
+        assert!(matches!(storage.webhook(own).await, Err(Error::NotFound)));
+        assert!(storage.deliveries(own, 10).await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn mail_settings_are_kept_per_job() {
+    for storage in backends("mail").await {
+        let job = storage.create_job("source".to_string()).await.unwrap();
+        let other = storage.create_job("other".to_string()).await.unwrap();
+        assert_eq!(
+            storage.mail_settings(job).await.unwrap(),
+            MailSettings::default()
+        );
+
+        let settings = MailSettings {
+            recipients: vec!["dev@example.com".to_string(), "ops@example.com".to_string()],
+            commit_author: true,
+            every_run: false,
+        };
+        storage.set_mail_settings(job, &settings).await.unwrap();
+        assert_eq!(storage.mail_settings(job).await.unwrap(), settings);
+        assert_eq!(
+            storage.mail_settings(other).await.unwrap(),
+            MailSettings::default()
+        );
+        assert!(matches!(
+            storage.set_mail_settings(-1, &settings).await,
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(
+            storage.mail_settings(-1).await,
+            Err(Error::NotFound)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn missing_migrations_are_reported() {
+    for storage in backends("migrations").await {
+        storage.check_migrations().await.unwrap();
+    }
+
+    let path = std::env::temp_dir().join(format!("osprei-{}-rollback.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let url = format!("sqlite:{}", path.display());
+    let storage = osprei_storage::connect(&url).await.unwrap();
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    let latest: i64 = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = $1")
+        .bind(latest)
+        .execute(&pool)
+        .await
+        .unwrap();
+    match storage.check_migrations().await {
+        Err(Error::PendingMigrations(versions)) => assert_eq!(versions, vec![latest]),
+        _ => panic!("the missing migration was not reported"),
+    }
+}
+
+#[tokio::test]
+async fn unknown_status_codes_are_errors() {
+    let path = std::env::temp_dir().join(format!("osprei-{}-status.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let url = format!("sqlite:{}", path.display());
+    let storage = osprei_storage::connect(&url).await.unwrap();
+    let job = storage.create_job("source".to_string()).await.unwrap();
+    let execution = storage.create_execution(job, "manual", None).await.unwrap();
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    sqlx::query("UPDATE executions SET status = 42 WHERE id = $1")
+        .bind(execution)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(matches!(
+        storage.execution_status(execution).await,
+        Err(Error::UnknownStatus(42))
+    ));
+    assert!(matches!(
+        storage.execution(execution).await,
+        Err(Error::UnknownStatus(42))
+    ));
+    assert!(matches!(
+        storage.list_executions(&for_job(job), 10, 0).await,
+        Err(Error::UnknownStatus(42))
+    ));
+    assert!(matches!(
+        storage.job_status(job).await,
+        Err(Error::UnknownStatus(42))
+    ));
+}